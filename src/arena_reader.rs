@@ -0,0 +1,166 @@
+//! This module contains the `ArenaReader` struct which reads all the packets of a pcap file into a single buffer
+
+use std::borrow::Cow;
+use std::io::Read;
+use std::ops::Range;
+
+use errors::*;
+
+use packet::{Packet, PacketHeader};
+use pcap_header::PcapHeader;
+use reader::PcapReader;
+
+
+/// This struct reads all the packets of a `PcapReader` at once, their payloads packed into a single buffer.
+///
+/// The packets are then yielded as views into this buffer, which avoids one allocation per packet
+/// and keeps the payloads close to each other, like the `PcapSliceReader` does with an in-memory file.
+/// It is meant for the captures which fit in memory: the size of the buffer is capped.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use pcap_file::{ArenaReader, PcapReader};
+///
+/// let file = File::open("test.pcap").expect("Error opening file");
+/// let pcap_reader = PcapReader::new(file).unwrap();
+///
+/// // At most 1 GiB of payloads
+/// let arena = ArenaReader::new(pcap_reader, 1 << 30).unwrap();
+///
+/// for pcap in &arena {
+///     println!("{:?}", pcap);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ArenaReader {
+
+    /// Global header of the file
+    pub header: PcapHeader,
+    arena: Vec<u8>,
+    packets: Vec<(PacketHeader, Range<usize>)>
+}
+
+impl ArenaReader {
+
+    /// Reads all the remaining packets of `reader`, their payloads being copied into an arena of at most `cap` bytes.
+    ///
+    /// # Errors
+    /// Return an error if a packet can't be read, or if the payloads don't fit in `cap` bytes.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{ArenaReader, PcapReader};
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// let arena = ArenaReader::new(pcap_reader, 64 * 1024 * 1024).unwrap();
+    /// println!("{} packets", arena.len());
+    /// ```
+    pub fn new<T: Read>(mut reader: PcapReader<T>, cap: usize) -> ResultChain<ArenaReader> {
+
+        let mut arena = Vec::new();
+        let mut packets = Vec::new();
+
+        while !reader.is_eof()? {
+
+            let packet = reader.locate(|reader| {
+
+                let mut header = reader.read_packet_header()?;
+
+                // Checked before the payload is read, the arena never grows past the cap
+                let len = arena.len() as u64 + u64::from(header.incl_len);
+                if len > cap as u64 {
+                    bail!(ErrorKind::ArenaFull(len, cap));
+                }
+
+                let start = arena.len();
+                reader.read_payload(&mut header, &mut arena)?;
+                Ok((header, start..arena.len()))
+            })?;
+            packets.push(packet);
+        }
+
+        Ok(
+            ArenaReader {
+                header: reader.header,
+                arena,
+                packets
+            }
+        )
+    }
+
+    /// Returns the number of packets.
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// Returns true if there is no packet.
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    /// Returns the buffer holding the payloads of all the packets, one after the other.
+    pub fn arena(&self) -> &[u8] {
+        &self.arena
+    }
+
+    /// Returns the nth packet, if any.
+    pub fn get(&self, n: usize) -> Option<Packet<'_>> {
+
+        let (header, ref range) = *self.packets.get(n)?;
+
+        Some(
+            Packet {
+                header,
+                data: Cow::Borrowed(&self.arena[range.clone()])
+            }
+        )
+    }
+
+    /// Returns an iterator over the packets, borrowing their payloads from the arena.
+    pub fn iter(&self) -> ArenaIter<'_> {
+        ArenaIter { reader: self, next: 0 }
+    }
+}
+
+impl<'a> IntoIterator for &'a ArenaReader {
+
+    type Item = Packet<'a>;
+    type IntoIter = ArenaIter<'a>;
+
+    fn into_iter(self) -> ArenaIter<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over the packets of an `ArenaReader`.
+///
+/// It is created by `ArenaReader::iter`.
+#[derive(Clone, Debug)]
+pub struct ArenaIter<'a> {
+    reader: &'a ArenaReader,
+    next: usize
+}
+
+impl<'a> Iterator for ArenaIter<'a> {
+
+    type Item = Packet<'a>;
+
+    fn next(&mut self) -> Option<Packet<'a>> {
+
+        let packet = self.reader.get(self.next)?;
+        self.next += 1;
+
+        Some(packet)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+
+        let remaining = self.reader.len() - self.next;
+        (remaining, Some(remaining))
+    }
+}
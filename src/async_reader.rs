@@ -0,0 +1,239 @@
+//! This module contains the `AsyncPcapReader` struct which is used to read from an async pcap stream
+
+use std::future::{poll_fn, Future};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use errors::*;
+
+use packet::{Packet, DEFAULT_MAX_PACKET_LEN};
+use pcap_header::PcapHeader;
+
+
+/// This struct wraps an `AsyncRead` and enables it to read a Pcap formated stream without blocking.
+///
+/// It implements the `Stream` trait in order to read one packet at a time.
+/// A packet is only yielded once all of its bytes are available, until then the stream is pending.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use futures::StreamExt;
+/// use pcap_file::AsyncPcapReader;
+///
+/// let file_in = tokio::fs::File::open("test.pcap").await.expect("Error opening file");
+/// let mut pcap_reader = AsyncPcapReader::new(file_in).await.unwrap();
+///
+/// // Read test.pcap
+/// while let Some(pcap) = pcap_reader.next().await {
+///
+///     //Check if there is no error
+///     let pcap = pcap.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AsyncPcapReader<T: AsyncRead + Unpin> {
+
+    pub header: PcapHeader,
+    reader: T,
+    buffer: Vec<u8>,
+    filled: usize,
+    max_packet_len: u32
+}
+
+impl <T: AsyncRead + Unpin> AsyncPcapReader<T> {
+
+    /// Create a new AsyncPcapReader from an existing async reader.
+    /// The returned future reads the global pcap header of the stream to verify its integrity.
+    ///
+    /// # Errors
+    /// Return an error if the data stream is not in a valid pcap file format.
+    /// Or if the underlying data are not readable.
+    ///
+    /// # Examples
+    /// ```rust,ignore
+    /// use pcap_file::AsyncPcapReader;
+    ///
+    /// let file_in = tokio::fs::File::open("test.pcap").await.expect("Error opening file");
+    /// let pcap_reader = AsyncPcapReader::new(file_in).await.unwrap();
+    /// ```
+    pub fn new(reader: T) -> impl Future<Output = ResultChain<AsyncPcapReader<T>>> {
+
+        let mut reader = Some(reader);
+        let mut buffer = [0u8; PcapHeader::SIZE];
+        let mut filled = 0;
+
+        poll_fn(move |cx| {
+
+            let inner = reader.as_mut().expect("AsyncPcapReader::new polled after completion");
+
+            match poll_fill(inner, cx, &mut buffer, &mut filled) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err.into())),
+                Poll::Ready(Ok(false)) => return Poll::Ready(Err(unexpected_eof().into())),
+                Poll::Ready(Ok(true)) => {}
+            }
+
+            let header = PcapHeader::from_reader(&mut &buffer[..])?;
+
+            Poll::Ready(
+                Ok(
+                    AsyncPcapReader {
+                        header,
+                        reader: reader.take().unwrap(),
+                        buffer: Vec::new(),
+                        filled: 0,
+                        max_packet_len: DEFAULT_MAX_PACKET_LEN
+                    }
+                )
+            )
+        })
+    }
+
+    /// Consumes the `AsyncPcapReader`, returning the wrapped reader.
+    ///
+    /// The bytes of a partially read packet are lost.
+    pub fn into_reader(self) -> T {
+        self.reader
+    }
+
+    /// Gets a reference to the underlying reader.
+    ///
+    /// It is not advised to directly read from the underlying reader.
+    pub fn get_ref(&self) -> &T {
+        &self.reader
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// It is not advised to directly read from the underlying reader.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.reader
+    }
+
+    /// Returns the maximum length of the packets read, `DEFAULT_MAX_PACKET_LEN` (256 KiB) by default.
+    pub fn max_packet_len(&self) -> u32 {
+        self.max_packet_len
+    }
+
+    /// Sets the maximum length of the packets read.
+    ///
+    /// A longer packet is refused with a `PacketTooLarge` error instead of being buffered.
+    pub fn set_max_packet_len(&mut self, max_packet_len: u32) {
+        self.max_packet_len = max_packet_len;
+    }
+
+    /// Reads the bytes of the current record until `len` bytes are buffered, the first `header_len` being the packet header
+    fn poll_record(&mut self, cx: &mut Context, header_len: usize, len: usize) -> Poll<ResultChain<()>> {
+
+        if self.buffer.len() < len {
+            self.buffer.resize(len, 0);
+        }
+
+        match poll_fill(&mut self.reader, cx, &mut self.buffer[..len], &mut self.filled) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(true)) => Poll::Ready(Ok(())),
+            Poll::Ready(Ok(false)) => Poll::Ready(Err(ErrorKind::TruncatedPacket(len - header_len, self.filled - header_len).into())),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err.into()))
+        }
+    }
+}
+
+impl <T: AsyncRead + Unpin> Stream for AsyncPcapReader<T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<ResultChain<Packet<'static>>>> {
+
+        let this = self.get_mut();
+
+        // A clean end of stream can only happen between two packets
+        let header_len = this.header.packet_header_len();
+        if this.buffer.len() < header_len {
+            this.buffer.resize(header_len, 0);
+        }
+
+        match poll_fill(&mut this.reader, cx, &mut this.buffer[..header_len], &mut this.filled) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Ok(false)) if this.filled == 0 => return Poll::Ready(None),
+            Poll::Ready(Ok(false)) => {
+                let got = this.filled;
+                this.filled = 0;
+                return Poll::Ready(Some(Err(ErrorKind::TruncatedPacket(header_len, got).into())));
+            },
+            Poll::Ready(Err(err)) => {
+                this.filled = 0;
+                return Poll::Ready(Some(Err(err.into())));
+            },
+            Poll::Ready(Ok(true)) => {}
+        }
+
+        // The whole record is buffered, but the bytes past the effective snaplen are dropped
+        let (header, stored_len) = match this.header.read_record_header(&this.buffer[..header_len]) {
+            Ok((_, stored_len)) if stored_len > this.max_packet_len as usize => {
+                this.filled = 0;
+                return Poll::Ready(Some(Err(ErrorKind::PacketTooLarge(stored_len as u32, this.max_packet_len).into())));
+            },
+            Ok(header) => header,
+            Err(err) => {
+                this.filled = 0;
+                return Poll::Ready(Some(Err(err)));
+            }
+        };
+
+        let len = header_len + stored_len;
+        match this.poll_record(cx, header_len, len) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(err)) => {
+                this.filled = 0;
+                return Poll::Ready(Some(Err(err)));
+            },
+            Poll::Ready(Ok(())) => {}
+        }
+
+        this.filled = 0;
+
+        Poll::Ready(
+            Some(
+                Ok(
+                    Packet {
+                        header,
+                        data: this.buffer[header_len..header_len + header.incl_len as usize].to_vec().into()
+                    }
+                )
+            )
+        )
+    }
+}
+
+/// Polls the reader until `buffer` is full.
+///
+/// Returns false if the end of the stream is reached before.
+fn poll_fill<R: AsyncRead + Unpin>(reader: &mut R, cx: &mut Context, buffer: &mut [u8], filled: &mut usize) -> Poll<IoResult<bool>> {
+
+    while *filled < buffer.len() {
+
+        let mut read_buf = ReadBuf::new(&mut buffer[*filled..]);
+
+        match Pin::new(&mut *reader).poll_read(cx, &mut read_buf) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Ready(Ok(())) => {}
+        }
+
+        match read_buf.filled().len() {
+            0 => return Poll::Ready(Ok(false)),
+            nb_read => *filled += nb_read
+        }
+    }
+
+    Poll::Ready(Ok(true))
+}
+
+fn unexpected_eof() -> IoError {
+    IoError::new(IoErrorKind::UnexpectedEof, "The stream ended in the middle of a record")
+}
@@ -0,0 +1,263 @@
+//! This module contains the `ChecksumWriter` and `ChecksumReader` structs which compute the CRC32 of the packet records
+//! of a pcap file, to detect its corruption.
+
+use std::io::{Read, Result as IoResult, Write};
+
+use crc32fast::Hasher;
+
+use errors::*;
+
+use packet::Packet;
+use pcap_header::PcapHeader;
+use reader::PcapReader;
+use writer::PcapWriter;
+
+
+/// Computes the CRC32 of the bytes written through it
+#[derive(Debug)]
+struct HashWriter<W: Write> {
+    inner: W,
+    hasher: Hasher
+}
+
+impl<W: Write> Write for HashWriter<W> {
+
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+
+        let nb_written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..nb_written]);
+        Ok(nb_written)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.inner.flush()
+    }
+}
+
+/// Computes the CRC32 of the bytes read through it
+#[derive(Debug)]
+struct HashReader<R: Read> {
+    inner: R,
+    hasher: Hasher
+}
+
+impl<R: Read> Read for HashReader<R> {
+
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+
+        let nb_read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..nb_read]);
+        Ok(nb_read)
+    }
+}
+
+/// This struct wraps a `PcapWriter` and computes the CRC32 of the packet records written, global header excluded.
+///
+/// The digest is the big endian CRC32 (IEEE) of the bytes following the global header.
+/// It can be stored in a sidecar file, to be checked with a `ChecksumReader`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use std::io::Write;
+/// use pcap_file::{ChecksumWriter, PcapHeader};
+///
+/// let file = File::create("out.pcap").expect("Error creating file");
+/// let mut checksum_writer = ChecksumWriter::with_header(PcapHeader::default(), file).unwrap();
+///
+/// checksum_writer.write(0, 0, &[0u8; 10]).unwrap();
+///
+/// let digest = checksum_writer.digest().unwrap();
+/// File::create("out.pcap.crc32").unwrap().write_all(&digest).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ChecksumWriter<W: Write> {
+    writer: PcapWriter<HashWriter<W>>
+}
+
+impl<W: Write> ChecksumWriter<W> {
+
+    /// Creates a new `ChecksumWriter` from an existing writer, with the default global header of `PcapWriter::new`.
+    ///
+    /// # Errors
+    /// Return an error if the global header can't be written.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::ChecksumWriter;
+    ///
+    /// let file = File::create("out.pcap").expect("Error creating file");
+    /// let checksum_writer = ChecksumWriter::new(file).unwrap();
+    /// ```
+    pub fn new(writer: W) -> ResultChain<ChecksumWriter<W>> {
+        ChecksumWriter::with_header(PcapHeader::default(), writer)
+    }
+
+    /// Creates a new `ChecksumWriter` from an existing writer with a user defined global header.
+    ///
+    /// # Errors
+    /// Return an error if the global header can't be written.
+    pub fn with_header(header: PcapHeader, writer: W) -> ResultChain<ChecksumWriter<W>> {
+
+        // Unbuffered, so that the digest covers every packet written
+        let mut writer = PcapWriter::with_header_and_capacity(header, HashWriter { inner: writer, hasher: Hasher::new() }, 0)?;
+
+        // Only the packet records are hashed
+        writer.get_mut().hasher = Hasher::new();
+
+        Ok(ChecksumWriter { writer })
+    }
+
+    /// Returns the global header written.
+    pub fn header(&self) -> PcapHeader {
+        self.writer.header
+    }
+
+    /// Writes some raw data, the same way as `PcapWriter::write`.
+    ///
+    /// # Errors
+    /// Return an error if the data can't be written.
+    pub fn write(&mut self, ts_sec: u32, ts_usec: u32, data: &[u8]) -> ResultChain<()> {
+        self.writer.write(ts_sec, ts_usec, data)
+    }
+
+    /// Writes a `Packet`, the same way as `PcapWriter::write_packet`.
+    ///
+    /// # Errors
+    /// Return an error if the packet is invalid or can't be written.
+    pub fn write_packet(&mut self, packet: &Packet) -> ResultChain<()> {
+        self.writer.write_packet(packet)
+    }
+
+    /// Returns the digest of the packet records written so far.
+    pub fn current_digest(&self) -> [u8; 4] {
+        self.writer.get_ref().hasher.clone().finalize().to_be_bytes()
+    }
+
+    /// Consumes the `ChecksumWriter`, flushing the wrapped writer and returning the digest of the packet records.
+    ///
+    /// # Errors
+    /// Return an error if the underlying writer can't be flushed.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use pcap_file::ChecksumWriter;
+    ///
+    /// let mut checksum_writer = ChecksumWriter::new(Vec::new()).unwrap();
+    /// checksum_writer.write(0, 0, &[0u8; 10]).unwrap();
+    ///
+    /// println!("CRC32: {:02x?}", checksum_writer.digest().unwrap());
+    /// ```
+    pub fn digest(self) -> ResultChain<[u8; 4]> {
+        self.into_writer().map(|(_, digest)| digest)
+    }
+
+    /// Consumes the `ChecksumWriter`, flushing and returning the wrapped writer with the digest of the packet records.
+    ///
+    /// # Errors
+    /// Return an error if the underlying writer can't be flushed.
+    pub fn into_writer(self) -> ResultChain<(W, [u8; 4])> {
+
+        let writer = self.writer.into_writer()?;
+        Ok((writer.inner, writer.hasher.finalize().to_be_bytes()))
+    }
+}
+
+/// This struct wraps a `PcapReader` and computes the CRC32 of the packet records read, like a `ChecksumWriter`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::fs::{self, File};
+/// use pcap_file::ChecksumReader;
+///
+/// let file = File::open("out.pcap").expect("Error opening file");
+/// let mut checksum_reader = ChecksumReader::new(file).unwrap();
+///
+/// for pcap in &mut checksum_reader {
+///     let pcap = pcap.unwrap();
+/// }
+///
+/// let digest = fs::read("out.pcap.crc32").unwrap();
+/// checksum_reader.verify(&digest).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ChecksumReader<T: Read> {
+    reader: PcapReader<HashReader<T>>
+}
+
+impl<T: Read> ChecksumReader<T> {
+
+    /// Creates a new `ChecksumReader` from an existing reader.
+    ///
+    /// # Errors
+    /// Return an error if the data stream is not in a valid pcap file format.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::ChecksumReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let checksum_reader = ChecksumReader::new(file).unwrap();
+    /// ```
+    pub fn new(mut reader: T) -> ResultChain<ChecksumReader<T>> {
+
+        // The global header is read before the hashing starts
+        let header = PcapHeader::from_reader(&mut reader)?;
+        let reader = PcapReader::from_parts(HashReader { inner: reader, hasher: Hasher::new() }, header)?;
+
+        Ok(ChecksumReader { reader })
+    }
+
+    /// Returns the global header read.
+    pub fn header(&self) -> PcapHeader {
+        self.reader.header
+    }
+
+    /// Returns the digest of the bytes read so far from the wrapped reader.
+    ///
+    /// It is the digest of all the packet records once the iteration has ended.
+    pub fn current_digest(&self) -> [u8; 4] {
+        self.reader.get_ref().hasher.clone().finalize().to_be_bytes()
+    }
+
+    /// Consumes the `ChecksumReader`, reading the remaining packets and comparing the digest of the packet records
+    /// with `expected`.
+    ///
+    /// # Errors
+    /// Return an error if a packet can't be read or if the digests differ.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::{self, File};
+    /// use pcap_file::ChecksumReader;
+    ///
+    /// let checksum_reader = ChecksumReader::new(File::open("out.pcap").unwrap()).unwrap();
+    /// checksum_reader.verify(&fs::read("out.pcap.crc32").unwrap()).unwrap();
+    /// ```
+    pub fn verify(mut self, expected: &[u8]) -> ResultChain<()> {
+
+        for packet in &mut self {
+            packet?;
+        }
+
+        let digest = self.current_digest();
+        if expected != digest {
+            bail!(ErrorKind::ChecksumMismatch(expected.to_vec(), digest.to_vec()));
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Read> Iterator for ChecksumReader<T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+        self.reader.next()
+    }
+}
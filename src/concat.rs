@@ -0,0 +1,45 @@
+//! This module contains the `concat_bytes` function which concatenates two in-memory pcap files
+
+use errors::*;
+
+use reader::PcapReader;
+use writer::{check_compatible, PcapWriter};
+
+
+/// Concatenates two in-memory pcap files: the packets of `b` are appended after the ones of `a`, under a single global header.
+///
+/// The global header of `a` is kept, its snaplen raised to the one of `b` if it is lower, so that no packet is truncated.
+/// The packet records are copied as they are encoded, see `PcapWriter::copy_from`. This is the in-memory counterpart
+/// of the `MultiFileReader`.
+///
+/// # Errors
+/// Return an `IncompatibleHeaders` error if the files don't use the same datalink, endianness, timestamp resolution
+/// and packet header format, or an error if one of them is not a valid pcap file.
+///
+/// # Examples
+/// ```rust,no_run
+/// use pcap_file::concat_bytes;
+///
+/// let a = std::fs::read("a.pcap").expect("Error reading file");
+/// let b = std::fs::read("b.pcap").expect("Error reading file");
+///
+/// let both = concat_bytes(&a, &b).unwrap();
+/// ```
+pub fn concat_bytes(a: &[u8], b: &[u8]) -> ResultChain<Vec<u8>> {
+
+    let reader_a = PcapReader::new(a)?;
+    let reader_b = PcapReader::new(b)?;
+
+    check_compatible(&reader_b.header, &reader_a.header)?;
+
+    let mut header = reader_a.header;
+    if reader_b.header.effective_snaplen() > header.effective_snaplen() {
+        header.snaplen = reader_b.header.snaplen;
+    }
+
+    let mut writer = PcapWriter::with_header(header, Vec::with_capacity(a.len() + b.len()))?;
+    writer.copy_from(reader_a)?;
+    writer.copy_from(reader_b)?;
+
+    writer.into_writer()
+}
@@ -0,0 +1,114 @@
+//! This module contains the `diff` function which compares the packets of two pcap files
+
+use std::io::Read;
+
+use errors::*;
+
+use reader::PcapReader;
+
+
+/// Difference between two packets at the same index, found by `diff`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffEntry {
+
+    /// Index of the packet in both files, starting at 0
+    pub index: u64,
+
+    /// How the packets differ
+    pub reason: DiffReason
+}
+
+/// Describes how two packets at the same index differ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffReason {
+
+    /// The payloads have different lengths, the ones of the first and the second file
+    LengthMismatch(usize, usize),
+
+    /// The payloads have the same length but different bytes
+    PayloadMismatch,
+
+    /// The first file has more packets, this one has no counterpart in the second file
+    OnlyInA,
+
+    /// The second file has more packets, this one has no counterpart in the first file
+    OnlyInB
+}
+
+/// Iterator over the differences between the packets of two `PcapReader`.
+///
+/// It is created by `diff`.
+#[derive(Debug)]
+pub struct Diff<A: Read, B: Read> {
+    a: PcapReader<A>,
+    b: PcapReader<B>,
+    index: u64,
+    done: bool
+}
+
+impl <A:Read, B:Read> Iterator for Diff<A, B> {
+
+    type Item = ResultChain<DiffEntry>;
+
+    fn next(&mut self) -> Option<ResultChain<DiffEntry>> {
+
+        while !self.done {
+
+            let index = self.index;
+            self.index += 1;
+
+            let reason = match (self.a.next(), self.b.next()) {
+
+                (None, None) => {
+                    self.done = true;
+                    return None;
+                },
+                (Some(Err(err)), _) | (_, Some(Err(err))) => {
+                    self.done = true;
+                    return Some(Err(err));
+                },
+                (Some(Ok(_)), None) => DiffReason::OnlyInA,
+                (None, Some(Ok(_))) => DiffReason::OnlyInB,
+                (Some(Ok(a)), Some(Ok(b))) => {
+
+                    if a.data.len() != b.data.len() {
+                        DiffReason::LengthMismatch(a.data.len(), b.data.len())
+                    }
+                    else if a.data != b.data {
+                        DiffReason::PayloadMismatch
+                    }
+                    else {
+                        continue;
+                    }
+                }
+            };
+
+            return Some(Ok(DiffEntry { index, reason }));
+        }
+
+        None
+    }
+}
+
+/// Compares the packets of two pcap files, aligned by index, and yields the ones which differ.
+///
+/// Only the payloads are compared, the timestamps are ignored.
+/// The packets past the end of the shorter file are reported as `OnlyInA` or `OnlyInB`.
+/// The iteration ends at the first error of either reader.
+///
+/// # Examples
+/// ```rust,no_run
+/// use std::fs::File;
+/// use pcap_file::{diff, PcapReader};
+///
+/// let before = PcapReader::new(File::open("before.pcap").unwrap()).unwrap();
+/// let after = PcapReader::new(File::open("after.pcap").unwrap()).unwrap();
+///
+/// for entry in diff(before, after) {
+///     let entry = entry.unwrap();
+///     println!("Packet {}: {:?}", entry.index, entry.reason);
+/// }
+/// ```
+pub fn diff<A: Read, B: Read>(a: PcapReader<A>, b: PcapReader<B>) -> Diff<A, B> {
+    Diff { a, b, index: 0, done: false }
+}
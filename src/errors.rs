@@ -1,5 +1,8 @@
 //! Contains `error_chain` error handling materials
 
+// error_chain 0.10 still relies on the deprecated `description` and `cause` methods
+#![allow(deprecated)]
+
 error_chain! {
     types {
         Error, ErrorKind, ResultExt, ResultChain;
@@ -13,14 +16,98 @@ error_chain! {
             display("{}", cause)
         }
 
+        /// The magic number of the global header is not the one of a pcap file.
+        ///
+        /// The message tells whether it is the start of a pcapng file or of a compressed file.
+        UnknownMagic(got: u32) {
+            description("Not a pcap file")
+            display("Not a pcap file: got magic {:#010x}{}", got, ::format::magic_hint(*got))
+        }
+
         /// Not enough space in the buffer to read the requested bytes
         BufferUnderflow(requested: u64, available: u64) {
             description("Buffer underflow")
             display("Buffer underflow: {} requested, {} available", requested, available)
         }
+
+        /// The requested packet is not in the `PacketIndex`
+        PacketIndexOutOfRange(index: usize, len: usize) {
+            description("Packet index out of range")
+            display("Packet index out of range: packet {} requested, {} indexed", index, len)
+        }
+
+        /// The data stream ends in the middle of a packet
+        TruncatedPacket(expected: usize, got: usize) {
+            description("Truncated packet")
+            display("Truncated packet: {} bytes expected, {} available", expected, got)
+        }
+
+        /// The packet is longer than the maximum packet length of the reader
+        PacketTooLarge(len: u32, max: u32) {
+            description("Packet too large")
+            display("Packet too large: {} bytes, the maximum is {} bytes", len, max)
+        }
+
+        /// The payloads of the packets don't fit in the arena of an `ArenaReader`
+        ArenaFull(len: u64, cap: usize) {
+            description("Arena full")
+            display("Arena full: {} bytes of payloads, the arena holds at most {} bytes", len, cap)
+        }
+
+        /// The capture filter expression can't be compiled
+        InvalidFilter(expr: String, cause: String) {
+            description("Invalid filter expression")
+            display("Invalid filter expression \"{}\": {}", expr, cause)
+        }
+
+        /// The snaplen of the global header is 0 in strict mode, or greater than the maximum snaplen
+        InvalidSnaplen(snaplen: u32, max: u32) {
+            description("Invalid snaplen")
+            display("Invalid snaplen: {}, it must be between 1 and {}", snaplen, max)
+        }
+
+        /// The global headers of several pcap files can't be combined
+        IncompatibleHeaders(cause: String) {
+            description("Incompatible pcap headers")
+            display("Incompatible pcap headers: {}", cause)
+        }
+
+        /// No packet was written by a `PcapWriter` built with `error_if_empty`
+        EmptyCapture {
+            description("Empty capture")
+            display("Empty capture: no packet was written")
+        }
+
+        /// The digest of the packet records read is not the expected one
+        ChecksumMismatch(expected: Vec<u8>, got: Vec<u8>) {
+            description("Checksum mismatch")
+            display("Checksum mismatch: {:02x?} expected, got {:02x?}", expected, got)
+        }
+
+        /// A packet read by `PcapReader` can't be read, the error which occurred is chained.
+        ///
+        /// The index is the one of the packet in the file, None if it isn't known because the reader was moved
+        /// to an arbitrary offset. The offset is the one of the packet header, see `PcapReader::offset`.
+        ReadPacket(index: Option<u64>, offset: u64) {
+            description("Can't read a packet")
+            display("Can't read packet {}at offset {:#x}", index.map_or(String::new(), |index| format!("{} ", index)), offset)
+        }
     }
 
     foreign_links {
         Io(::std::io::Error);// #[doc = "std::io::Error"];
     }
  }
+
+impl Error {
+
+    /// Returns the kind of the error at the root of the chain, such as the `TruncatedPacket`
+    /// behind a `ReadPacket` error.
+    pub fn root_kind(&self) -> &ErrorKind {
+
+        match self.1.next_error.as_ref().and_then(|err| err.downcast_ref::<Error>()) {
+            Some(cause) => cause.root_kind(),
+            None => self.kind()
+        }
+    }
+}
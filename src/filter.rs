@@ -0,0 +1,525 @@
+//! This module contains the `Filter` struct which selects packets with a capture filter expression.
+//!
+//! The expressions use the libpcap filter syntax, of which a subset is supported:
+//!
+//! * the protocols `ip`, `ip6`, `arp`, `tcp`, `udp`, `icmp` and `icmp6`
+//! * `[src|dst] host <address>` with an IPv4 or IPv6 address
+//! * `[src|dst] net <address>/<len>` with an IPv4 network
+//! * `[src|dst] port <port>` on TCP, UDP and SCTP
+//! * `less <length>` and `greater <length>`, which compare the original length of the packet
+//! * the operators `and`/`&&`, `or`/`||`, `not`/`!` and parentheses
+//!
+//! A protocol can qualify the following primitive, as in `tcp port 443` or `ip6 host ::1`.
+//!
+//! The expression is parsed and checked once, then evaluated against the bytes of each packet.
+//! It is not compiled to BPF bytecode, no libpcap is needed.
+
+use std::iter::Peekable;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::vec::IntoIter;
+
+use errors::*;
+
+use packet::Packet;
+use pcap_header::DataLink;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+
+const IPPROTO_ICMP: u8 = 1;
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+const IPPROTO_ICMPV6: u8 = 58;
+const IPPROTO_SCTP: u8 = 132;
+
+
+/// Represents a compiled capture filter expression.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use pcap_file::PcapReader;
+/// use pcap_file::filter::Filter;
+///
+/// let file = File::open("test.pcap").expect("Error opening file");
+/// let pcap_reader = PcapReader::new(file).unwrap();
+///
+/// let filter = Filter::new("tcp port 443", pcap_reader.header.datalink).unwrap();
+///
+/// for pcap in pcap_reader {
+///
+///     let pcap = pcap.unwrap();
+///     if filter.matches(&pcap) {
+///         println!("{} bytes", pcap.data.len());
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Filter {
+    datalink: DataLink,
+    expr: Option<Expr>
+}
+
+impl Filter {
+
+    /// Compiles a filter expression for the packets of the given datalink type.
+    ///
+    /// An empty expression matches every packet.
+    ///
+    /// # Errors
+    /// Return an `InvalidFilter` error if the expression can't be parsed or if the datalink type isn't supported.
+    /// The supported datalinks are `ETHERNET`, `LINUX_SLL`, `LINUX_SLL2`, `RAW`, `IPV4`, `IPV6`, `NULL` and `LOOP`.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use pcap_file::DataLink;
+    /// use pcap_file::filter::Filter;
+    ///
+    /// let filter = Filter::new("udp and not port 53", DataLink::ETHERNET).unwrap();
+    /// assert!(Filter::new("udp and", DataLink::ETHERNET).is_err());
+    /// ```
+    pub fn new(expr: &str, datalink: DataLink) -> ResultChain<Filter> {
+
+        let invalid = |cause: String| ErrorKind::InvalidFilter(expr.to_string(), cause);
+
+        match datalink {
+            DataLink::ETHERNET | DataLink::LINUX_SLL | DataLink::LINUX_SLL2 | DataLink::RAW | DataLink::IPV4 |
+            DataLink::IPV6 | DataLink::NULL | DataLink::LOOP => {},
+            _ => bail!(invalid(format!("datalink {:?} is not supported", datalink)))
+        }
+
+        let mut parser = Parser {
+            tokens: tokenize(expr).into_iter().peekable()
+        };
+
+        if parser.tokens.peek().is_none() {
+            return Ok(Filter { datalink, expr: None });
+        }
+
+        let parsed = parser.parse_or().map_err(invalid)?;
+        if let Some(token) = parser.tokens.next() {
+            bail!(invalid(format!("unexpected '{}'", token)));
+        }
+
+        Ok(
+            Filter {
+                datalink,
+                expr: Some(parsed)
+            }
+        )
+    }
+
+    /// Returns true if the packet matches the filter expression.
+    ///
+    /// A packet too short to be decoded only matches the primitives which don't need the missing bytes.
+    pub fn matches(&self, packet: &Packet) -> bool {
+
+        match self.expr {
+            Some(ref expr) => expr.eval(&Layers::decode(self.datalink, &packet.data), packet.header.orig_len),
+            None => true
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Dir {
+    Src,
+    Dst,
+    Any
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Proto {
+    Ip,
+    Ip6,
+    Arp,
+    Tcp,
+    Udp,
+    Icmp,
+    Icmp6
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Proto(Proto),
+    Host(Dir, IpAddr),
+    Net(Dir, Ipv4Addr, u32),
+    Port(Dir, u16),
+    Less(u32),
+    Greater(u32)
+}
+
+impl Expr {
+
+    fn eval(&self, layers: &Layers, orig_len: u32) -> bool {
+
+        match *self {
+            Expr::And(ref left, ref right) => left.eval(layers, orig_len) && right.eval(layers, orig_len),
+            Expr::Or(ref left, ref right) => left.eval(layers, orig_len) || right.eval(layers, orig_len),
+            Expr::Not(ref expr) => !expr.eval(layers, orig_len),
+            Expr::Proto(proto) => match proto {
+                Proto::Ip => layers.ethertype == Some(ETHERTYPE_IPV4),
+                Proto::Ip6 => layers.ethertype == Some(ETHERTYPE_IPV6),
+                Proto::Arp => layers.ethertype == Some(ETHERTYPE_ARP),
+                Proto::Tcp => layers.ip.as_ref().map_or(false, |ip| ip.proto == IPPROTO_TCP),
+                Proto::Udp => layers.ip.as_ref().map_or(false, |ip| ip.proto == IPPROTO_UDP),
+                Proto::Icmp => layers.ip.as_ref().map_or(false, |ip| ip.src.is_ipv4() && ip.proto == IPPROTO_ICMP),
+                Proto::Icmp6 => layers.ip.as_ref().map_or(false, |ip| ip.src.is_ipv6() && ip.proto == IPPROTO_ICMPV6)
+            },
+            Expr::Host(dir, addr) => layers.ip.as_ref().map_or(false, |ip| dir.test(ip.src == addr, ip.dst == addr)),
+            Expr::Net(dir, net, mask) => layers.ip.as_ref().map_or(false, |ip| {
+
+                let in_net = |addr: IpAddr| match addr {
+                    IpAddr::V4(addr) => u32::from(addr) & mask == u32::from(net),
+                    IpAddr::V6(_) => false
+                };
+
+                dir.test(in_net(ip.src), in_net(ip.dst))
+            }),
+            Expr::Port(dir, port) => layers.ports().map_or(false, |(src, dst)| dir.test(src == port, dst == port)),
+            Expr::Less(len) => orig_len <= len,
+            Expr::Greater(len) => orig_len >= len
+        }
+    }
+}
+
+impl Dir {
+
+    fn test(self, src: bool, dst: bool) -> bool {
+
+        match self {
+            Dir::Src => src,
+            Dir::Dst => dst,
+            Dir::Any => src || dst
+        }
+    }
+}
+
+/// Network and transport layers of a packet, as far as they could be decoded
+struct Layers<'a> {
+    ethertype: Option<u16>,
+    ip: Option<IpLayer<'a>>
+}
+
+struct IpLayer<'a> {
+    src: IpAddr,
+    dst: IpAddr,
+    proto: u8,
+
+    /// None for the non-first fragments, which don't start with the transport header
+    transport: Option<&'a [u8]>
+}
+
+impl<'a> Layers<'a> {
+
+    fn decode(datalink: DataLink, data: &'a [u8]) -> Layers<'a> {
+
+        let (ethertype, network) = match network_layer(datalink, data) {
+            Some((ethertype, network)) => (ethertype, network),
+            None => return Layers { ethertype: None, ip: None }
+        };
+
+        let ip = match ethertype {
+            ETHERTYPE_IPV4 => decode_ipv4(network),
+            ETHERTYPE_IPV6 => decode_ipv6(network),
+            _ => None
+        };
+
+        Layers {
+            ethertype: Some(ethertype),
+            ip
+        }
+    }
+
+    fn ports(&self) -> Option<(u16, u16)> {
+
+        let ip = self.ip.as_ref()?;
+        match ip.proto {
+            IPPROTO_TCP | IPPROTO_UDP | IPPROTO_SCTP => {},
+            _ => return None
+        }
+
+        let transport = ip.transport?;
+        if transport.len() < 4 {
+            return None;
+        }
+
+        Some((read_u16(transport, 0), read_u16(transport, 2)))
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from(data[offset]) << 8 | u16::from(data[offset + 1])
+}
+
+/// Returns the ethertype of the network layer and its bytes
+fn network_layer(datalink: DataLink, data: &[u8]) -> Option<(u16, &[u8])> {
+
+    match datalink {
+        DataLink::ETHERNET if data.len() >= 14 => Some((read_u16(data, 12), &data[14..])),
+        DataLink::LINUX_SLL if data.len() >= 16 => Some((read_u16(data, 14), &data[16..])),
+        DataLink::LINUX_SLL2 if data.len() >= 20 => Some((read_u16(data, 0), &data[20..])),
+        DataLink::RAW | DataLink::IPV4 | DataLink::IPV6 if !data.is_empty() => match data[0] >> 4 {
+            4 => Some((ETHERTYPE_IPV4, data)),
+            6 => Some((ETHERTYPE_IPV6, data)),
+            _ => None
+        },
+        DataLink::NULL | DataLink::LOOP if data.len() >= 4 => {
+
+            // The family is in host byte order for NULL, try both
+            let family = match (data[0], data[3]) {
+                (0, family) | (family, 0) => family,
+                _ => return None
+            };
+
+            match family {
+                2 => Some((ETHERTYPE_IPV4, &data[4..])),
+                24 | 28 | 30 => Some((ETHERTYPE_IPV6, &data[4..])),
+                _ => None
+            }
+        },
+        _ => None
+    }
+}
+
+fn decode_ipv4(data: &[u8]) -> Option<IpLayer<'_>> {
+
+    if data.len() < 20 || data[0] >> 4 != 4 {
+        return None;
+    }
+
+    let header_len = usize::from(data[0] & 0x0f) * 4;
+    let fragment_offset = read_u16(data, 6) & 0x1fff;
+
+    let transport = if fragment_offset == 0 && header_len >= 20 && data.len() >= header_len {
+        Some(&data[header_len..])
+    }
+    else {
+        None
+    };
+
+    Some(
+        IpLayer {
+            src: IpAddr::V4(Ipv4Addr::new(data[12], data[13], data[14], data[15])),
+            dst: IpAddr::V4(Ipv4Addr::new(data[16], data[17], data[18], data[19])),
+            proto: data[9],
+            transport
+        }
+    )
+}
+
+fn decode_ipv6(data: &[u8]) -> Option<IpLayer<'_>> {
+
+    if data.len() < 40 || data[0] >> 4 != 6 {
+        return None;
+    }
+
+    let mut src = [0u8; 16];
+    let mut dst = [0u8; 16];
+    src.copy_from_slice(&data[8..24]);
+    dst.copy_from_slice(&data[24..40]);
+
+    // The extension headers are not followed, like libpcap does for `port`
+    Some(
+        IpLayer {
+            src: IpAddr::V6(Ipv6Addr::from(src)),
+            dst: IpAddr::V6(Ipv6Addr::from(dst)),
+            proto: data[6],
+            transport: Some(&data[40..])
+        }
+    )
+}
+
+/// Splits an expression into words, parentheses and operators
+fn tokenize(expr: &str) -> Vec<String> {
+
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+
+    while let Some(c) = chars.next() {
+
+        match c {
+            c if c.is_whitespace() => {},
+            '(' | ')' | '!' => tokens.push(c.to_string()),
+            '&' | '|' if chars.peek() == Some(&c) => {
+                chars.next();
+                tokens.push(format!("{}{}", c, c));
+            },
+            _ => {
+
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()!&|".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+
+                tokens.push(word);
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Recursive descent parser of the filter expressions, `not` binds tighter than `and` which binds tighter than `or`
+struct Parser {
+    tokens: Peekable<IntoIter<String>>
+}
+
+impl Parser {
+
+    fn next_is(&mut self, words: &[&str]) -> bool {
+
+        match self.tokens.peek() {
+            Some(token) if words.contains(&token.as_str()) => {
+                self.tokens.next();
+                true
+            },
+            _ => false
+        }
+    }
+
+    fn expect_value(&mut self, keyword: &str) -> Result<String, String> {
+        self.tokens.next().ok_or_else(|| format!("'{}' expects a value", keyword))
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+
+        let mut expr = self.parse_and()?;
+        while self.next_is(&["or", "||"]) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+
+        let mut expr = self.parse_not()?;
+        while self.next_is(&["and", "&&"]) {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, String> {
+
+        if self.next_is(&["not", "!"]) {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+
+        if self.next_is(&["("]) {
+
+            let expr = self.parse_or()?;
+            if !self.next_is(&[")"]) {
+                return Err("missing ')'".to_string());
+            }
+
+            return Ok(expr);
+        }
+
+        self.parse_primitive()
+    }
+
+    fn parse_primitive(&mut self) -> Result<Expr, String> {
+
+        let token = self.tokens.next().ok_or_else(|| "unexpected end of expression".to_string())?;
+
+        let proto = match token.as_str() {
+            "ip" => Some(Proto::Ip),
+            "ip6" => Some(Proto::Ip6),
+            "arp" => Some(Proto::Arp),
+            "tcp" => Some(Proto::Tcp),
+            "udp" => Some(Proto::Udp),
+            "icmp" => Some(Proto::Icmp),
+            "icmp6" => Some(Proto::Icmp6),
+            _ => None
+        };
+
+        if let Some(proto) = proto {
+
+            // A protocol qualifies the primitive which follows it
+            let qualified = match self.tokens.peek().map(String::as_str) {
+                Some("src") | Some("dst") | Some("host") | Some("net") | Some("port") => self.parse_primitive()?,
+                _ => return Ok(Expr::Proto(proto))
+            };
+
+            return Ok(Expr::And(Box::new(Expr::Proto(proto)), Box::new(qualified)));
+        }
+
+        match token.as_str() {
+            "src" => self.parse_qualified(Dir::Src),
+            "dst" => self.parse_qualified(Dir::Dst),
+            "host" | "net" | "port" => self.parse_id(Dir::Any, &token),
+            "less" => Ok(Expr::Less(parse_number(&self.expect_value("less")?)?)),
+            "greater" => Ok(Expr::Greater(parse_number(&self.expect_value("greater")?)?)),
+            _ => Err(format!("unknown primitive '{}'", token))
+        }
+    }
+
+    fn parse_qualified(&mut self, dir: Dir) -> Result<Expr, String> {
+
+        match self.tokens.next() {
+            Some(ref keyword) if keyword == "host" || keyword == "net" || keyword == "port" => self.parse_id(dir, keyword),
+            Some(token) => Err(format!("expected 'host', 'net' or 'port' after the direction, found '{}'", token)),
+            None => Err("expected 'host', 'net' or 'port' after the direction".to_string())
+        }
+    }
+
+    fn parse_id(&mut self, dir: Dir, keyword: &str) -> Result<Expr, String> {
+
+        let value = self.expect_value(keyword)?;
+
+        match keyword {
+            "host" => value.parse::<IpAddr>()
+                .map(|addr| Expr::Host(dir, addr))
+                .map_err(|_| format!("'{}' is not an IP address", value)),
+            "net" => parse_net(&value).map(|(net, mask)| Expr::Net(dir, net, mask)),
+            _ => {
+
+                let port = parse_number(&value)?;
+                if port > u32::from(u16::MAX) {
+                    return Err(format!("port {} out of range", port));
+                }
+
+                Ok(Expr::Port(dir, port as u16))
+            }
+        }
+    }
+}
+
+fn parse_number(value: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("'{}' is not a number", value))
+}
+
+/// Parses an IPv4 network `a.b.c.d/len`, the length defaults to 32
+fn parse_net(value: &str) -> Result<(Ipv4Addr, u32), String> {
+
+    let mut parts = value.splitn(2, '/');
+    let addr = parts.next().unwrap_or("");
+    let len = match parts.next() {
+        Some(len) => parse_number(len)?,
+        None => 32
+    };
+
+    let addr: Ipv4Addr = addr.parse().map_err(|_| format!("'{}' is not an IPv4 network", value))?;
+    if len > 32 {
+        return Err(format!("'{}' has a prefix length greater than 32", value));
+    }
+
+    let mask = if len == 0 { 0 } else { u32::MAX << (32 - len) };
+    if u32::from(addr) & !mask != 0 {
+        return Err(format!("'{}' has non-network bits set", value));
+    }
+
+    Ok((addr, mask))
+}
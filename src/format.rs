@@ -0,0 +1,112 @@
+//! This module contains the `detect_format` function which is used to sniff the format of a capture
+
+use byteorder::{BigEndian, ByteOrder};
+
+use pcap_header::{magic_format, Endianness};
+use pcapng::SECTION_HEADER_BLOCK;
+
+/// First bytes of a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// First bytes of a zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// First bytes of the captures of Microsoft Network Monitor 1.x and 2.x
+const NETMON_MAGICS: [&[u8; 4]; 2] = [b"RTSS", b"GMBU"];
+
+/// First bytes of a snoop capture, the last 4 ones are only checked if present
+const SNOOP_MAGIC: &[u8; 8] = b"snoop\0\0\0";
+
+/// Format of a capture, as detected by `detect_format`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+
+    /// Pcap file, read by the `PcapReader`
+    Pcap(Endianness),
+
+    /// PcapNg file, read by the `PcapNgReader`
+    PcapNg,
+
+    /// Gzip-compressed file, whose decompressed content can be detected again
+    Gzip,
+
+    /// Zstd-compressed file, whose decompressed content can be detected again
+    Zstd,
+
+    /// Microsoft Network Monitor capture, which can't be read by this crate
+    NetMon,
+
+    /// Snoop capture (RFC 1761), which can't be read by this crate
+    Snoop,
+
+    /// None of the above
+    Unknown
+}
+
+/// Detects the format of a capture from its first bytes.
+///
+/// Only the magic number at the start of the capture is checked, the first 4 bytes are enough.
+/// A shorter prefix gives `Format::Unknown`, unless it is a gzip prefix.
+///
+/// # Examples
+/// ```rust,no_run
+/// use std::fs::File;
+/// use std::io::{BufRead, BufReader};
+/// use pcap_file::{detect_format, Format, PcapNgReader, PcapReader};
+///
+/// let mut file = BufReader::new(File::open("capture").expect("Error opening file"));
+///
+/// match detect_format(file.fill_buf().unwrap()) {
+///     Format::Pcap(_) => println!("{} packets", PcapReader::new(file).unwrap().count()),
+///     Format::PcapNg => println!("{} packets", PcapNgReader::new(file).unwrap().count()),
+///     format => println!("Unsupported format {:?}", format)
+/// }
+/// ```
+pub fn detect_format(prefix: &[u8]) -> Format {
+
+    if prefix.starts_with(&GZIP_MAGIC) {
+        return Format::Gzip;
+    }
+
+    if prefix.len() < 4 {
+        return Format::Unknown;
+    }
+
+    if prefix.starts_with(&ZSTD_MAGIC) {
+        return Format::Zstd;
+    }
+
+    if NETMON_MAGICS.iter().any(|magic| prefix.starts_with(&magic[..])) {
+        return Format::NetMon;
+    }
+
+    if SNOOP_MAGIC.starts_with(&prefix[..prefix.len().min(8)]) {
+        return Format::Snoop;
+    }
+
+    // The magic number of a pcap header is read in big endian, like in PcapHeader::from_reader
+    let magic_number = BigEndian::read_u32(&prefix[..4]);
+
+    match magic_format(magic_number) {
+        Some((endianness, _)) => Format::Pcap(endianness),
+        None if magic_number == SECTION_HEADER_BLOCK => Format::PcapNg,
+        None => Format::Unknown
+    }
+}
+
+/// Returns a hint about the actual format of a file whose magic number isn't the one of a pcap file,
+/// appended to the message of an `UnknownMagic` error
+pub(crate) fn magic_hint(magic_number: u32) -> &'static str {
+
+    let mut prefix = [0u8; 4];
+    BigEndian::write_u32(&mut prefix, magic_number);
+
+    match detect_format(&prefix) {
+        Format::PcapNg => ", it looks like a pcapng file, read it with the PcapNgReader",
+        Format::Gzip => ", it looks like a gzip-compressed file, decompress it first or use PcapReader::new_gz",
+        Format::Zstd => ", it looks like a zstd-compressed file, decompress it first",
+        Format::NetMon => ", it looks like a Microsoft Network Monitor capture, convert it to pcap first",
+        Format::Snoop => ", it looks like a snoop capture, convert it to pcap first",
+        _ => ""
+    }
+}
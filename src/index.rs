@@ -0,0 +1,47 @@
+//! This module contains the `PacketIndex` struct which records the location of each packet of a pcap file.
+
+/// Records the byte offset and the timestamp of every packet of a pcap file.
+///
+/// It is built by `PcapReader::index` and allows to read any packet without reading the previous ones.
+#[derive(Clone, Debug, Default)]
+pub struct PacketIndex {
+
+    /// Byte offset of the packet header and timestamp of the packet, in nanoseconds since the epoch
+    pub entries: Vec<(u64, u64)>
+}
+
+impl PacketIndex {
+
+    /// Returns the number of indexed packets.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no packet is indexed.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the byte offset of the nth packet, if any.
+    pub fn offset(&self, n: usize) -> Option<u64> {
+        self.entries.get(n).map(|&(offset, _)| offset)
+    }
+
+    /// Returns the timestamp in nanoseconds of the nth packet, if any.
+    pub fn timestamp(&self, n: usize) -> Option<u64> {
+        self.entries.get(n).map(|&(_, timestamp)| timestamp)
+    }
+}
+
+/// Location of a packet record, global header included, in a pcap file.
+///
+/// It is returned by `PcapWriter::write_packet_indexed`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PacketLocation {
+
+    /// Byte offset of the packet header from the start of the global header
+    pub offset: u64,
+
+    /// Length of the record, packet header included
+    pub len: u64
+}
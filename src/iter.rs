@@ -0,0 +1,785 @@
+//! This module contains the iterator adapters returned by the `PcapReader` methods.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Seek};
+use std::time::{Duration, Instant};
+
+use errors::*;
+
+#[cfg(feature = "bpf")]
+use filter::Filter;
+use index::PacketIndex;
+use packet::{Packet, PacketHeader};
+use reader::PcapReader;
+use slice_reader::PcapSliceReader;
+
+
+/// Iterator over the packets of a `PcapReader` whose timestamp is in a given range.
+///
+/// It is created by `PcapReader::in_time_range`.
+#[derive(Debug)]
+pub struct TimeRange<T: Read> {
+    reader: PcapReader<T>,
+    start: Duration,
+    end: Duration,
+    sorted: bool,
+    done: bool
+}
+
+impl <T:Read> TimeRange<T> {
+
+    pub(crate) fn new(reader: PcapReader<T>, start: Duration, end: Duration) -> TimeRange<T> {
+
+        TimeRange {
+            reader,
+            start,
+            end,
+            sorted: true,
+            done: false
+        }
+    }
+
+    /// Reads the whole file instead of stopping at the first packet past the end of the range.
+    ///
+    /// Required for captures whose packets are not sorted by timestamp.
+    pub fn unsorted(mut self) -> TimeRange<T> {
+        self.sorted = false;
+        self
+    }
+}
+
+impl <T:Read> Iterator for TimeRange<T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+
+        let (start, end, sorted) = (self.start, self.end, self.sorted);
+
+        while !self.done {
+
+            let packet = self.reader.locate(|reader| {
+
+                if reader.is_eof()? {
+                    return Ok((true, None));
+                }
+
+                let header = reader.read_packet_header()?;
+                let timestamp = header.duration_since_epoch();
+
+                if timestamp >= end && sorted {
+                    Ok((true, None))
+                }
+                else if timestamp < start || timestamp >= end {
+                    reader.skip_packet_data(&header)?;
+                    Ok((false, None))
+                }
+                else {
+                    reader.read_packet_data(header).map(|packet| (false, Some(packet)))
+                }
+            });
+
+            match packet {
+                Ok((done, None)) => self.done = done,
+                Ok((_, Some(packet))) => return Some(Ok(packet)),
+                Err(err) => return Some(Err(err))
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterator over the next packets of a `PcapReader` whose timestamp is before a boundary.
+///
+/// It is created by `PcapReader::take_until`.
+#[derive(Debug)]
+pub struct TakeUntil<'a, T: Read + 'a> {
+    reader: &'a mut PcapReader<T>,
+    end: Duration,
+    done: bool
+}
+
+impl <'a, T:Read> TakeUntil<'a, T> {
+
+    pub(crate) fn new(reader: &'a mut PcapReader<T>, end: Duration) -> TakeUntil<'a, T> {
+        TakeUntil { reader, end, done: false }
+    }
+}
+
+impl <'a, T:Read> Iterator for TakeUntil<'a, T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+
+        if self.done {
+            return None;
+        }
+
+        // The first packet past the boundary stays buffered in the reader
+        let timestamp = match self.reader.peek_packet()? {
+            Ok(header) => header.duration_since_epoch(),
+            Err(err) => {
+                self.done = true;
+                return Some(Err(err));
+            }
+        };
+
+        if timestamp >= self.end {
+            self.done = true;
+            return None;
+        }
+
+        self.reader.next()
+    }
+}
+
+/// Iterator over the packets of a `PcapReader` read before a wall-clock deadline.
+///
+/// The deadline is checked before reading each packet: a read blocked on a slow stream isn't interrupted,
+/// the packet it returns is yielded and the iteration stops at the next call. The timestamps of the packets are ignored.
+///
+/// It is created by `PcapReader::take_for`.
+#[derive(Debug)]
+pub struct TakeFor<T: Read> {
+    reader: PcapReader<T>,
+    deadline: Instant
+}
+
+impl <T:Read> TakeFor<T> {
+
+    pub(crate) fn new(reader: PcapReader<T>, wall: Duration) -> TakeFor<T> {
+        TakeFor { reader, deadline: Instant::now() + wall }
+    }
+
+    /// Consumes the `TakeFor`, returning the `PcapReader` positioned after the last packet yielded.
+    pub fn into_inner(self) -> PcapReader<T> {
+        self.reader
+    }
+}
+
+impl <T:Read> Iterator for TakeFor<T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+
+        if Instant::now() >= self.deadline {
+            return None;
+        }
+
+        self.reader.next()
+    }
+}
+
+/// Iterator over the packets of a `PcapReader` whose timestamp is replaced by a function of the original one.
+///
+/// It is created by `PcapReader::retimestamp` and `PcapReader::shift_to`.
+#[derive(Debug)]
+pub struct Retimestamp<T: Read, F> {
+    reader: PcapReader<T>,
+    f: F
+}
+
+impl <T:Read, F: FnMut(Duration) -> Duration> Retimestamp<T, F> {
+
+    pub(crate) fn new(reader: PcapReader<T>, f: F) -> Retimestamp<T, F> {
+        Retimestamp { reader, f }
+    }
+}
+
+impl <T:Read, F: FnMut(Duration) -> Duration> Iterator for Retimestamp<T, F> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+
+        let mut packet = match self.reader.next()? {
+            Ok(packet) => packet,
+            Err(err) => return Some(Err(err))
+        };
+
+        let ts = (self.f)(packet.duration_since_epoch());
+        Some(packet.header.set_timestamp(ts).map(|_| packet))
+    }
+}
+
+/// Iterator over the packets of a `PcapReader` which match a filter expression.
+///
+/// It is created by `PcapReader::filter_expr`.
+#[cfg(feature = "bpf")]
+#[derive(Debug)]
+pub struct Filtered<T: Read> {
+    reader: PcapReader<T>,
+    filter: Filter
+}
+
+#[cfg(feature = "bpf")]
+impl <T:Read> Filtered<T> {
+
+    pub(crate) fn new(reader: PcapReader<T>, filter: Filter) -> Filtered<T> {
+        Filtered { reader, filter }
+    }
+
+    /// Returns the compiled filter.
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+}
+
+#[cfg(feature = "bpf")]
+impl <T:Read> Iterator for Filtered<T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+
+        for packet in &mut self.reader {
+
+            match packet {
+                Ok(packet) if !self.filter.matches(&packet) => continue,
+                packet => return Some(packet)
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterator over the packets of a `PcapReader` without the consecutive duplicates.
+///
+/// A packet is dropped when its payload is the same as the payload of the packet read just before it.
+/// The timestamps are not compared, unless a window is set with `within()`.
+///
+/// It is created by `PcapReader::dedup`.
+#[derive(Debug)]
+pub struct Dedup<T: Read> {
+    reader: PcapReader<T>,
+    window: Duration,
+    previous_ts: Option<Duration>,
+    previous_data: Vec<u8>
+}
+
+impl <T:Read> Dedup<T> {
+
+    pub(crate) fn new(reader: PcapReader<T>) -> Dedup<T> {
+        Dedup { reader, window: Duration::default(), previous_ts: None, previous_data: Vec::new() }
+    }
+
+    /// Only drops a duplicate received at most `window` after the previous packet.
+    ///
+    /// A window of 0, the default, drops the duplicates whatever their timestamp.
+    pub fn within(mut self, window: Duration) -> Dedup<T> {
+        self.window = window;
+        self
+    }
+
+    /// Returns true if `packet` repeats the previous packet
+    fn is_duplicate(&self, packet: &Packet) -> bool {
+
+        let previous_ts = match self.previous_ts {
+            Some(previous_ts) => previous_ts,
+            None => return false
+        };
+
+        if self.previous_data[..] != packet.data[..] {
+            return false;
+        }
+
+        if self.window == Duration::default() {
+            return true;
+        }
+
+        // The packets may not be sorted by timestamp
+        let ts = packet.duration_since_epoch();
+        let delta = ts.checked_sub(previous_ts).unwrap_or_else(|| previous_ts - ts);
+
+        delta <= self.window
+    }
+}
+
+impl <T:Read> Iterator for Dedup<T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+
+        loop {
+
+            let packet = match self.reader.next()? {
+                Ok(packet) => packet,
+                Err(err) => return Some(Err(err))
+            };
+
+            let duplicate = self.is_duplicate(&packet);
+
+            // A run of duplicates is compared packet by packet, each one against the one before it
+            self.previous_ts = Some(packet.duration_since_epoch());
+
+            if !duplicate {
+
+                // The payload of a duplicate is already the previous one
+                self.previous_data.clear();
+                self.previous_data.extend_from_slice(&packet.data);
+                return Some(Ok(packet));
+            }
+        }
+    }
+}
+
+/// Iterator over the packets of a `PcapReader` grouped by fixed time intervals.
+///
+/// Each item is the start of a bucket, a multiple of the interval since the epoch, and its packets in the read order.
+/// The empty buckets are skipped.
+/// A bucket is yielded once a packet at least one whole interval after its end is read, or at the end of the stream,
+/// so a packet out of order by less than one interval still joins its bucket.
+/// A packet older than that is yielded in a group of its own bucket, after the bucket has already been yielded.
+///
+/// It is created by `PcapReader::bucketed`.
+#[derive(Debug)]
+pub struct Bucketed<T: Read> {
+    reader: PcapReader<T>,
+    interval: u128,
+    buckets: BTreeMap<u128, Vec<Packet<'static>>>,
+    newest: u128,
+    done: bool
+}
+
+impl <T:Read> Bucketed<T> {
+
+    pub(crate) fn new(reader: PcapReader<T>, interval: Duration) -> Bucketed<T> {
+
+        assert!(interval > Duration::default(), "The interval of the buckets can't be 0");
+
+        Bucketed {
+            reader,
+            interval: interval.as_nanos(),
+            buckets: BTreeMap::new(),
+            newest: 0,
+            done: false
+        }
+    }
+
+    /// Removes the oldest bucket if no more packet is expected in it
+    fn pop_closed(&mut self) -> Option<(Duration, Vec<Packet<'static>>)> {
+
+        let index = *self.buckets.keys().next()?;
+
+        if !self.done && index + 1 >= self.newest {
+            return None;
+        }
+
+        let packets = self.buckets.remove(&index)?;
+        let start = index * self.interval;
+        let start = Duration::new((start / 1_000_000_000) as u64, (start % 1_000_000_000) as u32);
+
+        Some((start, packets))
+    }
+}
+
+impl <T:Read> Iterator for Bucketed<T> {
+
+    type Item = ResultChain<(Duration, Vec<Packet<'static>>)>;
+
+    fn next(&mut self) -> Option<ResultChain<(Duration, Vec<Packet<'static>>)>> {
+
+        loop {
+
+            if let Some(bucket) = self.pop_closed() {
+                return Some(Ok(bucket));
+            }
+
+            if self.done {
+                return None;
+            }
+
+            match self.reader.next() {
+                Some(Ok(packet)) => {
+
+                    let index = packet.duration_since_epoch().as_nanos() / self.interval;
+                    self.newest = self.newest.max(index);
+                    self.buckets.entry(index).or_default().push(packet);
+                },
+                Some(Err(err)) => return Some(Err(err)),
+                None => self.done = true
+            }
+        }
+    }
+}
+
+/// Iterator over the packets of a `PcapReader` which could be parsed, the errors being kept aside.
+///
+/// The errors are available with `errors()` once the iteration is over. Only the first ones are kept,
+/// 100 by default, the others are only counted. An I/O error ends the iteration, as it would probably repeat.
+/// The packets following an invalid packet header are usually garbage, as its payload can't be skipped.
+///
+/// It is created by `PcapReader::lossy`.
+#[derive(Debug)]
+pub struct Lossy<T: Read> {
+    reader: PcapReader<T>,
+    errors: Vec<Error>,
+    max_errors: usize,
+    dropped_errors: u64,
+    done: bool
+}
+
+impl <T:Read> Lossy<T> {
+
+    pub(crate) fn new(reader: PcapReader<T>) -> Lossy<T> {
+
+        Lossy {
+            reader,
+            errors: Vec::new(),
+            max_errors: 100,
+            dropped_errors: 0,
+            done: false
+        }
+    }
+
+    /// Keeps at most `max_errors` errors.
+    pub fn max_errors(mut self, max_errors: usize) -> Lossy<T> {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// Returns the errors encountered so far, in the read order.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// Returns the number of errors encountered once `max_errors` errors were kept.
+    pub fn dropped_errors(&self) -> u64 {
+        self.dropped_errors
+    }
+
+    /// Consumes the `Lossy`, returning the errors encountered.
+    pub fn into_errors(self) -> Vec<Error> {
+        self.errors
+    }
+}
+
+impl <T:Read> Iterator for Lossy<T> {
+
+    type Item = Packet<'static>;
+
+    fn next(&mut self) -> Option<Packet<'static>> {
+
+        while !self.done {
+
+            let err = match self.reader.next()? {
+                Ok(packet) => return Some(packet),
+                Err(err) => err
+            };
+
+            if let ErrorKind::Io(_) = *err.root_kind() {
+                self.done = true;
+            }
+
+            if self.errors.len() < self.max_errors {
+                self.errors.push(err);
+            }
+            else {
+                self.dropped_errors += 1;
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterator over the first packets of a `PcapReader`.
+///
+/// It is created by `PcapReader::limit`.
+#[derive(Debug)]
+pub struct Limit<T: Read> {
+    reader: PcapReader<T>,
+    remaining: usize
+}
+
+impl <T:Read> Limit<T> {
+
+    pub(crate) fn new(reader: PcapReader<T>, n: usize) -> Limit<T> {
+        Limit { reader, remaining: n }
+    }
+
+    /// Consumes the `Limit`, returning the `PcapReader` positioned after the last packet yielded.
+    pub fn into_inner(self) -> PcapReader<T> {
+        self.reader
+    }
+}
+
+impl <T:Read> Iterator for Limit<T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+
+        // Nothing is read once the limit is reached
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+        self.reader.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+
+        let (min, max) = self.reader.size_hint();
+        (min.min(self.remaining), Some(max.map_or(self.remaining, |max| max.min(self.remaining))))
+    }
+}
+
+/// Iterator over the packets of a `PcapReader` which were, or were not, truncated to the snaplen during the capture.
+///
+/// It is created by `PcapReader::only_complete` and `PcapReader::only_truncated`.
+#[derive(Debug)]
+pub struct Truncation<T: Read> {
+    reader: PcapReader<T>,
+    truncated: bool
+}
+
+impl <T:Read> Truncation<T> {
+
+    pub(crate) fn new(reader: PcapReader<T>, truncated: bool) -> Truncation<T> {
+        Truncation { reader, truncated }
+    }
+}
+
+impl <T:Read> Iterator for Truncation<T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+
+        for packet in &mut self.reader {
+
+            match packet {
+                Ok(packet) if packet.is_truncated() != self.truncated => continue,
+                packet => return Some(packet)
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterator over the packets of a `PcapReader` along with the time elapsed since the previous packet.
+///
+/// The delta is None for the first packet. The timestamps are not required to be sorted:
+/// a packet older than the previous one gets a zero delta, and becomes the reference for the next packet.
+/// The errors are yielded as is and don't change the reference.
+///
+/// It is created by `PcapReader::with_deltas`.
+#[derive(Debug)]
+pub struct Deltas<T: Read> {
+    reader: PcapReader<T>,
+    prev_ts: Option<Duration>
+}
+
+impl <T:Read> Deltas<T> {
+
+    pub(crate) fn new(reader: PcapReader<T>) -> Deltas<T> {
+        Deltas { reader, prev_ts: None }
+    }
+}
+
+impl <T:Read> Iterator for Deltas<T> {
+
+    type Item = ResultChain<(Packet<'static>, Option<Duration>)>;
+
+    fn next(&mut self) -> Option<ResultChain<(Packet<'static>, Option<Duration>)>> {
+
+        let packet = match self.reader.next()? {
+            Ok(packet) => packet,
+            Err(e) => return Some(Err(e))
+        };
+
+        let ts = packet.header.duration_since_epoch();
+        let delta = self.prev_ts.map(|prev| ts.checked_sub(prev).unwrap_or_default());
+        self.prev_ts = Some(ts);
+
+        Some(Ok((packet, delta)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.reader.size_hint()
+    }
+}
+
+/// Iterator over the packet headers of a `PcapReader`, the payloads are read and discarded without being allocated.
+///
+/// It is created by `PcapReader::headers`.
+#[derive(Debug)]
+pub struct Headers<T: Read> {
+    reader: PcapReader<T>,
+    done: bool
+}
+
+impl <T:Read> Headers<T> {
+
+    pub(crate) fn new(reader: PcapReader<T>) -> Headers<T> {
+        Headers { reader, done: false }
+    }
+}
+
+impl <T:Read> Iterator for Headers<T> {
+
+    type Item = ResultChain<PacketHeader>;
+
+    fn next(&mut self) -> Option<ResultChain<PacketHeader>> {
+
+        if self.done {
+            return None;
+        }
+
+        let header = self.reader.locate(|reader| {
+
+            if reader.is_eof()? {
+                return Ok(None);
+            }
+
+            let header = reader.read_packet_header()?;
+            reader.skip_packet_data(&header)?;
+            Ok(Some(header))
+        });
+
+        // The iteration ends at the first error
+        self.done = !matches!(header, Ok(Some(_)));
+        header.transpose()
+    }
+}
+
+/// Iterator over the packet headers of a seekable `PcapReader`, the payloads are skipped by seeking.
+///
+/// It is created by `PcapReader::headers_seek`.
+#[derive(Debug)]
+pub struct SeekHeaders<T: Read + Seek> {
+    reader: PcapReader<T>,
+    end: u64,
+    done: bool
+}
+
+impl <T:Read + Seek> SeekHeaders<T> {
+
+    pub(crate) fn new(reader: PcapReader<T>, end: u64) -> SeekHeaders<T> {
+        SeekHeaders { reader, end, done: false }
+    }
+}
+
+impl <T:Read + Seek> Iterator for SeekHeaders<T> {
+
+    type Item = ResultChain<PacketHeader>;
+
+    fn next(&mut self) -> Option<ResultChain<PacketHeader>> {
+
+        if self.done {
+            return None;
+        }
+
+        let end = self.end;
+        let header = self.reader.locate(|reader| {
+
+            if reader.is_eof()? {
+                return Ok(None);
+            }
+
+            let mut header = reader.read_packet_header()?;
+            reader.seek_packet_data(&mut header, end)?;
+            Ok(Some(header))
+        });
+
+        // The iteration ends at the first error
+        self.done = !matches!(header, Ok(Some(_)));
+        header.transpose()
+    }
+}
+
+/// Iterator over the packets of a `PacketIndex`, from the last one to the first one.
+///
+/// Each packet is read by seeking to its offset in the index.
+///
+/// It is created by `PcapReader::rev_from_index`.
+#[derive(Debug)]
+pub struct RevFromIndex<'a, T: Read + Seek + 'a> {
+    reader: &'a mut PcapReader<T>,
+    index: &'a PacketIndex,
+    remaining: usize
+}
+
+impl <'a, T:Read + Seek> RevFromIndex<'a, T> {
+
+    pub(crate) fn new(reader: &'a mut PcapReader<T>, index: &'a PacketIndex) -> RevFromIndex<'a, T> {
+        RevFromIndex { reader, index, remaining: index.len() }
+    }
+}
+
+impl <'a, T:Read + Seek> Iterator for RevFromIndex<'a, T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+        Some(self.reader.read_packet_at(self.index, self.remaining))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// Iterator over the headers and the payloads of the packets of a `PcapReader`, as separate values.
+///
+/// It is created by `PcapReader::headers_and_data`.
+#[derive(Debug)]
+pub struct HeadersAndData<T: Read> {
+    reader: PcapReader<T>
+}
+
+impl <T:Read> HeadersAndData<T> {
+
+    pub(crate) fn new(reader: PcapReader<T>) -> HeadersAndData<T> {
+        HeadersAndData { reader }
+    }
+}
+
+impl <T:Read> Iterator for HeadersAndData<T> {
+
+    type Item = ResultChain<(PacketHeader, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<ResultChain<(PacketHeader, Vec<u8>)>> {
+
+        // The payload read is owned, it is moved out without copy
+        self.reader.next().map(|packet| packet.map(|packet| (packet.header, packet.data.into_owned())))
+    }
+}
+
+/// Iterator over the headers and the payloads of the packets of a `PcapSliceReader`, as separate values.
+///
+/// It is created by `PcapSliceReader::headers_and_data`.
+#[derive(Debug)]
+pub struct SliceHeadersAndData<'a> {
+    reader: PcapSliceReader<'a>
+}
+
+impl<'a> SliceHeadersAndData<'a> {
+
+    pub(crate) fn new(reader: PcapSliceReader<'a>) -> SliceHeadersAndData<'a> {
+        SliceHeadersAndData { reader }
+    }
+}
+
+impl<'a> Iterator for SliceHeadersAndData<'a> {
+
+    type Item = ResultChain<(PacketHeader, &'a [u8])>;
+
+    fn next(&mut self) -> Option<ResultChain<(PacketHeader, &'a [u8])>> {
+        self.reader.next_record()
+    }
+}
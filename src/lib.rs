@@ -2,6 +2,43 @@
 //!
 //! Also provides a `Packet` struct which represents a pcap packet with its header.
 //!
+//! In-memory pcap files can be read without copy with the `PcapSliceReader`,
+//! and the `ArenaReader` reads all the packets of a file into a single buffer.
+//! `PcapReader::next_pooled` reads owned packets whose payload buffers are recycled by a `PacketPool`.
+//!
+//! Several pcap files can be merged into a single timeline with the `PcapMerger`,
+//! and a pcap file can be split into smaller ones with the functions of the `split` module.
+//! A long-running capture can be written into a ring of files with the `RotatingWriter`,
+//! and rotated captures can be read back as a single stream with the `MultiFileReader`.
+//! Two in-memory pcap files can be concatenated with `concat_bytes`.
+//!
+//! The integrity of a whole pcap file can be checked with `validate`, without reading the payloads,
+//! and the packets of two pcap files can be compared with `diff`.
+//! `transcode` rewrites the headers of a pcap file in another endianness,
+//! and `PcapReader::summarize` gives the timestamps, the number and the lengths of the packets of a file.
+//!
+//! With the `gzip` feature, gzip-compressed pcap files can be read with `PcapReader::new_gz`.
+//! Other compressions, such as zstd, only need their decoder or encoder to be wrapped:
+//!
+//! ```rust,ignore
+//! // With the zstd crate
+//! let pcap_reader = PcapReader::new(zstd::Decoder::new(File::open("test.pcap.zst")?)?)?;
+//!
+//! let mut pcap_writer = PcapWriter::new(zstd::Encoder::new(File::create("out.pcap.zst")?, 3)?)?;
+//! // The zstd frame must be finished to get a valid file
+//! pcap_writer.into_writer()?.finish()?;
+//! ```
+//!
+//! With the `bpf` feature, packets can be selected with a libpcap-like filter expression with `PcapReader::filter_expr`.
+//!
+//! With the `checksum` feature, the CRC32 of the packet records can be computed while writing them with the `ChecksumWriter`,
+//! and checked while reading them with the `ChecksumReader`.
+//!
+//! With the `tokio` feature, pcap streams can be read asynchronously with the `AsyncPcapReader`.
+//!
+//! PcapNg files can be read with the `PcapNgReader` of the `pcapng` module,
+//! `detect_format` tells them apart from pcap files.
+//!
 //! # Examples
 //!
 //! ```rust,no_run
@@ -27,21 +64,89 @@
 //! ```
 extern crate byteorder;
 
+#[cfg(feature = "checksum")]
+extern crate crc32fast;
+#[cfg(feature = "gzip")]
+extern crate flate2;
+#[cfg(feature = "tokio")]
+extern crate futures_core;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+
 #[macro_use]
 extern crate error_chain;
 
+mod arena_reader;
+pub use arena_reader::{ArenaIter, ArenaReader};
+
+#[cfg(feature = "tokio")]
+mod async_reader;
+#[cfg(feature = "tokio")]
+pub use async_reader::AsyncPcapReader;
+
+#[cfg(feature = "checksum")]
+mod checksum;
+#[cfg(feature = "checksum")]
+pub use checksum::{ChecksumReader, ChecksumWriter};
+
+mod concat;
+pub use concat::concat_bytes;
+
+mod diff;
+pub use diff::{diff, Diff, DiffEntry, DiffReason};
+
 pub mod errors;
 
+#[cfg(feature = "bpf")]
+pub mod filter;
+
+mod format;
+pub use format::{detect_format, Format};
+
+mod index;
+pub use index::{PacketIndex, PacketLocation};
+
+pub mod iter;
+
+mod merger;
+pub use merger::PcapMerger;
+
+mod multi_file_reader;
+pub use multi_file_reader::MultiFileReader;
+
 mod packet;
-pub use packet::{Packet, PacketHeader};
+pub use packet::{read_packet, read_packet_with_max, Packet, PacketBuilder, PacketHeader, DEFAULT_MAX_PACKET_LEN};
 
 mod pcap_header;
-pub use pcap_header::{DataLink, PcapHeader};
+pub use pcap_header::{loopback_af, ppi_len, radiotap_len, DataLink, Endianness, PcapHeader, SnaplenCheck, TsResolution};
+
+pub mod pcapng;
+pub use pcapng::PcapNgReader;
 
 pub mod peek_reader;
 
+mod pool;
+pub use pool::{PacketPool, PooledPacket};
+
 mod reader;
 pub use reader::PcapReader;
 
+mod rotating_writer;
+pub use rotating_writer::RotatingWriter;
+
+mod slice_reader;
+pub use slice_reader::PcapSliceReader;
+
+pub mod split;
+
+mod stats;
+pub use stats::CaptureStats;
+
+mod transcode;
+pub use transcode::transcode;
+
+mod validate;
+pub use validate::{validate, Anomaly, ValidationReport};
+
 mod writer;
-pub use writer::PcapWriter;
+pub use writer::{PcapWriter, PcapWriterBuilder, DEFAULT_BUFFER_CAPACITY};
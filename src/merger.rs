@@ -0,0 +1,147 @@
+//! This module contains the `PcapMerger` struct which is used to merge several pcap files by timestamp
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::Read;
+use std::time::Duration;
+
+use errors::*;
+
+use packet::Packet;
+use reader::PcapReader;
+
+
+/// This struct merges several `PcapReader` into a single stream of packets sorted by timestamp.
+///
+/// It implements the Iterator trait in order to yield the oldest pending packet of all the readers.
+/// Each reader is expected to be sorted by timestamp, only the next packet of each of them is kept in memory.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use pcap_file::{PcapMerger, PcapReader, PcapWriter};
+///
+/// let reader_1 = PcapReader::new(File::open("eth0.pcap").unwrap()).unwrap();
+/// let reader_2 = PcapReader::new(File::open("eth1.pcap").unwrap()).unwrap();
+/// let datalink = reader_1.header.datalink;
+///
+/// let pcap_merger = PcapMerger::new(vec![reader_1, reader_2]).unwrap();
+///
+/// let file_out = File::create("out.pcap").expect("Error creating file out");
+/// let mut pcap_writer = PcapWriter::with_datalink(datalink, file_out).unwrap();
+///
+/// for pcap in pcap_merger {
+///     pcap_writer.write_packet(&pcap.unwrap()).unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PcapMerger<T: Read> {
+    readers: Vec<PcapReader<T>>,
+    pending: Vec<Option<Packet<'static>>>,
+    heap: BinaryHeap<Reverse<(Duration, usize)>>,
+    to_fill: Vec<usize>
+}
+
+impl <T:Read> PcapMerger<T> {
+
+    /// Create a new `PcapMerger` from several readers.
+    ///
+    /// # Errors
+    /// Return an error if the readers don't use the same datalink.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{PcapMerger, PcapReader};
+    ///
+    /// let reader_1 = PcapReader::new(File::open("eth0.pcap").unwrap()).unwrap();
+    /// let reader_2 = PcapReader::new(File::open("eth1.pcap").unwrap()).unwrap();
+    ///
+    /// let pcap_merger = PcapMerger::new(vec![reader_1, reader_2]).unwrap();
+    /// ```
+    pub fn new(readers: Vec<PcapReader<T>>) -> ResultChain<PcapMerger<T>> {
+
+        if let Some(first) = readers.first() {
+
+            let datalink = first.header.datalink;
+            if let Some(other) = readers.iter().find(|reader| reader.header.datalink != datalink) {
+                bail!(ErrorKind::IncompatibleHeaders(format!("datalink {:?} can't be merged with datalink {:?}", datalink, other.header.datalink)));
+            }
+        }
+
+        Ok(PcapMerger::new_permissive(readers))
+    }
+
+    /// Create a new `PcapMerger` from several readers without checking their datalink.
+    ///
+    /// The packets of the different datalinks are mixed up, the caller must tell them apart.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{PcapMerger, PcapReader};
+    ///
+    /// let reader_1 = PcapReader::new(File::open("eth0.pcap").unwrap()).unwrap();
+    /// let reader_2 = PcapReader::new(File::open("tun0.pcap").unwrap()).unwrap();
+    ///
+    /// let pcap_merger = PcapMerger::new_permissive(vec![reader_1, reader_2]);
+    /// ```
+    pub fn new_permissive(readers: Vec<PcapReader<T>>) -> PcapMerger<T> {
+
+        let len = readers.len();
+
+        PcapMerger {
+            readers,
+            pending: (0..len).map(|_| None).collect(),
+            heap: BinaryHeap::with_capacity(len),
+            to_fill: (0..len).rev().collect()
+        }
+    }
+
+    /// Consumes the `PcapMerger`, returning the wrapped readers.
+    ///
+    /// The packets already read from the readers but not yielded yet are lost.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{PcapMerger, PcapReader};
+    ///
+    /// let reader = PcapReader::new(File::open("test.pcap").unwrap()).unwrap();
+    /// let pcap_merger = PcapMerger::new(vec![reader]).unwrap();
+    ///
+    /// let readers = pcap_merger.into_readers();
+    /// ```
+    pub fn into_readers(self) -> Vec<PcapReader<T>> {
+        self.readers
+    }
+}
+
+impl <T:Read> Iterator for PcapMerger<T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+
+        // Read the next packet of the readers whose packet has been yielded
+        while let Some(idx) = self.to_fill.pop() {
+
+            match self.readers[idx].next() {
+                Some(Ok(packet)) => {
+                    self.heap.push(Reverse((packet.duration_since_epoch(), idx)));
+                    self.pending[idx] = Some(packet);
+                },
+
+                // The position of the next packet of this reader is unknown, stop reading it
+                Some(Err(err)) => return Some(Err(err)),
+                None => {}
+            }
+        }
+
+        let Reverse((_, idx)) = self.heap.pop()?;
+        self.to_fill.push(idx);
+
+        self.pending[idx].take().map(Ok)
+    }
+}
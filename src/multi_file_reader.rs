@@ -0,0 +1,148 @@
+//! This module contains the `MultiFileReader` struct which is used to read several pcap files as one
+
+use std::collections::VecDeque;
+use std::io::Read;
+use std::path::Path;
+
+use errors::*;
+
+use packet::Packet;
+use pcap_header::PcapHeader;
+use reader::PcapReader;
+
+
+/// This struct reads several `PcapReader` one after the other as a single stream of packets.
+///
+/// It is meant for captures rotated into several files: the packets of the first reader are yielded,
+/// then the ones of the second reader and so on.
+/// All the readers must share the datalink and the endianness of the first one.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pcap_file::{MultiFileReader, PcapWriter};
+/// use std::fs::File;
+///
+/// let multi_reader = MultiFileReader::from_paths(&["capture1.pcap", "capture2.pcap"]).unwrap();
+///
+/// let file_out = File::create("out.pcap").expect("Error creating file out");
+/// let mut pcap_writer = PcapWriter::with_header(multi_reader.header, file_out).unwrap();
+///
+/// for pcap in multi_reader {
+///     pcap_writer.write_packet(&pcap.unwrap()).unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MultiFileReader<T: Read> {
+
+    /// Global header of the first reader
+    pub header: PcapHeader,
+    readers: VecDeque<PcapReader<T>>
+}
+
+impl <T:Read> MultiFileReader<T> {
+
+    /// Create a new `MultiFileReader` from several readers, read in the given order.
+    ///
+    /// # Errors
+    /// Return an error if there is no reader or if a reader doesn't use the datalink or the endianness of the first one.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{MultiFileReader, PcapReader};
+    ///
+    /// let reader_1 = PcapReader::new(File::open("capture1.pcap").unwrap()).unwrap();
+    /// let reader_2 = PcapReader::new(File::open("capture2.pcap").unwrap()).unwrap();
+    ///
+    /// let multi_reader = MultiFileReader::new(vec![reader_1, reader_2]).unwrap();
+    /// ```
+    pub fn new(readers: Vec<PcapReader<T>>) -> ResultChain<MultiFileReader<T>> {
+
+        let header = match readers.first() {
+            Some(first) => first.header,
+            None => bail!(ErrorKind::WrongField("A MultiFileReader needs at least one reader".to_string()))
+        };
+
+        for reader in &readers[1..] {
+
+            if reader.header.datalink != header.datalink {
+                bail!(ErrorKind::IncompatibleHeaders(format!("datalink {:?} can't follow datalink {:?}", reader.header.datalink, header.datalink)));
+            }
+
+            if reader.header.endianness() != header.endianness() {
+                bail!(ErrorKind::IncompatibleHeaders(format!("endianness {:?} can't follow endianness {:?}", reader.header.endianness(), header.endianness())));
+            }
+        }
+
+        Ok(
+            MultiFileReader {
+                header,
+                readers: readers.into()
+            }
+        )
+    }
+
+    /// Consumes the `MultiFileReader`, returning the readers which haven't been read until the end.
+    ///
+    /// The first returned reader is the one being read.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{MultiFileReader, PcapReader};
+    ///
+    /// let reader = PcapReader::new(File::open("test.pcap").unwrap()).unwrap();
+    /// let multi_reader = MultiFileReader::new(vec![reader]).unwrap();
+    ///
+    /// let readers = multi_reader.into_readers();
+    /// ```
+    pub fn into_readers(self) -> Vec<PcapReader<T>> {
+        self.readers.into()
+    }
+}
+
+impl MultiFileReader<Box<dyn Read>> {
+
+    /// Opens the pcap files at the given paths with `PcapReader::from_path` and reads them in the given order.
+    ///
+    /// All the files are opened, and their global header read, upfront.
+    ///
+    /// # Errors
+    /// Return an error if a file can't be opened, if it is not in a valid pcap file format
+    /// or if its header doesn't match the header of the first file.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use pcap_file::MultiFileReader;
+    ///
+    /// let multi_reader = MultiFileReader::from_paths(&["capture1.pcap", "capture2.pcap.gz"]).unwrap();
+    /// ```
+    pub fn from_paths<P: AsRef<Path>>(paths: &[P]) -> ResultChain<MultiFileReader<Box<dyn Read>>> {
+
+        let readers = paths.iter()
+            .map(PcapReader::from_path)
+            .collect::<ResultChain<Vec<_>>>()?;
+
+        MultiFileReader::new(readers)
+    }
+}
+
+impl <T:Read> Iterator for MultiFileReader<T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+
+        // Move on to the next reader once the current one is exhausted
+        loop {
+
+            match self.readers.front_mut()?.next() {
+                Some(packet) => return Some(packet),
+                None => {
+                    self.readers.pop_front();
+                }
+            }
+        }
+    }
+}
@@ -2,27 +2,52 @@
 //! and its header.
 
 use std::borrow::Cow;
-use std::io::Read;
+use std::fmt;
+use std::io::{self, Read};
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use byteorder::*;
 
 use errors::*;
 
+use pcap_header::{DataLink, Endianness, PcapHeader, TsResolution};
+
+/// Default maximum length of the packets read, 256 KiB.
+///
+/// Readers refuse longer packets instead of allocating them, a corrupt file could declare packets of 4 GiB.
+pub const DEFAULT_MAX_PACKET_LEN: u32 = 256 * 1024;
+
+/// Number of payload bytes shown by the `Debug` implementation of `Packet`
+const DEBUG_PAYLOAD_LEN: usize = 16;
+
 /// Describes a pcap packet header.
-#[derive(Copy, Clone, Default, Debug)]
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
 pub struct PacketHeader {
 
     /// Timestamp in seconds
     pub ts_sec: u32,
 
-    /// Microseconds/nanosecond part of the timestamp
+    /// Microseconds/nanosecond part of the timestamp, depending on `ts_resolution`
     pub ts_usec: u32,
 
     /// Number of octets of the packet saved in file
     pub incl_len: u32,
 
     /// Original length of the packet on the wire
-    pub orig_len: u32
+    pub orig_len: u32,
+
+    /// Resolution of the sub-second part of the timestamp, given by the global header
+    pub ts_resolution: TsResolution,
+
+    /// Index of the capture interface, only in the modified pcap format
+    pub ifindex: Option<u32>,
+
+    /// Ethernet protocol of the packet, only in the modified pcap format
+    pub protocol: Option<u16>,
+
+    /// Linux packet type (host, broadcast, outgoing...), only in the modified pcap format
+    pub pkt_type: Option<u8>
 }
 
 
@@ -31,56 +56,263 @@ impl PacketHeader {
     /// Create a new `PacketHeader` with the given parameters.
     ///
     /// Only one length field is provided because incl_len and orig_len are almost always the same.
+    /// The timestamp resolution is the microsecond.
     pub fn new(ts_sec: u32, ts_usec: u32, len:u32) -> PacketHeader {
 
         PacketHeader {
-            ts_sec: ts_sec,
-            ts_usec: ts_usec,
+            ts_sec,
+            ts_usec,
             incl_len: len,
             orig_len: len,
+            ts_resolution: TsResolution::MicroSecond,
+            ..Default::default()
         }
     }
 
     /// Create a new `PacketHeader` from a given reader.
-    pub fn from_reader<R: Read, B: ByteOrder>(reader: &mut R) -> ResultChain<PacketHeader> {
+    ///
+    /// The timestamp resolution is the one of the global header of the file.
+    pub fn from_reader<R: Read, B: ByteOrder>(reader: &mut R, ts_resolution: TsResolution) -> ResultChain<PacketHeader> {
 
-        let ts_sec = reader.read_u32::<B>()?;
-        let ts_usec = reader.read_u32::<B>()?;
-        let incl_len = reader.read_u32::<B>()?;
-        let orig_len = reader.read_u32::<B>()?;
+        let header = PacketHeader::from_reader_unchecked::<R, B>(reader, ts_resolution)?;
 
-        if incl_len > 0xFFFF {
-            bail!(ErrorKind::WrongField(format!("PacketHeader.incl_len = {} > 0xFFFF", incl_len)));
+        if header.incl_len > header.orig_len {
+            bail!(ErrorKind::WrongField(format!("PacketHeader.incl_len ({}) > PacketHeader.orig_len ({})", header.incl_len, header.orig_len)));
         }
 
-        if orig_len > 0xFFFF {
-            bail!(ErrorKind::WrongField(format!("PacketHeader.orig_len = {} > 0xFFFF", orig_len)));
+        Ok(header)
+    }
+
+    /// Parses a `PacketHeader` without checking its lengths
+    pub(crate) fn from_reader_unchecked<R: Read, B: ByteOrder>(reader: &mut R, ts_resolution: TsResolution) -> ResultChain<PacketHeader> {
+
+        let mut bytes = [0u8; 16];
+        read_full(reader, &mut bytes)?;
+
+        Ok(PacketHeader::from_bytes::<B>(&bytes, ts_resolution))
+    }
+
+    /// Parses the 16 bytes of a packet header, in the given endianness.
+    ///
+    /// Nothing is checked, not even `incl_len <= orig_len`, and the timestamp resolution is the microsecond:
+    /// `ts_resolution` must be set afterwards for a file with nanosecond timestamps.
+    /// The extra fields of the modified pcap format are not part of these 16 bytes.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::{Endianness, PacketHeader};
+    ///
+    /// let bytes = [1, 0, 0, 0, 2, 0, 0, 0, 60, 0, 0, 0, 64, 0, 0, 0];
+    /// let header = PacketHeader::from_slice(&bytes, Endianness::Little);
+    ///
+    /// assert_eq!((header.ts_sec, header.ts_usec, header.incl_len, header.orig_len), (1, 2, 60, 64));
+    /// assert_eq!(header.to_bytes(Endianness::Little), bytes);
+    /// ```
+    pub fn from_slice(bytes: &[u8; 16], endianness: Endianness) -> PacketHeader {
+
+        match endianness {
+            Endianness::Big => PacketHeader::from_bytes::<BigEndian>(bytes, TsResolution::MicroSecond),
+            Endianness::Little => PacketHeader::from_bytes::<LittleEndian>(bytes, TsResolution::MicroSecond)
         }
+    }
 
-        if incl_len > orig_len {
-            bail!(ErrorKind::WrongField(format!("PacketHeader.incl_len ({}) > PacketHeader.orig_len ({})", incl_len, orig_len)));
+    /// Parses the 16 bytes of a packet header
+    fn from_bytes<B: ByteOrder>(bytes: &[u8; 16], ts_resolution: TsResolution) -> PacketHeader {
+
+        PacketHeader {
+
+            ts_sec: B::read_u32(&bytes[0..4]),
+            ts_usec: B::read_u32(&bytes[4..8]),
+            incl_len: B::read_u32(&bytes[8..12]),
+            orig_len: B::read_u32(&bytes[12..16]),
+            ts_resolution,
+            ..Default::default()
         }
+    }
 
-        Ok(
-            PacketHeader {
+    /// Parses the 8 extra bytes of a packet header of the modified pcap format.
+    pub fn read_modified_fields<R: Read, B: ByteOrder>(&mut self, reader: &mut R) -> ResultChain<()> {
 
-                ts_sec,
-                ts_usec,
-                incl_len,
-                orig_len
-            }
-        )
+        self.ifindex = Some(reader.read_u32::<B>()?);
+        self.protocol = Some(reader.read_u16::<B>()?);
+        self.pkt_type = Some(reader.read_u8()?);
+        let _padding = reader.read_u8()?;
+
+        Ok(())
+    }
+
+    /// Returns the sub-second part of the timestamp in nanoseconds, whatever the timestamp resolution.
+    pub fn ts_nsec(&self) -> u32 {
+
+        match self.ts_resolution {
+            TsResolution::MicroSecond => self.ts_usec.saturating_mul(1000),
+            TsResolution::NanoSecond => self.ts_usec
+        }
+    }
+
+    /// Returns the timestamp of the packet as a `Duration` since the epoch.
+    ///
+    /// A sub-second part out of range for the timestamp resolution, as found in some malformed captures,
+    /// is saturated to the greatest valid value.
+    pub fn duration_since_epoch(&self) -> Duration {
+
+        let nanos = match self.ts_resolution {
+            TsResolution::MicroSecond => self.ts_usec.min(999_999) * 1000,
+            TsResolution::NanoSecond => self.ts_usec.min(999_999_999)
+        };
+
+        Duration::new(u64::from(self.ts_sec), nanos)
+    }
+
+    /// Returns the timestamp of the packet as a `SystemTime`.
+    ///
+    /// See `duration_since_epoch` for the handling of out of range sub-second parts.
+    pub fn timestamp(&self) -> SystemTime {
+        UNIX_EPOCH + self.duration_since_epoch()
+    }
+
+    /// Returns true if the packet was truncated to the snaplen during the capture, its incl_len being lower than its orig_len.
+    pub fn is_truncated(&self) -> bool {
+        self.incl_len < self.orig_len
+    }
+
+    /// Raises the orig_len to the incl_len when it is lower, which is invalid.
+    ///
+    /// Some capture tools write an orig_len of 0: the packet is then assumed not to be truncated.
+    /// Returns true if the orig_len was changed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::PacketHeader;
+    ///
+    /// let mut header = PacketHeader::new(0, 0, 60);
+    /// header.orig_len = 0;
+    ///
+    /// assert!(header.fix_orig_len());
+    /// assert_eq!(header.orig_len, 60);
+    /// ```
+    pub fn fix_orig_len(&mut self) -> bool {
+
+        if self.orig_len >= self.incl_len {
+            return false;
+        }
+
+        self.orig_len = self.incl_len;
+        true
+    }
+
+    /// Swaps the incl_len and the orig_len when the incl_len is greater, which is invalid.
+    ///
+    /// Some buggy capture tools write the two fields in the wrong order, the captured length being in the orig_len.
+    /// Returns true if the lengths were swapped.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::PacketHeader;
+    ///
+    /// let mut header = PacketHeader::new(0, 0, 1514);
+    /// header.incl_len = 1514;
+    /// header.orig_len = 96;
+    ///
+    /// assert!(header.swap_len_fields());
+    /// assert_eq!((header.incl_len, header.orig_len), (96, 1514));
+    /// ```
+    pub fn swap_len_fields(&mut self) -> bool {
+
+        if self.incl_len <= self.orig_len {
+            return false;
+        }
+
+        ::std::mem::swap(&mut self.incl_len, &mut self.orig_len);
+        true
+    }
+
+    /// Replaces the timestamp of the packet by a `Duration` since the epoch.
+    ///
+    /// The timestamp resolution is kept, the sub-second part is truncated to microseconds if needed.
+    ///
+    /// # Errors
+    /// Return an error if the seconds don't fit in the 32 bits of `ts_sec`.
+    pub fn set_timestamp(&mut self, ts: Duration) -> ResultChain<()> {
+
+        if ts.as_secs() > u64::from(u32::MAX) {
+            bail!(ErrorKind::WrongField(format!("PacketHeader.ts_sec = {} > u32::MAX", ts.as_secs())));
+        }
+
+        self.ts_sec = ts.as_secs() as u32;
+        self.ts_usec = match self.ts_resolution {
+            TsResolution::MicroSecond => ts.subsec_micros(),
+            TsResolution::NanoSecond => ts.subsec_nanos()
+        };
+
+        Ok(())
+    }
+
+    /// Returns the timestamp of the packet as a UTC `SystemTime`, corrected with the thiszone field of the global header.
+    ///
+    /// See `Packet::timestamp_utc` for the direction of the correction.
+    pub fn timestamp_utc(&self, header: &PcapHeader) -> SystemTime {
+
+        let timestamp = self.timestamp();
+        let correction = Duration::from_secs(u64::from(header.thiszone().unsigned_abs()));
+
+        // Local time = UTC + thiszone
+        if header.thiszone() >= 0 {
+            timestamp - correction
+        }
+        else {
+            timestamp + correction
+        }
     }
 
     /// Convert the `PacketHeader` to a `Vec<u8>`.
     pub fn to_array<B: ByteOrder>(&self) -> ResultChain<Vec<u8>> {
+        Ok(self.bytes::<B>().to_vec())
+    }
+
+    /// Converts the `PacketHeader` to its 16 bytes, in the given endianness.
+    ///
+    /// The extra fields of the modified pcap format are written by `modified_fields_to_array`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::{Endianness, PacketHeader};
+    ///
+    /// let header = PacketHeader::new(1, 2, 60);
+    /// assert_eq!(header.to_bytes(Endianness::Big), [0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 60, 0, 0, 0, 60]);
+    /// ```
+    pub fn to_bytes(&self, endianness: Endianness) -> [u8; 16] {
+
+        match endianness {
+            Endianness::Big => self.bytes::<BigEndian>(),
+            Endianness::Little => self.bytes::<LittleEndian>()
+        }
+    }
+
+    /// Writes the 16 bytes of the packet header
+    fn bytes<B: ByteOrder>(&self) -> [u8; 16] {
+
+        let mut bytes = [0u8; 16];
 
-        let mut out = Vec::with_capacity(16);
+        B::write_u32(&mut bytes[0..4], self.ts_sec);
+        B::write_u32(&mut bytes[4..8], self.ts_usec);
+        B::write_u32(&mut bytes[8..12], self.incl_len);
+        B::write_u32(&mut bytes[12..16], self.orig_len);
 
-        out.write_u32::<B>(self.ts_sec)?;
-        out.write_u32::<B>(self.ts_usec)?;
-        out.write_u32::<B>(self.incl_len)?;
-        out.write_u32::<B>(self.orig_len)?;
+        bytes
+    }
+
+    /// Convert the extra fields of the modified pcap format to a `Vec<u8>`.
+    ///
+    /// The missing fields are written as 0.
+    pub fn modified_fields_to_array<B: ByteOrder>(&self) -> ResultChain<Vec<u8>> {
+
+        let mut out = Vec::with_capacity(8);
+
+        out.write_u32::<B>(self.ifindex.unwrap_or(0))?;
+        out.write_u16::<B>(self.protocol.unwrap_or(0))?;
+        out.write_u8(self.pkt_type.unwrap_or(0))?;
+        out.write_u8(0)?;
 
         Ok(out)
     }
@@ -89,7 +321,12 @@ impl PacketHeader {
 /// Represents a pcap packet.
 ///
 /// The payload can be owned or borrowed.
-#[derive(Clone, Debug)]
+///
+/// Its `Debug` output only shows the first 16 bytes of the payload, in hexadecimal, followed by the length of the payload.
+///
+/// Two packets are equal if their headers, timestamp resolution included, and their payload bytes are,
+/// whether the payloads are borrowed or owned.
+#[derive(Clone, PartialEq, Eq)]
 pub struct Packet<'a> {
 
     /// Header of the packet
@@ -99,6 +336,43 @@ pub struct Packet<'a> {
     pub data: Cow<'a, [u8]>
 }
 
+impl<'a> fmt::Debug for Packet<'a> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        f.debug_struct("Packet")
+            .field("header", &self.header)
+            .field("data", &PayloadPreview(&self.data))
+            .finish()
+    }
+}
+
+/// Prints the start of a payload, eliding the rest
+struct PayloadPreview<'a>(&'a [u8]);
+
+impl<'a> fmt::Debug for PayloadPreview<'a> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        let len = self.0.len().min(DEBUG_PAYLOAD_LEN);
+
+        write!(f, "[")?;
+        for (i, byte) in self.0[..len].iter().enumerate() {
+
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+
+        if self.0.len() > len {
+            write!(f, " ..")?;
+        }
+
+        write!(f, "] ({} bytes)", self.0.len())
+    }
+}
+
 
 impl<'a> Packet<'a> {
 
@@ -108,7 +382,7 @@ impl<'a> Packet<'a> {
         let header = PacketHeader::new(ts_sec, ts_usec, len);
 
         Packet {
-            header: header,
+            header,
             data: Cow::Borrowed(data)
         }
     }
@@ -119,18 +393,111 @@ impl<'a> Packet<'a> {
         let header = PacketHeader::new(ts_sec, ts_usec, len);
 
         Packet {
-            header: header,
+            header,
             data: Cow::Owned(data)
         }
     }
 
+    /// Create a new `Packet` whose `incl_len` is the length of the payload.
+    ///
+    /// `ts_frac` is the sub-second part of the timestamp in microseconds.
+    ///
+    /// # Errors
+    /// Return an error if the payload is longer than `u32::MAX` or than `orig_len`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::Packet;
+    ///
+    /// // A 60 bytes long packet captured with a snaplen of 32
+    /// let data = vec![0u8; 32];
+    /// let packet = Packet::from_data(1_331_901_000, 0, data.into(), 60).unwrap();
+    ///
+    /// assert_eq!(packet.incl_len(), 32);
+    /// ```
+    pub fn from_data(ts_sec: u32, ts_frac: u32, data: Cow<'a, [u8]>, orig_len: u32) -> ResultChain<Packet<'a>> {
+
+        if data.len() > u32::MAX as usize {
+            bail!(ErrorKind::WrongField(format!("Packet.data.len() = {} > u32::MAX", data.len())));
+        }
+
+        let incl_len = data.len() as u32;
+        if incl_len > orig_len {
+            bail!(ErrorKind::WrongField(format!("PacketHeader.incl_len ({}) > PacketHeader.orig_len ({})", incl_len, orig_len)));
+        }
+
+        Ok(
+            Packet {
+                header: PacketHeader {
+                    incl_len,
+                    orig_len,
+                    ..PacketHeader::new(ts_sec, ts_frac, 0)
+                },
+                data
+            }
+        )
+    }
+
+    /// Create a new owned `Packet` from a timestamp since the epoch and a whole payload.
+    ///
+    /// The timestamp is truncated to the microsecond.
+    ///
+    /// # Errors
+    /// Return an error if the payload is longer than `u32::MAX` or if the timestamp is past 2106.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use std::time::Duration;
+    /// use pcap_file::Packet;
+    ///
+    /// let packet = Packet::from_duration(Duration::from_millis(1_331_901_000_500), vec![0u8; 60]).unwrap();
+    ///
+    /// assert_eq!(packet.header.ts_usec, 500_000);
+    /// ```
+    pub fn from_duration(ts: Duration, data: Vec<u8>) -> ResultChain<Packet<'static>> {
+
+        if ts.as_secs() > u64::from(u32::MAX) {
+            bail!(ErrorKind::WrongField(format!("PacketHeader.ts_sec = {} > u32::MAX", ts.as_secs())));
+        }
+
+        let orig_len = data.len().min(u32::MAX as usize) as u32;
+        Packet::from_data(ts.as_secs() as u32, ts.subsec_micros(), Cow::Owned(data), orig_len)
+    }
+
     /// Create a new owned `Packet` from a reader.
-    pub fn from_reader<R: Read, B: ByteOrder>(reader: &mut R) -> ResultChain<Packet<'static>> {
+    ///
+    /// The packets longer than `DEFAULT_MAX_PACKET_LEN` are refused, see `from_reader_with_max`.
+    pub fn from_reader<R: Read, B: ByteOrder>(reader: &mut R, ts_resolution: TsResolution) -> ResultChain<Packet<'static>> {
+        Packet::from_reader_with_max::<R, B>(reader, ts_resolution, DEFAULT_MAX_PACKET_LEN)
+    }
 
-        let header = PacketHeader::from_reader::<R, B>(reader)?;
+    /// Create a new owned `Packet` from a reader, refusing the packets longer than `max_len` with a `PacketTooLarge` error.
+    ///
+    /// The payload is allocated from the length declared in the packet header, the maximum bounds the memory used by untrusted data.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate byteorder;
+    /// # extern crate pcap_file;
+    /// use byteorder::BigEndian;
+    /// use pcap_file::{Endianness, Packet, PacketHeader, TsResolution};
+    ///
+    /// let mut record = PacketHeader::new(0, 0, 100).to_bytes(Endianness::Big).to_vec();
+    /// record.extend_from_slice(&[0u8; 100]);
+    ///
+    /// assert!(Packet::from_reader_with_max::<_, BigEndian>(&mut &record[..], TsResolution::MicroSecond, 50).is_err());
+    /// assert!(Packet::from_reader_with_max::<_, BigEndian>(&mut &record[..], TsResolution::MicroSecond, 100).is_ok());
+    /// ```
+    pub fn from_reader_with_max<R: Read, B: ByteOrder>(reader: &mut R, ts_resolution: TsResolution, max_len: u32) -> ResultChain<Packet<'static>> {
+
+        let header = PacketHeader::from_reader::<R, B>(reader, ts_resolution)?;
+
+        if header.incl_len > max_len {
+            bail!(ErrorKind::PacketTooLarge(header.incl_len, max_len));
+        }
 
         let mut bytes = vec![0u8; header.incl_len as usize];
-        reader.read_exact(&mut bytes)?;
+        read_full(reader, &mut bytes)?;
 
         Ok(
             Packet {
@@ -142,11 +509,11 @@ impl<'a> Packet<'a> {
     }
 
     /// Create a new borrowed `Packet` from a slice.
-    pub fn from_slice<B: ByteOrder>(slice: &[u8]) -> ResultChain<Packet> {
+    pub fn from_slice<B: ByteOrder>(slice: &[u8], ts_resolution: TsResolution) -> ResultChain<Packet<'_>> {
 
-        let mut slice = &slice[..];
+        let mut slice = slice;
 
-        let header = PacketHeader::from_reader::<_, B>(&mut slice)?;
+        let header = PacketHeader::from_reader::<_, B>(&mut slice, ts_resolution)?;
 
         if header.incl_len > slice.len() as u32 {
             bail!(ErrorKind::BufferUnderflow(header.incl_len as u64, slice.len() as u64))
@@ -156,18 +523,511 @@ impl<'a> Packet<'a> {
         Ok(
             Packet {
 
-                header : header,
+                header,
                 data : Cow::Borrowed(&slice[0..len])
             }
         )
     }
 
+    /// Parses the packet record at the start of `input`, in the format given by the global header of the file.
+    ///
+    /// Returns `None` if `input` doesn't hold a whole record yet, or the borrowed `Packet` with the number of bytes
+    /// of its record, after which the next record starts.
+    ///
+    /// # Errors
+    /// Return an error if the packet header is invalid or if the packet is longer than `DEFAULT_MAX_PACKET_LEN`,
+    /// see `parse_one_with_max`.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use pcap_file::{Packet, PcapHeader};
+    ///
+    /// let header = PcapHeader::default();
+    /// let mut buffer: Vec<u8> = Vec::new();
+    ///
+    /// // Data received from a socket, without the global header
+    /// let received: &[u8] = &[];
+    /// buffer.extend_from_slice(received);
+    ///
+    /// let mut consumed = 0;
+    /// while let Some((packet, len)) = Packet::parse_one(&header, &buffer[consumed..]).unwrap() {
+    ///     println!("{} bytes", packet.data.len());
+    ///     consumed += len;
+    /// }
+    ///
+    /// // Keep the incomplete record for the next data
+    /// buffer.drain(..consumed);
+    /// ```
+    pub fn parse_one<'b>(header: &PcapHeader, input: &'b [u8]) -> ResultChain<Option<(Packet<'b>, usize)>> {
+        Packet::parse_one_with_max(header, input, DEFAULT_MAX_PACKET_LEN)
+    }
+
+    /// Parses the packet record at the start of `input` like `parse_one`, refusing the packets longer than `max_len`.
+    ///
+    /// # Errors
+    /// Return an error if the packet header is invalid or if the packet is longer than `max_len`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::{Packet, PcapHeader, PcapWriter};
+    ///
+    /// let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    /// pcap_writer.write(0, 0, &[0u8; 1000]).unwrap();
+    /// let out = pcap_writer.into_writer().unwrap();
+    ///
+    /// let header = PcapHeader::default();
+    /// assert!(Packet::parse_one_with_max(&header, &out[24..], 100).is_err());
+    /// assert!(Packet::parse_one_with_max(&header, &out[24..], 1000).unwrap().is_some());
+    /// ```
+    pub fn parse_one_with_max<'b>(header: &PcapHeader, input: &'b [u8], max_len: u32) -> ResultChain<Option<(Packet<'b>, usize)>> {
+
+        let header_len = header.packet_header_len();
+        if input.len() < header_len {
+            return Ok(None);
+        }
+
+        let packet_header = header.read_packet_header(&mut &input[..header_len], false, false)?;
+
+        // Refused before waiting for its payload
+        if packet_header.incl_len > max_len {
+            bail!(ErrorKind::PacketTooLarge(packet_header.incl_len, max_len));
+        }
+
+        let len = header_len + packet_header.incl_len as usize;
+        if input.len() < len {
+            return Ok(None);
+        }
+
+        let packet = Packet {
+            header: packet_header,
+            data: Cow::Borrowed(&input[header_len..len])
+        };
+
+        Ok(Some((packet, len)))
+    }
+
+    /// Returns the number of octets of the packet saved in the file.
+    ///
+    /// It is lower than `orig_len()` when the packet was truncated to the snaplen during the capture.
+    pub fn incl_len(&self) -> u32 {
+        self.header.incl_len
+    }
+
+    /// Returns the original length of the packet on the wire.
+    pub fn orig_len(&self) -> u32 {
+        self.header.orig_len
+    }
+
+    /// Returns true if the packet was truncated to the snaplen during the capture: only its first `incl_len()`
+    /// octets were saved.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::Packet;
+    ///
+    /// // A 60 bytes long packet captured with a snaplen of 32
+    /// let packet = Packet::from_data(0, 0, vec![0u8; 32].into(), 60).unwrap();
+    /// assert!(packet.is_truncated());
+    /// ```
+    pub fn is_truncated(&self) -> bool {
+        self.header.is_truncated()
+    }
+
+    /// Returns the number of octets the packet took in the file it was read from: its header followed by `incl_len` octets.
+    ///
+    /// The header takes 16 bytes, or 24 if the packet was read from a file of the modified pcap format, the one
+    /// that sets `ifindex`. Use `record_len_in` for the size of the packet once written to a given file.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::{Packet, PcapHeader};
+    ///
+    /// let packet = Packet::new(0, 0, 60, &[0u8; 60]);
+    ///
+    /// // Size of a file holding only this packet
+    /// assert_eq!(PcapHeader::SIZE + packet.record_len(), 24 + 16 + 60);
+    /// ```
+    pub fn record_len(&self) -> usize {
+
+        let header_len = if self.header.ifindex.is_some() { PcapHeader::MAX_PACKET_HEADER_LEN } else { 16 };
+        header_len + self.header.incl_len as usize
+    }
+
+    /// Returns the number of octets `PcapWriter::write_packet` writes for the packet in a file with the given global header.
+    ///
+    /// The header takes `header.packet_header_len()` bytes and the data is truncated to the snaplen of the file.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::{Packet, PcapHeader};
+    ///
+    /// let packet = Packet::new(0, 0, 60, &[0u8; 60]);
+    ///
+    /// let header = PcapHeader { snaplen: 32, ..PcapHeader::default() };
+    /// assert_eq!(packet.record_len_in(&header), 16 + 32);
+    /// ```
+    pub fn record_len_in(&self, header: &PcapHeader) -> usize {
+        header.packet_header_len() + self.data.len().min(header.effective_snaplen() as usize)
+    }
+
+    /// Returns the payload of the link layer, the network layer bytes, of a packet of the given datalink.
+    ///
+    /// Only the fixed link header given by `DataLink::header_len` is skipped, the VLAN tags of an ethernet frame are kept.
+    ///
+    /// Returns None if the length of the link header of the datalink isn't fixed or known,
+    /// or if the packet is shorter than the link header.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::{DataLink, Packet};
+    ///
+    /// let data = [0u8; 34];
+    /// let packet = Packet::new(0, 0, 34, &data);
+    ///
+    /// assert_eq!(packet.payload_after_link(DataLink::ETHERNET).unwrap().len(), 20);
+    /// assert_eq!(packet.payload_after_link(DataLink::IEEE802_11), None);
+    /// ```
+    pub fn payload_after_link(&self, datalink: DataLink) -> Option<&[u8]> {
+        self.data.get(datalink.header_len()?..)
+    }
+
+    /// Returns the payload without the 4 bytes of the Frame Check Sequence ending an ethernet frame.
+    ///
+    /// Pcap files don't tell whether the FCS was captured, it must only be called when it is known to be.
+    /// The payload is returned as is if the datalink isn't `DataLink::ETHERNET`, if the packet was truncated
+    /// to the snaplen, which cut its FCS, or if it is shorter than 4 bytes.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::{DataLink, Packet};
+    ///
+    /// let data = [0u8; 64];
+    /// let packet = Packet::new(0, 0, 64, &data);
+    ///
+    /// assert_eq!(packet.strip_fcs(DataLink::ETHERNET).len(), 60);
+    /// assert_eq!(packet.strip_fcs(DataLink::RAW).len(), 64);
+    /// ```
+    pub fn strip_fcs(&self, datalink: DataLink) -> &[u8] {
+
+        if datalink != DataLink::ETHERNET || self.is_truncated() || self.data.len() < 4 {
+            return &self.data;
+        }
+
+        &self.data[..self.data.len() - 4]
+    }
+
+    /// Appends the payload of the packet to `buf` and returns the range of `buf` where it was copied.
+    ///
+    /// Several payloads can be packed into a single buffer, next to a `Vec` of their headers and ranges,
+    /// instead of keeping one allocation per packet.
+    /// The range of an empty payload is empty and starts at the end of `buf`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::Packet;
+    ///
+    /// let mut arena = vec![0u8; 2];
+    ///
+    /// let range = Packet::new(0, 0, 3, &[1, 2, 3]).copy_payload_into(&mut arena);
+    /// assert_eq!(range, 2..5);
+    /// assert_eq!(arena[range], [1, 2, 3]);
+    ///
+    /// assert_eq!(Packet::new(0, 0, 0, &[]).copy_payload_into(&mut arena), 5..5);
+    /// ```
+    pub fn copy_payload_into(&self, buf: &mut Vec<u8>) -> Range<usize> {
+
+        let start = buf.len();
+        buf.extend_from_slice(&self.data);
+
+        start..buf.len()
+    }
+
+    /// Returns the timestamp of the packet as a `Duration` since the epoch.
+    ///
+    /// The sub-second part is interpreted with the timestamp resolution of the header.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use std::time::Duration;
+    /// use pcap_file::Packet;
+    ///
+    /// let packet = Packet::new(10, 500, 0, &[]);
+    /// assert_eq!(packet.duration_since_epoch(), Duration::new(10, 500_000));
+    /// ```
+    pub fn duration_since_epoch(&self) -> Duration {
+        self.header.duration_since_epoch()
+    }
+
+    /// Returns the timestamp of the packet as a `SystemTime`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use pcap_file::Packet;
+    ///
+    /// let packet = Packet::new(10, 500, 0, &[]);
+    /// assert_eq!(packet.timestamp(), UNIX_EPOCH + Duration::new(10, 500_000));
+    /// ```
+    pub fn timestamp(&self) -> SystemTime {
+        self.header.timestamp()
+    }
+
+    /// Returns the timestamp of the packet as whole seconds and a nanosecond remainder,
+    /// its sub-second part being in the `res` resolution.
+    ///
+    /// `res` is the timestamp resolution of the file the packet belongs to, given by `PcapHeader::ts_resolution()`,
+    /// and replaces the one of the packet header: a packet built with `Packet::new` is in microseconds.
+    /// The remainder is always in nanoseconds, a sub-second part out of range being saturated like in `duration_since_epoch`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::{Packet, TsResolution};
+    ///
+    /// let packet = Packet::new(10, 500, 0, &[]);
+    /// assert_eq!(packet.timestamp_parts(TsResolution::MicroSecond), (10, 500_000));
+    /// assert_eq!(packet.timestamp_parts(TsResolution::NanoSecond), (10, 500));
+    /// ```
+    pub fn timestamp_parts(&self, res: TsResolution) -> (u64, u32) {
+
+        let header = PacketHeader { ts_resolution: res, ..self.header };
+        let ts = header.duration_since_epoch();
+
+        (ts.as_secs(), ts.subsec_nanos())
+    }
+
+    /// Returns the timestamp of the packet as a UTC `SystemTime`, corrected with the thiszone field of the global header.
+    ///
+    /// The thiszone field is the offset in seconds of the timezone of the timestamps from UTC,
+    /// positive east of Greenwich: a file whose timestamps are in UTC+1 has a thiszone of 3600.
+    /// The correction is subtracted from the timestamp, so UTC = timestamp - thiszone.
+    ///
+    /// Almost every file has a thiszone of 0, in which case this is the same as `timestamp()`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use pcap_file::{Packet, PcapHeader};
+    ///
+    /// let header = PcapHeader { ts_correction: 3600, ..PcapHeader::default() };
+    /// let packet = Packet::new(7200, 0, 0, &[]);
+    ///
+    /// assert_eq!(packet.timestamp_utc(&header), UNIX_EPOCH + Duration::from_secs(3600));
+    /// ```
+    pub fn timestamp_utc(&self, header: &PcapHeader) -> SystemTime {
+        self.header.timestamp_utc(header)
+    }
+
     /// Convert a borrowed `Packet` to an owned one.
+    ///
+    /// An owned payload is moved, a borrowed one is copied.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use pcap_file::{Packet, PcapSliceReader};
+    ///
+    /// let data = std::fs::read("test.pcap").expect("Error reading file");
+    ///
+    /// // Keep the packets past the lifetime of the slice
+    /// let packets: Vec<Packet<'static>> = PcapSliceReader::new(&data).unwrap()
+    ///     .map(|pcap| pcap.unwrap().into_owned())
+    ///     .collect();
+    /// ```
     pub fn into_owned(self) -> Packet<'static> {
         Packet {
             header: self.header,
             data: Cow::Owned(self.data.into_owned())
         }
     }
+
+    /// Copy a `Packet`, borrowed or owned, to an owned one.
+    ///
+    /// Unlike `into_owned`, the original `Packet` is kept, so its payload is always copied.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::Packet;
+    ///
+    /// let data = [0u8; 10];
+    /// let packet = Packet::new(0, 0, 10, &data);
+    ///
+    /// let owned: Packet<'static> = packet.to_owned();
+    /// assert_eq!(owned.data, packet.data);
+    /// ```
+    pub fn to_owned(&self) -> Packet<'static> {
+        Packet {
+            header: self.header,
+            data: Cow::Owned(self.data.to_vec())
+        }
+    }
 }
 
+/// Builds a `Packet`, checking that its lengths are consistent.
+///
+/// The incl_len is the length of the payload, the orig_len defaults to it.
+/// The timestamp defaults to the epoch and its resolution to the microsecond.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use pcap_file::PacketBuilder;
+///
+/// // A 60 bytes long packet captured with a snaplen of 32
+/// let packet = PacketBuilder::new()
+///     .timestamp(Duration::from_millis(1_331_901_000_500))
+///     .data(vec![0u8; 32])
+///     .orig_len(60)
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!((packet.header.ts_usec, packet.incl_len(), packet.orig_len()), (500_000, 32, 60));
+/// assert!(PacketBuilder::new().data(&[0u8; 32][..]).orig_len(20).build().is_err());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PacketBuilder<'a> {
+    timestamp: Duration,
+    ts_resolution: TsResolution,
+    data: Cow<'a, [u8]>,
+    orig_len: Option<u32>
+}
+
+impl<'a> PacketBuilder<'a> {
+
+    /// Create a new `PacketBuilder` of an empty packet.
+    pub fn new() -> PacketBuilder<'a> {
+        PacketBuilder::default()
+    }
+
+    /// Sets the timestamp of the packet, as a `Duration` since the epoch.
+    pub fn timestamp(mut self, timestamp: Duration) -> PacketBuilder<'a> {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Sets the timestamp resolution of the packet, the timestamp is truncated to it.
+    pub fn ts_resolution(mut self, ts_resolution: TsResolution) -> PacketBuilder<'a> {
+        self.ts_resolution = ts_resolution;
+        self
+    }
+
+    /// Sets the payload of the packet, borrowed or owned.
+    pub fn data<D: Into<Cow<'a, [u8]>>>(mut self, data: D) -> PacketBuilder<'a> {
+        self.data = data.into();
+        self
+    }
+
+    /// Sets the original length of the packet on the wire.
+    pub fn orig_len(mut self, orig_len: u32) -> PacketBuilder<'a> {
+        self.orig_len = Some(orig_len);
+        self
+    }
+
+    /// Builds the `Packet`.
+    ///
+    /// # Errors
+    /// Return an error if the payload is longer than `u32::MAX` or than the orig_len,
+    /// or if the timestamp is past 2106.
+    pub fn build(self) -> ResultChain<Packet<'a>> {
+
+        let orig_len = self.orig_len.unwrap_or_else(|| self.data.len().min(u32::MAX as usize) as u32);
+
+        let mut packet = Packet::from_data(0, 0, self.data, orig_len)?;
+        packet.header.ts_resolution = self.ts_resolution;
+        packet.header.set_timestamp(self.timestamp)?;
+
+        Ok(packet)
+    }
+}
+
+/// Reads one packet record, a packet header followed by its payload, from a stream positioned on it.
+///
+/// It is meant for the streams whose global header was consumed by the caller, or which mix packet records with other data:
+/// nothing is read past the packet. `B` is the endianness of the file and `ts_resolution` its timestamp resolution,
+/// given by its global header.
+///
+/// Returns None if the stream ends cleanly, before the first byte of the packet header.
+///
+/// # Errors
+/// Return a `TruncatedPacket` error if the stream ends in the middle of the packet,
+/// and an error if the packet header is invalid, see `Packet::from_reader`. The packets longer than
+/// `DEFAULT_MAX_PACKET_LEN` are refused, see `read_packet_with_max`.
+///
+/// # Examples
+/// ```rust,no_run
+/// # extern crate byteorder;
+/// # extern crate pcap_file;
+/// use std::fs::File;
+/// use std::io::{BufReader, Read};
+/// use byteorder::LittleEndian;
+/// use pcap_file::{read_packet, TsResolution};
+///
+/// let mut reader = BufReader::new(File::open("test.pcap").expect("Error opening file"));
+///
+/// // Skip the global header
+/// reader.read_exact(&mut [0; 24]).unwrap();
+///
+/// while let Some(packet) = read_packet::<_, LittleEndian>(&mut reader, TsResolution::MicroSecond).unwrap() {
+///     println!("{:?}", packet);
+/// }
+/// ```
+pub fn read_packet<R: Read, B: ByteOrder>(reader: &mut R, ts_resolution: TsResolution) -> ResultChain<Option<Packet<'static>>> {
+    read_packet_with_max::<R, B>(reader, ts_resolution, DEFAULT_MAX_PACKET_LEN)
+}
+
+/// Reads one packet record from a stream positioned on it like `read_packet`, refusing the packets longer than `max_len`.
+///
+/// # Errors
+/// Return a `TruncatedPacket` error if the stream ends in the middle of the packet, and an error if the packet header
+/// is invalid or if the packet is longer than `max_len`.
+///
+/// # Examples
+/// ```rust,no_run
+/// # extern crate byteorder;
+/// # extern crate pcap_file;
+/// use std::fs::File;
+/// use std::io::{BufReader, Read};
+/// use byteorder::LittleEndian;
+/// use pcap_file::{read_packet_with_max, TsResolution};
+///
+/// let mut reader = BufReader::new(File::open("jumbo.pcap").expect("Error opening file"));
+/// reader.read_exact(&mut [0; 24]).unwrap();
+///
+/// while let Some(packet) = read_packet_with_max::<_, LittleEndian>(&mut reader, TsResolution::MicroSecond, 16 * 1024 * 1024).unwrap() {
+///     println!("{:?}", packet);
+/// }
+/// ```
+pub fn read_packet_with_max<R: Read, B: ByteOrder>(reader: &mut R, ts_resolution: TsResolution, max_len: u32) -> ResultChain<Option<Packet<'static>>> {
+
+    // The first byte tells a clean end of stream from a truncated packet header
+    let mut first = [0u8; 1];
+    loop {
+
+        match reader.read(&mut first) {
+            Ok(0) => return Ok(None),
+            Ok(_) => break,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {},
+            Err(err) => return Err(err.into())
+        }
+    }
+
+    Packet::from_reader_with_max::<_, B>(&mut (&first[..]).chain(reader), ts_resolution, max_len).map(Some)
+}
+
+/// Fills `buf` from `reader`, whatever the number of bytes returned by each read.
+///
+/// Returns a `TruncatedPacket` error if the data stream ends before `buf` is full.
+pub(crate) fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> ResultChain<()> {
+
+    let mut got = 0;
+    while got < buf.len() {
+
+        match reader.read(&mut buf[got..]) {
+            Ok(0) => bail!(ErrorKind::TruncatedPacket(buf.len(), got)),
+            Ok(nb_read) => got += nb_read,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {},
+            Err(err) => return Err(err.into())
+        }
+    }
+
+    Ok(())
+}
@@ -6,8 +6,10 @@ use byteorder::*;
 
 use errors::*;
 
+use packet::{PacketHeader, DEFAULT_MAX_PACKET_LEN};
+
 /// Struct that represents the global Pcap header of a Pcap file
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct PcapHeader {
 
     /// Magic number
@@ -26,6 +28,8 @@ pub struct PcapHeader {
     pub ts_accuracy: u32,
 
     /// Max length of captured packet, typically 65535
+    ///
+    /// Like libpcap, a snaplen of 0 is read as 65535, see `effective_snaplen()`.
     pub snaplen: u32,
 
     /// DataLink type (first layer in the packet (u32))
@@ -48,8 +52,38 @@ impl Default for PcapHeader {
 }
 
 
+/// Checks applied to the snaplen of a global header when it is read.
+///
+/// By default, the snaplen can't exceed `DEFAULT_MAX_PACKET_LEN`, the maximum snaplen of libpcap,
+/// and a snaplen of 0 is accepted: it is kept in the header and read as 65535, see `PcapHeader::effective_snaplen()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SnaplenCheck {
+
+    /// Greatest snaplen accepted
+    pub max: u32,
+
+    /// Refuses a snaplen of 0 instead of reading it as 65535
+    pub strict: bool
+}
+
+impl Default for SnaplenCheck {
+
+    fn default() -> Self {
+        SnaplenCheck {
+            max: DEFAULT_MAX_PACKET_LEN,
+            strict: false
+        }
+    }
+}
+
 impl PcapHeader {
 
+    /// Length of the global header in a file, in octets.
+    pub const SIZE: usize = 24;
+
+    /// Greatest length of a packet header in a file, in octets: the one of the modified pcap format.
+    pub const MAX_PACKET_HEADER_LEN: usize = 24;
+
     /// Creates a new `PcapHeader` with the following parameters:
     ///
     /// ```rust,ignore
@@ -73,24 +107,40 @@ impl PcapHeader {
     }
 
     /// Parses a `Reader` and creates a new `PcapHeader` from it if possible
+    ///
+    /// The snaplen is checked with the default `SnaplenCheck`: a snaplen of 0, written by some tools,
+    /// is kept and read as 65535 like libpcap does, and a snaplen greater than `DEFAULT_MAX_PACKET_LEN` is refused.
     pub fn from_reader<R: Read>(reader: &mut R) -> ResultChain<PcapHeader> {
+        PcapHeader::from_reader_checked(reader, SnaplenCheck::default())
+    }
+
+    /// Parses a `Reader` and creates a new `PcapHeader` from it if possible, checking its snaplen with `check`
+    ///
+    /// # Errors
+    /// Return an `UnknownMagic` error if the magic number is not the one of a pcap file,
+    /// an `InvalidSnaplen` error if the snaplen is greater than `check.max`, or 0 with `check.strict`.
+    pub fn from_reader_checked<R: Read>(reader: &mut R, check: SnaplenCheck) -> ResultChain<PcapHeader> {
 
         let magic_number = reader.read_u32::<BigEndian>()?;
 
-        match magic_number {
+        let header = match magic_format(magic_number) {
 
-            0xa1b2c3d4 | 0xa1b23c4d => return init_pcap_header::<_, BigEndian>(reader, magic_number),
-            0xd4c3b2a1 | 0x4d3cb2a1 => return init_pcap_header::<_, LittleEndian>(reader, magic_number),
-            _ => bail!(ErrorKind::WrongField(format!("Wrong PacketHeader.magic_number: {}", magic_number)))
+            Some((Endianness::Big, _)) => init_pcap_header::<_, BigEndian>(reader, magic_number)?,
+            Some((Endianness::Little, _)) => init_pcap_header::<_, LittleEndian>(reader, magic_number)?,
+            None => bail!(ErrorKind::UnknownMagic(magic_number))
         };
 
+        header.check_snaplen(check)?;
+
+        return Ok(header);
+
         // Inner function used for the initialisation of the `PcapHeader`
         fn init_pcap_header<R: Read, B: ByteOrder>(reader: &mut R, magic_number:u32) -> Result<PcapHeader, Error> {
 
             Ok(
                 PcapHeader {
 
-                    magic_number : magic_number,
+                    magic_number,
                     version_major : reader.read_u16::<B>()?,
                     version_minor : reader.read_u16::<B>()?,
                     ts_correction : reader.read_i32::<B>()?,
@@ -102,10 +152,21 @@ impl PcapHeader {
         }
     }
 
+    /// Returns an `InvalidSnaplen` error if the snaplen of the header doesn't pass `check`
+    pub(crate) fn check_snaplen(&self, check: SnaplenCheck) -> ResultChain<()> {
+
+        // A snaplen of 0 is kept as is, `effective_snaplen()` reads it as 65535
+        match self.snaplen {
+            0 if check.strict => bail!(ErrorKind::InvalidSnaplen(0, check.max)),
+            snaplen if snaplen > check.max => bail!(ErrorKind::InvalidSnaplen(snaplen, check.max)),
+            _ => Ok(())
+        }
+    }
+
     /// Convert a `PcapHeader` to a `Vec<u8>`.
     pub fn to_array<B: ByteOrder>(&self) -> ResultChain<Vec<u8>> {
 
-        let mut out = Vec::with_capacity(24);
+        let mut out = Vec::with_capacity(PcapHeader::SIZE);
 
         //The magic number is always read in BigEndian so it's always written in BigEndian too
         out.write_u32::<BigEndian>(self.magic_number)?;
@@ -119,6 +180,66 @@ impl PcapHeader {
         Ok(out)
     }
 
+    /// Return the DataLink type of the global header
+    ///
+    /// It gives the first layer of every packet of the file.
+    pub fn datalink(&self) -> DataLink {
+        self.datalink
+    }
+
+    /// Return the magic number of the global header, as read from the file.
+    ///
+    /// It is read in big endian, so a little endian file gives a swapped magic number such as 0xd4c3b2a1.
+    pub fn magic(&self) -> u32 {
+        self.magic_number
+    }
+
+    /// Return the (major, minor) version of the global header, usually (2, 4)
+    pub fn version(&self) -> (u16, u16) {
+        (self.version_major, self.version_minor)
+    }
+
+    /// Return the snaplen as written in the global header, which may be 0.
+    pub fn raw_snaplen(&self) -> u32 {
+        self.snaplen
+    }
+
+    /// Return the snaplen to apply to the packets.
+    ///
+    /// libpcap treats a snaplen of 0 as its default of 65535, and some tools write 0 relying on it:
+    /// such a snaplen is kept as is in the header, for the files to be rewritten unchanged,
+    /// but the packets are truncated and checked against 65535.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::PcapHeader;
+    ///
+    /// let header = PcapHeader { snaplen: 0, ..PcapHeader::default() };
+    /// assert_eq!(header.raw_snaplen(), 0);
+    /// assert_eq!(header.effective_snaplen(), 65535);
+    /// ```
+    pub fn effective_snaplen(&self) -> u32 {
+
+        match self.snaplen {
+            0 => 65535,
+            snaplen => snaplen
+        }
+    }
+
+    /// Return the thiszone field of the global header, the GMT to local timezone correction in seconds.
+    ///
+    /// It is 0 in the vast majority of files.
+    pub fn thiszone(&self) -> i32 {
+        self.ts_correction
+    }
+
+    /// Return the sigfigs field of the global header, the accuracy of the timestamps.
+    ///
+    /// It is 0 in the vast majority of files.
+    pub fn sigfigs(&self) -> u32 {
+        self.ts_accuracy
+    }
+
     /// Return the endianness of the global header
     ///
     /// # Panics
@@ -126,11 +247,9 @@ impl PcapHeader {
     /// Panics if the magic number is invalid
     pub fn endianness(&self) -> Endianness {
 
-        match self.magic_number {
-
-            0xa1b2c3d4 | 0xa1b23c4d => Endianness::Big,
-            0xd4c3b2a1 | 0x4d3cb2a1 => Endianness::Little,
-            _ => unreachable!("Wrong magic number, can't get the header's endianness")
+        match magic_format(self.magic_number) {
+            Some((endianness, _)) => endianness,
+            None => unreachable!("Wrong magic number, can't get the header's endianness")
         }
     }
 
@@ -141,32 +260,118 @@ impl PcapHeader {
     /// Panics if the magic number is invalid
     pub fn ts_resolution(&self) -> TsResolution {
 
-        match self.magic_number {
+        match magic_format(self.magic_number) {
+            Some((_, ts_resolution)) => ts_resolution,
+            None => unreachable!("Wrong magic number, can't get the header's timestamp resolution")
+        }
+    }
+
+    /// Return true if the file uses the modified pcap format (magic number 0xa1b2cd34).
+    ///
+    /// Its packet headers have 8 extra bytes holding the interface index, the protocol and the packet type.
+    pub fn is_modified(&self) -> bool {
+        self.magic_number == 0xa1b2cd34 || self.magic_number == 0x34cdb2a1
+    }
+
+    /// Return the length of the packet headers of the file: 16, or 24 in the modified pcap format.
+    pub fn packet_header_len(&self) -> usize {
+
+        if self.is_modified() {
+            PcapHeader::MAX_PACKET_HEADER_LEN
+        }
+        else {
+            16
+        }
+    }
+
+    /// Parses a packet header of the file, including the extra fields of the modified pcap format
+    ///
+    /// With `fix_orig_len`, an orig_len lower than the incl_len is raised to it instead of being an error.
+    /// With `swap_len_fields`, an incl_len greater than the orig_len is swapped with it, before `fix_orig_len` applies.
+    pub(crate) fn read_packet_header<R: Read>(&self, reader: &mut R, fix_orig_len: bool, swap_len_fields: bool) -> ResultChain<PacketHeader> {
 
-            0xa1b2c3d4 | 0xd4c3b2a1 => TsResolution::MicroSecond,
-            0xa1b23c4d | 0x4d3cb2a1 => TsResolution::NanoSecond,
-            _ => unreachable!("Wrong magic number, can't get the header's timestamp resolution")
+        match self.endianness() {
+            Endianness::Big => read_packet_header::<_, BigEndian>(self, reader, fix_orig_len, swap_len_fields),
+            Endianness::Little => read_packet_header::<_, LittleEndian>(self, reader, fix_orig_len, swap_len_fields)
         }
     }
+
+    /// Parses the packet header of the record starting with `bytes`, the readers of in-memory records share it
+    ///
+    /// Returns the header, whose incl_len is lowered to the effective snaplen like `PcapReader` does,
+    /// and the length of the payload stored in the file, to skip past the bytes cut.
+    pub(crate) fn read_record_header(&self, bytes: &[u8]) -> ResultChain<(PacketHeader, usize)> {
+
+        let mut header = self.read_packet_header(&mut &bytes[..self.packet_header_len()], false, false)?;
+        let stored_len = header.incl_len as usize;
+        header.incl_len = header.incl_len.min(self.effective_snaplen());
+
+        Ok((header, stored_len))
+    }
+}
+
+/// Returns the endianness and the timestamp resolution of a magic number read in big endian,
+/// or `None` if it is not the one of a pcap file.
+///
+/// The modified pcap format has microsecond timestamps.
+pub(crate) fn magic_format(magic_number: u32) -> Option<(Endianness, TsResolution)> {
+
+    match magic_number {
+
+        0xa1b2c3d4 | 0xa1b2cd34 => Some((Endianness::Big, TsResolution::MicroSecond)),
+        0xa1b23c4d => Some((Endianness::Big, TsResolution::NanoSecond)),
+        0xd4c3b2a1 | 0x34cdb2a1 => Some((Endianness::Little, TsResolution::MicroSecond)),
+        0x4d3cb2a1 => Some((Endianness::Little, TsResolution::NanoSecond)),
+        _ => None
+    }
+}
+
+// Inner function used by `PcapHeader::read_packet_header`
+fn read_packet_header<R: Read, B: ByteOrder>(pcap_header: &PcapHeader, reader: &mut R, fix_orig_len: bool, swap_len_fields: bool) -> ResultChain<PacketHeader> {
+
+    let mut header = if fix_orig_len || swap_len_fields {
+
+        let mut header = PacketHeader::from_reader_unchecked::<_, B>(reader, pcap_header.ts_resolution())?;
+        if swap_len_fields {
+            header.swap_len_fields();
+        }
+        if fix_orig_len {
+            header.fix_orig_len();
+        }
+
+        header
+    }
+    else {
+        PacketHeader::from_reader::<_, B>(reader, pcap_header.ts_resolution())?
+    };
+
+    if pcap_header.is_modified() {
+        header.read_modified_fields::<_, B>(reader)?;
+    }
+
+    Ok(header)
 }
 
 /// Represents the endianness of the global header
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Endianness {
     Big,
     Little
 }
 
 /// Represents each possible timestamp resolution of the global header
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub enum TsResolution {
+    #[default]
     MicroSecond,
     NanoSecond
 }
 
 /// Represents each possible Pcap datalink
+///
+/// Values without a dedicated variant are kept in `Unknown`.
 #[allow(non_camel_case_types)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum DataLink {
 
     NULL,
@@ -276,10 +481,123 @@ pub enum DataLink {
     RDS,
     USB_DARWIN,
     SDLC,
+    LINUX_SLL2,
 
     Unknown(u32)
 }
 
+impl DataLink {
+
+    /// Returns the length of the link-layer header of the packets, if it is fixed for this datalink.
+    ///
+    /// The network layer of a packet starts right after this header.
+    /// Returns None for the datalinks with a variable header length and for the unknown ones.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::DataLink;
+    ///
+    /// assert_eq!(DataLink::ETHERNET.header_len(), Some(14));
+    /// assert_eq!(DataLink::LINUX_SLL2.header_len(), Some(20));
+    /// assert_eq!(DataLink::IEEE802_11_RADIOTAP.header_len(), None);
+    /// ```
+    pub fn header_len(&self) -> Option<usize> {
+
+        match *self {
+            DataLink::RAW | DataLink::IPV4 | DataLink::IPV6 => Some(0),
+            DataLink::NULL | DataLink::LOOP => Some(4),
+            DataLink::ETHERNET => Some(14),
+            DataLink::LINUX_SLL => Some(16),
+            DataLink::LINUX_SLL2 => Some(20),
+            _ => None
+        }
+    }
+}
+
+/// Returns the length of the radiotap header at the start of a `DataLink::IEEE802_11_RADIOTAP` packet.
+///
+/// The 802.11 frame starts right after this header.
+/// Returns None if the packet is shorter than the header, or if the header is invalid.
+///
+/// # Examples
+/// ```rust
+/// use pcap_file::radiotap_len;
+///
+/// // Version 0, 8 bytes header without any field
+/// let packet = [0, 0, 8, 0, 0, 0, 0, 0, 0x80, 0];
+/// assert_eq!(radiotap_len(&packet), Some(8));
+/// assert_eq!(radiotap_len(&packet[..6]), None);
+/// ```
+pub fn radiotap_len(bytes: &[u8]) -> Option<usize> {
+    variable_header_len(bytes)
+}
+
+/// Returns the length of the PPI header at the start of a `DataLink::PPI` packet.
+///
+/// The frame of the datalink given by the PPI header starts right after this header.
+/// Returns None if the packet is shorter than the header, or if the header is invalid.
+///
+/// # Examples
+/// ```rust
+/// use pcap_file::ppi_len;
+///
+/// // Version 0, 8 bytes header without any field, 802.11 frame
+/// let packet = [0, 0, 8, 0, 105, 0, 0, 0, 0x80, 0];
+/// assert_eq!(ppi_len(&packet), Some(8));
+/// assert_eq!(ppi_len(&packet[..6]), None);
+/// ```
+pub fn ppi_len(bytes: &[u8]) -> Option<usize> {
+    variable_header_len(bytes)
+}
+
+/// Returns the protocol family of the BSD loopback header at the start of a `DataLink::NULL` or `DataLink::LOOP` packet.
+///
+/// The family is in the byte order of the capturing host for `DataLink::NULL`, which is the `endianness` of the file,
+/// and in network byte order for `DataLink::LOOP`.
+/// The network layer packet starts after the 4 bytes of the header.
+/// Returns None if the datalink isn't NULL or LOOP, or if the packet is shorter than the header.
+///
+/// # Examples
+/// ```rust
+/// use pcap_file::{loopback_af, DataLink, Endianness};
+///
+/// // AF_INET, then an IPv4 packet
+/// let packet = [2, 0, 0, 0, 0x45, 0];
+/// assert_eq!(loopback_af(&packet, DataLink::NULL, Endianness::Little), Some(2));
+/// assert_eq!(loopback_af(&[0, 0, 0, 2, 0x45, 0], DataLink::LOOP, Endianness::Little), Some(2));
+/// assert_eq!(loopback_af(&packet[..3], DataLink::NULL, Endianness::Little), None);
+/// ```
+pub fn loopback_af(bytes: &[u8], datalink: DataLink, endianness: Endianness) -> Option<u32> {
+
+    if bytes.len() < 4 {
+        return None;
+    }
+
+    match (datalink, endianness) {
+        (DataLink::NULL, Endianness::Little) => Some(LittleEndian::read_u32(&bytes[..4])),
+        (DataLink::NULL, Endianness::Big) | (DataLink::LOOP, _) => Some(BigEndian::read_u32(&bytes[..4])),
+        _ => None
+    }
+}
+
+/// Reads the length of a radiotap or PPI header: version (u8), flags or padding (u8), length (u16 little endian), ...
+fn variable_header_len(bytes: &[u8]) -> Option<usize> {
+
+    // Both headers are at least 8 bytes long, their first fields included
+    const MIN_LEN: usize = 8;
+
+    if bytes.len() < MIN_LEN || bytes[0] != 0 {
+        return None;
+    }
+
+    let len = LittleEndian::read_u16(&bytes[2..4]) as usize;
+    if len < MIN_LEN || len > bytes.len() {
+        return None;
+    }
+
+    Some(len)
+}
+
 impl From<u32> for DataLink {
 
     fn from(n: u32) -> DataLink {
@@ -392,6 +710,7 @@ impl From<u32> for DataLink {
             265 => DataLink::RDS,
             266 => DataLink::USB_DARWIN,
             268 => DataLink::SDLC,
+            276 => DataLink::LINUX_SLL2,
 
             _ => DataLink::Unknown(n)
         }
@@ -511,6 +830,7 @@ impl From<DataLink> for u32 {
             DataLink::RDS => 265,
             DataLink::USB_DARWIN => 266,
             DataLink::SDLC => 268,
+            DataLink::LINUX_SLL2 => 276,
 
             DataLink::Unknown(n) => n
         }
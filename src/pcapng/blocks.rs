@@ -0,0 +1,322 @@
+//! This module contains the pcapng blocks supported by the `PcapNgReader`.
+
+use std::time::Duration;
+
+use byteorder::*;
+
+use errors::*;
+
+use pcap_header::{DataLink, Endianness};
+
+/// Block type of a Section Header Block
+pub const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+
+/// Block type of an Interface Description Block
+pub const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+
+/// Block type of a Simple Packet Block
+pub const SIMPLE_PACKET_BLOCK: u32 = 0x0000_0003;
+
+/// Block type of an Enhanced Packet Block
+pub const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+
+/// Byte-order magic of a Section Header Block
+pub const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+/// Option code of the `if_tsresol` option of an Interface Description Block
+const IF_TSRESOL: u16 = 9;
+
+/// Represents a pcapng block.
+#[derive(Clone, Debug)]
+pub enum Block {
+
+    /// Section Header Block, starts a new section
+    SectionHeader(SectionHeaderBlock),
+
+    /// Interface Description Block, describes an interface of the current section
+    InterfaceDescription(InterfaceDescriptionBlock),
+
+    /// Enhanced Packet Block, a packet captured on a given interface
+    EnhancedPacket(EnhancedPacketBlock),
+
+    /// Simple Packet Block, a packet captured on the first interface
+    SimplePacket(SimplePacketBlock),
+
+    /// Any other block, left undecoded
+    Unknown(UnknownBlock)
+}
+
+/// Represents a block option.
+///
+/// The value is not decoded because its meaning depends on the block and the option code.
+#[derive(Clone, Debug)]
+pub struct BlockOption {
+
+    /// Option code
+    pub code: u16,
+
+    /// Raw value of the option, without padding
+    pub value: Vec<u8>
+}
+
+impl BlockOption {
+
+    /// Parses the options of a block up to the `opt_endofopt` option or the end of the slice.
+    pub fn parse_list<B: ByteOrder>(mut slice: &[u8]) -> ResultChain<Vec<BlockOption>> {
+
+        let mut options = Vec::new();
+
+        while slice.len() >= 4 {
+
+            let code = slice.read_u16::<B>()?;
+            let len = slice.read_u16::<B>()? as usize;
+
+            if code == 0 {
+                break;
+            }
+
+            let padded_len = (len + 3) & !3;
+            if padded_len > slice.len() {
+                bail!(ErrorKind::BufferUnderflow(padded_len as u64, slice.len() as u64));
+            }
+
+            options.push(
+                BlockOption {
+                    code,
+                    value: slice[..len].to_vec()
+                }
+            );
+
+            slice = &slice[padded_len..];
+        }
+
+        Ok(options)
+    }
+}
+
+/// Represents a Section Header Block.
+#[derive(Clone, Debug)]
+pub struct SectionHeaderBlock {
+
+    /// Endianness of every block of the section, given by the byte-order magic
+    pub endianness: Endianness,
+
+    /// Major version number
+    pub version_major: u16,
+
+    /// Minor version number
+    pub version_minor: u16,
+
+    /// Length in bytes of the section, -1 if unspecified
+    pub section_length: i64,
+
+    /// Options of the block
+    pub options: Vec<BlockOption>
+}
+
+impl SectionHeaderBlock {
+
+    /// Parses the body of a Section Header Block, starting at the byte-order magic.
+    pub fn from_slice(mut slice: &[u8]) -> ResultChain<SectionHeaderBlock> {
+
+        let magic = slice.read_u32::<BigEndian>()?;
+        let endianness = match magic {
+            BYTE_ORDER_MAGIC => Endianness::Big,
+            _ if magic.swap_bytes() == BYTE_ORDER_MAGIC => Endianness::Little,
+            _ => bail!(ErrorKind::WrongField(format!("Wrong SectionHeaderBlock.byte_order_magic: {:#x}", magic)))
+        };
+
+        // The byte-order magic is read in BigEndian, the rest of the block must use the real endianness
+        match endianness {
+            Endianness::Big => init_section_header::<BigEndian>(slice, endianness),
+            Endianness::Little => init_section_header::<LittleEndian>(slice, endianness)
+        }
+    }
+}
+
+// Inner function used for the initialisation of the `SectionHeaderBlock`
+fn init_section_header<B: ByteOrder>(mut slice: &[u8], endianness: Endianness) -> ResultChain<SectionHeaderBlock> {
+
+    let version_major = slice.read_u16::<B>()?;
+    let version_minor = slice.read_u16::<B>()?;
+    let section_length = slice.read_i64::<B>()?;
+
+    if version_major != 1 {
+        bail!(ErrorKind::WrongField(format!("SectionHeaderBlock.version_major = {}, only 1 is supported", version_major)));
+    }
+
+    Ok(
+        SectionHeaderBlock {
+            endianness,
+            version_major,
+            version_minor,
+            section_length,
+            options: BlockOption::parse_list::<B>(slice)?
+        }
+    )
+}
+
+/// Represents an Interface Description Block.
+#[derive(Clone, Debug)]
+pub struct InterfaceDescriptionBlock {
+
+    /// DataLink type of the packets captured on this interface
+    pub linktype: DataLink,
+
+    /// Max length of captured packet, 0 if unlimited
+    pub snaplen: u32,
+
+    /// Raw `if_tsresol` value, 6 (microseconds) if the option is absent
+    pub ts_resolution: u8,
+
+    /// Options of the block
+    pub options: Vec<BlockOption>
+}
+
+impl InterfaceDescriptionBlock {
+
+    /// Parses the body of an Interface Description Block.
+    pub fn from_slice<B: ByteOrder>(mut slice: &[u8]) -> ResultChain<InterfaceDescriptionBlock> {
+
+        let linktype = DataLink::from(slice.read_u16::<B>()? as u32);
+        let _reserved = slice.read_u16::<B>()?;
+        let snaplen = slice.read_u32::<B>()?;
+        let options = BlockOption::parse_list::<B>(slice)?;
+
+        let ts_resolution = options.iter()
+            .find(|opt| opt.code == IF_TSRESOL && opt.value.len() == 1)
+            .map(|opt| opt.value[0])
+            .unwrap_or(6);
+
+        Ok(
+            InterfaceDescriptionBlock {
+                linktype,
+                snaplen,
+                ts_resolution,
+                options
+            }
+        )
+    }
+
+    /// Converts a raw timestamp of this interface to a `Duration` since the epoch.
+    ///
+    /// If the most significant bit of `ts_resolution` is 0 the remaining bits are a negative power of 10,
+    /// otherwise they are a negative power of 2.
+    pub fn timestamp_to_duration(&self, timestamp: u64) -> Duration {
+
+        // Resolutions finer than a u64 can hold are clamped, they can't be represented anyway
+        let exponent = u32::from(self.ts_resolution & 0x7F);
+        let units_per_sec = if self.ts_resolution & 0x80 == 0 {
+            10u128.pow(exponent.min(19))
+        }
+        else {
+            1u128 << exponent.min(63)
+        };
+
+        let timestamp = u128::from(timestamp);
+        let secs = timestamp / units_per_sec;
+        let nanos = (timestamp % units_per_sec) * 1_000_000_000 / units_per_sec;
+
+        Duration::new(secs as u64, nanos as u32)
+    }
+}
+
+/// Represents an Enhanced Packet Block.
+#[derive(Clone, Debug)]
+pub struct EnhancedPacketBlock {
+
+    /// Index of the interface the packet was captured on, in the current section
+    pub interface_id: u32,
+
+    /// Timestamp of the packet since the epoch, using the resolution of its interface
+    pub timestamp: Duration,
+
+    /// Original length of the packet on the wire
+    pub original_len: u32,
+
+    /// Captured data of the packet
+    pub data: Vec<u8>,
+
+    /// Options of the block
+    pub options: Vec<BlockOption>
+}
+
+impl EnhancedPacketBlock {
+
+    /// Parses the body of an Enhanced Packet Block.
+    ///
+    /// The interfaces of the current section are needed to decode the timestamp.
+    pub fn from_slice<B: ByteOrder>(mut slice: &[u8], interfaces: &[InterfaceDescriptionBlock]) -> ResultChain<EnhancedPacketBlock> {
+
+        let interface_id = slice.read_u32::<B>()?;
+        let ts_high = slice.read_u32::<B>()?;
+        let ts_low = slice.read_u32::<B>()?;
+        let captured_len = slice.read_u32::<B>()? as usize;
+        let original_len = slice.read_u32::<B>()?;
+
+        let interface = match interfaces.get(interface_id as usize) {
+            Some(interface) => interface,
+            None => bail!(ErrorKind::WrongField(format!("EnhancedPacketBlock.interface_id = {} but only {} interfaces are described", interface_id, interfaces.len())))
+        };
+
+        let padded_len = (captured_len + 3) & !3;
+        if padded_len > slice.len() {
+            bail!(ErrorKind::BufferUnderflow(padded_len as u64, slice.len() as u64));
+        }
+
+        let timestamp = (u64::from(ts_high) << 32) | u64::from(ts_low);
+
+        Ok(
+            EnhancedPacketBlock {
+                interface_id,
+                timestamp: interface.timestamp_to_duration(timestamp),
+                original_len,
+                data: slice[..captured_len].to_vec(),
+                options: BlockOption::parse_list::<B>(&slice[padded_len..])?
+            }
+        )
+    }
+}
+
+/// Represents a Simple Packet Block.
+///
+/// A Simple Packet Block always refers to the first interface of the section and has no timestamp.
+#[derive(Clone, Debug)]
+pub struct SimplePacketBlock {
+
+    /// Original length of the packet on the wire
+    pub original_len: u32,
+
+    /// Captured data of the packet
+    pub data: Vec<u8>
+}
+
+impl SimplePacketBlock {
+
+    /// Parses the body of a Simple Packet Block.
+    pub fn from_slice<B: ByteOrder>(mut slice: &[u8]) -> ResultChain<SimplePacketBlock> {
+
+        let original_len = slice.read_u32::<B>()?;
+
+        // The captured length is not stored, it is bounded by the block length
+        let captured_len = (original_len as usize).min(slice.len());
+
+        Ok(
+            SimplePacketBlock {
+                original_len,
+                data: slice[..captured_len].to_vec()
+            }
+        )
+    }
+}
+
+/// Represents a block which is not decoded by the `PcapNgReader`.
+#[derive(Clone, Debug)]
+pub struct UnknownBlock {
+
+    /// Block type
+    pub block_type: u32,
+
+    /// Raw body of the block
+    pub body: Vec<u8>
+}
@@ -0,0 +1,39 @@
+//! This module contains the `PcapNgReader` struct which is used to read from a pcapng file
+//! and the `Block` enum which represents each supported pcapng block.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use std::fs::File;
+//! use pcap_file::pcapng::{Block, PcapNgReader};
+//!
+//! let file_in = File::open("test.pcapng").expect("Error opening file");
+//! let pcapng_reader = PcapNgReader::new(file_in).unwrap();
+//!
+//! // Read test.pcapng
+//! for block in pcapng_reader {
+//!
+//!     //Check if there is no error
+//!     let block = block.unwrap();
+//!
+//!     //Only keep the packets
+//!     if let Block::EnhancedPacket(packet) = block {
+//!         println!("Interface {}: {} bytes", packet.interface_id, packet.data.len());
+//!     }
+//! }
+//! ```
+
+mod blocks;
+pub use self::blocks::{
+    Block,
+    BlockOption,
+    EnhancedPacketBlock,
+    InterfaceDescriptionBlock,
+    SectionHeaderBlock,
+    SimplePacketBlock,
+    UnknownBlock
+};
+pub(crate) use self::blocks::SECTION_HEADER_BLOCK;
+
+mod reader;
+pub use self::reader::{PcapNgReader, DEFAULT_MAX_BLOCK_LEN};
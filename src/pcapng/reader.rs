@@ -0,0 +1,345 @@
+//! This module contains the `PcapNgReader` struct which is used to read from a pcapng file
+
+use std::io::Read;
+
+use byteorder::*;
+
+use errors::*;
+
+use packet::DEFAULT_MAX_PACKET_LEN;
+
+use pcap_header::Endianness;
+
+use peek_reader::PeekReader;
+
+use super::blocks::*;
+
+/// Default maximum length of the blocks read, `DEFAULT_MAX_PACKET_LEN` plus 4 KiB for the fields and the options of a block.
+///
+/// Readers refuse longer blocks instead of allocating them, a corrupt file could declare blocks of 4 GiB.
+pub const DEFAULT_MAX_BLOCK_LEN: u32 = DEFAULT_MAX_PACKET_LEN + 4 * 1024;
+
+/// This struct wraps another reader and enables it to read a PcapNg formated stream.
+///
+/// It implements the Iterator trait in order to read one block at a time.
+///
+/// The endianness is given by the Section Header Block of each section, and the Interface Description
+/// Blocks of the current section are kept to decode the packets which refer to them.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use pcap_file::pcapng::{Block, PcapNgReader};
+///
+/// let file_in = File::open("test.pcapng").expect("Error opening file");
+/// let pcapng_reader = PcapNgReader::new(file_in).unwrap();
+///
+/// // Read test.pcapng
+/// for block in pcapng_reader {
+///
+///     //Check if there is no error
+///     let block = block.unwrap();
+///
+///     //Print the packets
+///     if let Block::EnhancedPacket(packet) = block {
+///         println!("{:?}", packet.timestamp);
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PcapNgReader<T: Read> {
+
+    /// Section Header Block of the current section
+    pub section: SectionHeaderBlock,
+    interfaces: Vec<InterfaceDescriptionBlock>,
+    reader: PeekReader<T>,
+    max_block_len: u32,
+    /// Set after an error, the position of the next block being unknown
+    done: bool
+}
+
+impl <T:Read> PcapNgReader<T> {
+
+    /// Create a new PcapNgReader from an existing reader.
+    /// This function reads the first Section Header Block of the file to verify its integrity.
+    ///
+    /// The underlying reader must point to a valid pcapng file/stream.
+    ///
+    /// # Errors
+    /// Return an error if the data stream doesn't start with a valid Section Header Block.
+    /// Or if the underlying data are not readable.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::pcapng::PcapNgReader;
+    ///
+    /// let file_in = File::open("test.pcapng").expect("Error opening file");
+    /// let pcapng_reader = PcapNgReader::new(file_in).unwrap();
+    /// ```
+    pub fn new(reader: T) -> ResultChain<PcapNgReader<T>> {
+
+        let mut reader = PeekReader::new(reader);
+
+        let section = match read_block(&mut reader, Endianness::Big, &[], DEFAULT_MAX_BLOCK_LEN)? {
+            Block::SectionHeader(section) => section,
+            _ => bail!(ErrorKind::WrongField("The first block of a pcapng file must be a Section Header Block".to_string()))
+        };
+
+        Ok(
+            PcapNgReader {
+                section,
+                interfaces: Vec::new(),
+                reader,
+                max_block_len: DEFAULT_MAX_BLOCK_LEN,
+                done: false
+            }
+        )
+    }
+
+    /// Returns the maximum length of the blocks read, `DEFAULT_MAX_BLOCK_LEN` by default.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::pcapng::{PcapNgReader, DEFAULT_MAX_BLOCK_LEN};
+    ///
+    /// let file = File::open("test.pcapng").expect("Error opening file");
+    /// let pcapng_reader = PcapNgReader::new(file).unwrap();
+    ///
+    /// assert_eq!(pcapng_reader.max_block_len(), DEFAULT_MAX_BLOCK_LEN);
+    /// ```
+    pub fn max_block_len(&self) -> u32 {
+        self.max_block_len
+    }
+
+    /// Sets the maximum length of the blocks read, their total length included.
+    ///
+    /// The body of a block is allocated from the length declared in its header,
+    /// a longer block is refused with a `PacketTooLarge` error instead.
+    /// Raise it to read files with huge packets, lower it to limit the memory used by untrusted files.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::pcapng::PcapNgReader;
+    ///
+    /// let file = File::open("test.pcapng").expect("Error opening file");
+    /// let mut pcapng_reader = PcapNgReader::new(file).unwrap();
+    ///
+    /// pcapng_reader.set_max_block_len(16 * 1024 * 1024);
+    /// ```
+    pub fn set_max_block_len(&mut self, max_block_len: u32) {
+        self.max_block_len = max_block_len;
+    }
+
+    /// Returns the Interface Description Blocks read so far in the current section.
+    ///
+    /// The interface id of a packet is its index in this slice.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::pcapng::PcapNgReader;
+    ///
+    /// let file = File::open("test.pcapng").expect("Error opening file");
+    /// let pcapng_reader = PcapNgReader::new(file).unwrap();
+    ///
+    /// for interface in pcapng_reader.interfaces() {
+    ///     println!("{:?}", interface.linktype);
+    /// }
+    /// ```
+    pub fn interfaces(&self) -> &[InterfaceDescriptionBlock] {
+        &self.interfaces
+    }
+
+    /// Returns the Interface Description Block a packet was captured on.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::pcapng::{Block, PcapNgReader};
+    ///
+    /// let file = File::open("test.pcapng").expect("Error opening file");
+    /// let mut pcapng_reader = PcapNgReader::new(file).unwrap();
+    ///
+    /// while let Some(block) = pcapng_reader.next() {
+    ///     if let Block::EnhancedPacket(packet) = block.unwrap() {
+    ///         let interface = pcapng_reader.packet_interface(&packet).unwrap();
+    ///         println!("{:?}", interface.linktype);
+    ///     }
+    /// }
+    /// ```
+    pub fn packet_interface(&self, packet: &EnhancedPacketBlock) -> Option<&InterfaceDescriptionBlock> {
+        self.interfaces.get(packet.interface_id as usize)
+    }
+
+    /// Consumes the `PcapNgReader`, returning the wrapped reader.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::pcapng::PcapNgReader;
+    ///
+    /// let file = File::open("test.pcapng").expect("Error opening file");
+    /// let pcapng_reader = PcapNgReader::new(file).unwrap();
+    ///
+    /// let file2 = pcapng_reader.into_reader();
+    /// ```
+    pub fn into_reader(self) -> T {
+        self.reader.inner
+    }
+
+    /// Gets a reference to the underlying reader.
+    ///
+    /// It is not advised to directly read from the underlying reader.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::pcapng::PcapNgReader;
+    ///
+    /// let file = File::open("test.pcapng").expect("Error opening file");
+    /// let pcapng_reader = PcapNgReader::new(file).unwrap();
+    ///
+    /// let file_ref = pcapng_reader.get_ref();
+    /// ```
+    pub fn get_ref(&self) -> &T {
+        &self.reader.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// It is not advised to directly read from the underlying reader.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::pcapng::PcapNgReader;
+    ///
+    /// let file = File::open("test.pcapng").expect("Error opening file");
+    /// let mut pcapng_reader = PcapNgReader::new(file).unwrap();
+    ///
+    /// let file_mut = pcapng_reader.get_mut();
+    /// ```
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.reader.inner
+    }
+}
+
+impl <T:Read> Iterator for PcapNgReader<T> {
+
+    type Item = ResultChain<Block>;
+
+    fn next(&mut self) -> Option<ResultChain<Block>> {
+
+        if self.done {
+            return None;
+        }
+
+        match self.reader.is_empty() {
+            Ok(is_empty) if is_empty => {
+                return None;
+            },
+            Err(err) => return Some(Err(err.into())),
+            _ => {}
+        }
+
+        let block = read_block(&mut self.reader, self.section.endianness, &self.interfaces, self.max_block_len);
+
+        // Keep track of the section and its interfaces to decode the following blocks
+        match block {
+            Ok(Block::SectionHeader(ref section)) => {
+                self.section = section.clone();
+                self.interfaces.clear();
+            },
+            Ok(Block::InterfaceDescription(ref interface)) => self.interfaces.push(interface.clone()),
+
+            // The position of the next block is unknown, stop there
+            Err(_) => self.done = true,
+            _ => {}
+        }
+
+        Some(block)
+    }
+}
+
+/// Reads a whole block, using the endianness of the current section unless it is a Section Header Block
+fn read_block<R: Read>(reader: &mut R, endianness: Endianness, interfaces: &[InterfaceDescriptionBlock], max_block_len: u32) -> ResultChain<Block> {
+
+    let mut prefix = [0u8; 8];
+    reader.read_exact(&mut prefix)?;
+
+    // The Section Header Block type is a palindrome, its body gives the endianness of the section
+    let endianness = if BigEndian::read_u32(&prefix[..4]) == SECTION_HEADER_BLOCK {
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+
+        match BigEndian::read_u32(&magic) {
+            BYTE_ORDER_MAGIC => Endianness::Big,
+            magic if magic.swap_bytes() == BYTE_ORDER_MAGIC => Endianness::Little,
+            magic => bail!(ErrorKind::WrongField(format!("Wrong SectionHeaderBlock.byte_order_magic: {:#x}", magic)))
+        }
+    }
+    else {
+        endianness
+    };
+
+    match endianness {
+        Endianness::Big => read_block_body::<_, BigEndian>(reader, &prefix, interfaces, max_block_len),
+        Endianness::Little => read_block_body::<_, LittleEndian>(reader, &prefix, interfaces, max_block_len)
+    }
+}
+
+/// Reads the remaining body of a block and parses it
+fn read_block_body<R: Read, B: ByteOrder>(reader: &mut R, prefix: &[u8; 8], interfaces: &[InterfaceDescriptionBlock], max_block_len: u32) -> ResultChain<Block> {
+
+    let block_type = B::read_u32(&prefix[..4]);
+    let block_len = B::read_u32(&prefix[4..]);
+
+    if block_len < 12 || block_len % 4 != 0 {
+        bail!(ErrorKind::WrongField(format!("Block.total_length = {} is not a multiple of 4 greater than 12", block_len)));
+    }
+
+    // Checked before the body is allocated
+    if block_len > max_block_len {
+        bail!(ErrorKind::PacketTooLarge(block_len, max_block_len));
+    }
+
+    // The byte-order magic of a Section Header Block has already been consumed
+    let mut body = vec![0u8; block_len as usize - 12];
+    if block_type == SECTION_HEADER_BLOCK {
+
+        if body.len() < 4 {
+            bail!(ErrorKind::BufferUnderflow(4, body.len() as u64));
+        }
+
+        B::write_u32(&mut body[..4], BYTE_ORDER_MAGIC);
+        reader.read_exact(&mut body[4..])?;
+    }
+    else {
+        reader.read_exact(&mut body)?;
+    }
+
+    let trailing_len = reader.read_u32::<B>()?;
+    if trailing_len != block_len {
+        bail!(ErrorKind::WrongField(format!("Block.total_length = {} but the trailing length is {}", block_len, trailing_len)));
+    }
+
+    Ok(
+        match block_type {
+            SECTION_HEADER_BLOCK => Block::SectionHeader(SectionHeaderBlock::from_slice(&body)?),
+            INTERFACE_DESCRIPTION_BLOCK => Block::InterfaceDescription(InterfaceDescriptionBlock::from_slice::<B>(&body)?),
+            ENHANCED_PACKET_BLOCK => Block::EnhancedPacket(EnhancedPacketBlock::from_slice::<B>(&body, interfaces)?),
+            SIMPLE_PACKET_BLOCK => Block::SimplePacket(SimplePacketBlock::from_slice::<B>(&body)?),
+            _ => Block::Unknown(
+                UnknownBlock {
+                    block_type,
+                    body
+                }
+            )
+        }
+    )
+}
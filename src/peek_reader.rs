@@ -1,11 +1,19 @@
-use std::io::{Read, Result as IoResult};
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult};
 use std::io::Seek;
 use std::io::SeekFrom;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct PeekReader<R: Read> {
     pub inner: R,
-    pub peeked: Option<u8>,
+    /// Bytes read from the inner reader but not consumed yet
+    peeked: Vec<u8>,
+    /// Position in the stream, the peeked bytes excluded, counted from the position of the inner reader
+    /// when the `PeekReader` was created. The seek positions are counted from there too.
+    pos: u64,
+    /// Total number of bytes consumed, the peeked bytes excluded, whatever the seeks
+    consumed: u64,
+    /// Position of the inner reader when the `PeekReader` was created, measured at the first seek
+    start: Option<u64>
 }
 
 impl<R: Read> Read for PeekReader<R> {
@@ -16,55 +24,116 @@ impl<R: Read> Read for PeekReader<R> {
             return Ok(0);
         }
 
-        if let Some(b) = self.peeked {
+        let nb_read = if !self.peeked.is_empty() {
 
-            buf[0] = b;
-            self.peeked = None;
-            self.inner.read(&mut buf[1..]).map(|x| x+1)
+            let len = buf.len().min(self.peeked.len());
+            buf[..len].copy_from_slice(&self.peeked[..len]);
+            self.peeked.drain(..len);
+            len
         }
         else {
-            self.inner.read(buf)
-        }
+            self.inner.read(buf)?
+        };
+
+        self.pos += nb_read as u64;
+        self.consumed += nb_read as u64;
+        Ok(nb_read)
     }
 }
 
 impl<R: Read + Seek> Seek for PeekReader<R> {
-    fn seek(&mut self, pos: SeekFrom) -> ::std::io::Result<u64> {
-        if let Some(_) = self.peeked {
-            self.inner.seek(SeekFrom::Current(-1))?;
-            self.peeked = None;
-        }
-        self.inner.seek(pos)
+
+    /// Seeks in the stream, the positions being counted from the position of the inner reader
+    /// when the `PeekReader` was created, like `pos`.
+    fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+
+        // The inner reader is ahead of `pos` by the peeked bytes
+        let start = match self.start {
+            Some(start) => start,
+            None => {
+                let inner_pos = self.inner.stream_position()?;
+                let start = inner_pos.saturating_sub(self.peeked.len() as u64 + self.pos);
+                self.start = Some(start);
+                start
+            }
+        };
+
+        let pos = match pos {
+            SeekFrom::Start(offset) => SeekFrom::Start(start + offset),
+            SeekFrom::Current(offset) => SeekFrom::Current(offset - self.peeked.len() as i64),
+            end => end
+        };
+
+        let inner_pos = self.inner.seek(pos)?;
+        self.peeked.clear();
+
+        self.pos = match inner_pos.checked_sub(start) {
+            Some(pos) => pos,
+            None => return Err(IoError::new(ErrorKind::InvalidInput, "seek before the start of the stream"))
+        };
+
+        Ok(self.pos)
     }
 }
 
 impl<R: Read> PeekReader<R> {
 
     pub fn new(inner: R) -> PeekReader<R> {
+        PeekReader::with_capacity(inner, 0)
+    }
+
+    /// Creates a `PeekReader` whose buffer of peeked bytes can hold `capacity` bytes without reallocating.
+    pub fn with_capacity(inner: R, capacity: usize) -> PeekReader<R> {
         PeekReader {
             inner,
-            peeked: None
+            peeked: Vec::with_capacity(capacity),
+            pos: 0,
+            consumed: 0,
+            start: None
         }
     }
 
+    /// Returns the position in the stream, the peeked bytes excluded.
+    ///
+    /// It is counted from the position of the inner reader when the `PeekReader` was created, like the seek positions.
+    pub fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    /// Returns the total number of bytes consumed, the peeked bytes excluded, whatever the seeks.
+    pub fn consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Returns the bytes read from the inner reader but not consumed yet.
+    pub fn peeked(&self) -> &[u8] {
+        &self.peeked
+    }
+
     pub fn is_empty(&mut self) -> IoResult<bool> {
 
-        if self.peeked.is_some() {
-            return Ok(false);
-        }
+        Ok(self.peek(1)?.is_empty())
+    }
+
+    /// Returns the next `len` bytes without consuming them.
+    ///
+    /// Less bytes are returned if the stream ends before.
+    pub fn peek(&mut self, len: usize) -> IoResult<&[u8]> {
+
+        // Read from the inner reader, the peeked bytes are not consumed yet
+        let mut buf = [0; 4096];
+        while self.peeked.len() < len {
 
-        let mut buf = [0; 1];
-        let nb_read = self.read(&mut buf)?;
-
-        Ok(
-            match nb_read {
-                0 => true,
-                1 => {
-                    self.peeked = Some(buf[0]);
-                    false
-                },
-                _ => unreachable!(),
+            let wanted = (len - self.peeked.len()).min(buf.len());
+            match self.inner.read(&mut buf[..wanted]) {
+                Ok(0) => break,
+                Ok(nb_read) => self.peeked.extend_from_slice(&buf[..nb_read]),
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => {},
+                Err(err) => return Err(err)
             }
-        )
+        }
+
+        let len = len.min(self.peeked.len());
+        Ok(&self.peeked[..len])
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,149 @@
+//! This module contains the `PacketPool` struct which recycles the payload buffers of the packets.
+
+use std::borrow::Cow;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use packet::{Packet, PacketHeader};
+
+
+/// Pool of payload buffers, filled by `PcapReader::next_pooled`.
+///
+/// A `PooledPacket` owns its buffer and gives it back to the pool when it is dropped,
+/// so that reading goes on without allocating once enough buffers have been recycled.
+/// The pool is shared by its clones and can be used by several threads.
+///
+/// At most `max_buffers` idle buffers are kept, the others are freed.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use pcap_file::{PacketPool, PcapReader};
+///
+/// let file = File::open("test.pcap").expect("Error opening file");
+/// let mut pcap_reader = PcapReader::new(file).unwrap();
+/// let mut pool = PacketPool::new(64);
+///
+/// while let Some(pcap) = pcap_reader.next_pooled(&mut pool) {
+///
+///     // The buffer goes back into the pool at the end of the loop
+///     let pcap = pcap.unwrap();
+///     println!("{} bytes", pcap.data().len());
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PacketPool {
+    shared: Arc<Shared>
+}
+
+#[derive(Debug)]
+struct Shared {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    max_buffers: usize
+}
+
+impl Shared {
+
+    fn put(&self, mut buf: Vec<u8>) {
+
+        // A buffer taken out of the pool by `into_packet` leaves nothing behind
+        if buf.capacity() == 0 {
+            return;
+        }
+
+        buf.clear();
+
+        // The buffers can't be left in an invalid state, a poisoned lock is still usable
+        let mut buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+        if buffers.len() < self.max_buffers {
+            buffers.push(buf);
+        }
+    }
+}
+
+impl PacketPool {
+
+    /// Creates a new empty pool keeping at most `max_buffers` idle buffers.
+    pub fn new(max_buffers: usize) -> PacketPool {
+
+        PacketPool {
+            shared: Arc::new(
+                Shared {
+                    buffers: Mutex::new(Vec::new()),
+                    max_buffers
+                }
+            )
+        }
+    }
+
+    /// Returns the number of idle buffers, waiting to be reused.
+    pub fn idle(&self) -> usize {
+        self.shared.buffers.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Returns an idle buffer, or a new one if there is none
+    pub(crate) fn take(&self) -> Vec<u8> {
+        self.shared.buffers.lock().unwrap_or_else(|e| e.into_inner()).pop().unwrap_or_default()
+    }
+
+    /// Gives back a buffer which wasn't handed out
+    pub(crate) fn put(&self, buf: Vec<u8>) {
+        self.shared.put(buf)
+    }
+
+    /// Wraps a buffer taken from this pool into a `PooledPacket`
+    pub(crate) fn wrap(&self, header: PacketHeader, data: Vec<u8>) -> PooledPacket {
+
+        PooledPacket {
+            header,
+            data,
+            shared: self.shared.clone()
+        }
+    }
+}
+
+/// Packet whose payload is a buffer of a `PacketPool`, given back to the pool when it is dropped.
+///
+/// It is returned by `PcapReader::next_pooled`.
+#[derive(Debug)]
+pub struct PooledPacket {
+
+    /// Header of the packet
+    pub header: PacketHeader,
+    data: Vec<u8>,
+    shared: Arc<Shared>
+}
+
+impl PooledPacket {
+
+    /// Returns the payload of the packet.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns a `Packet` borrowing the payload.
+    pub fn as_packet(&self) -> Packet<'_> {
+
+        Packet {
+            header: self.header,
+            data: Cow::Borrowed(&self.data)
+        }
+    }
+
+    /// Consumes the `PooledPacket`, returning an owned `Packet`. The buffer is taken out of the pool.
+    pub fn into_packet(mut self) -> Packet<'static> {
+
+        Packet {
+            header: self.header,
+            data: Cow::Owned(mem::take(&mut self.data))
+        }
+    }
+}
+
+impl Drop for PooledPacket {
+
+    fn drop(&mut self) {
+        self.shared.put(mem::take(&mut self.data));
+    }
+}
@@ -1,21 +1,42 @@
 //! This module contains the `PcapReader` struct which is used to read from a pcap file
 
-use byteorder::{BigEndian, LittleEndian};
-
 use errors::*;
 
-use packet::Packet;
-use pcap_header::{PcapHeader, Endianness};
+use format::{detect_format, Format};
+use index::PacketIndex;
+#[cfg(feature = "bpf")]
+use filter::Filter;
+#[cfg(feature = "bpf")]
+use iter::Filtered;
+use iter::{Bucketed, Dedup, Deltas, Headers, HeadersAndData, Limit, Lossy, RevFromIndex, SeekHeaders, Truncation, Retimestamp, TakeFor, TakeUntil, TimeRange};
+use packet::{read_full, Packet, PacketHeader, DEFAULT_MAX_PACKET_LEN};
+use pcap_header::{magic_format, DataLink, Endianness, PcapHeader, SnaplenCheck, TsResolution};
 
 use peek_reader::PeekReader;
+use pool::{PacketPool, PooledPacket};
+use stats::CaptureStats;
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
 
-use std::io::Read;
+#[cfg(feature = "gzip")]
+use flate2::read::GzDecoder;
 
 
 /// This struct wraps another reader and enables it to read a Pcap formated stream.
 ///
 /// It implements the Iterator trait in order to read one packet at a time
 ///
+/// The iteration ends cleanly if the stream ends between two packets, a stream which ends
+/// in the middle of a packet yields a `TruncatedPacket` error instead, unless the reader is lenient (see `set_lenient()`).
+/// The errors of the packets read, by the iterator or by the other methods and adapters, are `ReadPacket` errors
+/// giving the location of the faulty packet, their cause is the actual error, see `Error::root_kind`.
+///
+/// It can be cloned when the underlying reader can, the clone resumes reading at the same packet.
+///
 /// # Examples
 ///
 /// ```rust,no_run
@@ -38,11 +59,26 @@ use std::io::Read;
 ///     pcap_writer.write_packet(&pcap).unwrap();
 /// }
 /// ```
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct PcapReader<T: Read> {
 
     pub header: PcapHeader,
-    reader: PeekReader<T>
+    reader: PeekReader<T>,
+    data_offset: u64,
+    max_packet_len: u32,
+    /// Header of the last peeked packet, kept to hand out a reference to it
+    peeked: Option<PacketHeader>,
+    /// Length of the stream, only known when the reader has been created with `new_seek()`
+    stream_len: Option<u64>,
+    /// Offset and index of the next packet, when known, used to locate the errors
+    next_packet: Option<(u64, u64)>,
+    /// Yields the truncated packets at the end of the stream instead of an error
+    lenient: bool,
+    /// Number of bytes of a truncated packet header ignored at the end of the stream, in lenient mode
+    trailing_bytes: usize,
+    fix_orig_len: bool,
+    /// Swaps the incl_len and orig_len of the packets whose incl_len is greater
+    swap_len_fields: bool
 }
 
 impl <T:Read> PcapReader<T>{
@@ -64,15 +100,137 @@ impl <T:Read> PcapReader<T>{
     /// let file_in = File::open("test.pcap").expect("Error opening file");
     /// let pcap_reader = PcapReader::new(file_in).unwrap();
     /// ```
-    pub fn new(mut reader:T) -> ResultChain<PcapReader<T>> {
+    pub fn new(reader:T) -> ResultChain<PcapReader<T>> {
+        PcapReader::with_capacity(reader, 0)
+    }
 
-        Ok(
-            PcapReader {
+    /// Create a new PcapReader from an existing reader, with a buffer of `capacity` bytes for the peeked data.
+    ///
+    /// The buffer holds the packets buffered by `peek_packet()` and the adapters built on it, such as `take_until()`,
+    /// it grows as needed whatever its initial capacity. A capacity of the largest expected packet avoids reallocations
+    /// on streams of big packets, while `new()` allocates nothing until a packet is peeked.
+    ///
+    /// # Errors
+    /// Return an error if the data stream is not in a valid pcap file format.
+    /// Or if the underlying data are not readable.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// // Jumbo frames
+    /// let file_in = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::with_capacity(file_in, 16 + 9000).unwrap();
+    /// ```
+    pub fn with_capacity(reader: T, capacity: usize) -> ResultChain<PcapReader<T>> {
 
-                header : PcapHeader::from_reader(&mut reader)?,
-                reader : PeekReader::new(reader)
-            }
-        )
+        let mut reader = PeekReader::with_capacity(reader, capacity);
+        let header = PcapHeader::from_reader(&mut reader)?;
+
+        Ok(PcapReader::with_peek_reader(header, reader))
+    }
+
+    /// Create a new PcapReader from an existing reader, checking the snaplen of its global header with `check`.
+    ///
+    /// `new()` uses the default `SnaplenCheck`, see `PcapHeader::from_reader`.
+    ///
+    /// # Errors
+    /// Return an error if the data stream is not in a valid pcap file format, if its snaplen doesn't pass the check,
+    /// or if the underlying data are not readable.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{PcapReader, SnaplenCheck};
+    ///
+    /// // Refuse a snaplen of 0 and accept snaplens up to 1 MiB
+    /// let check = SnaplenCheck { max: 1024 * 1024, strict: true };
+    ///
+    /// let file_in = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::with_snaplen_check(file_in, check).unwrap();
+    /// ```
+    pub fn with_snaplen_check(reader: T, check: SnaplenCheck) -> ResultChain<PcapReader<T>> {
+
+        let mut reader = PeekReader::new(reader);
+        let header = PcapHeader::from_reader_checked(&mut reader, check)?;
+
+        Ok(PcapReader::with_peek_reader(header, reader))
+    }
+
+    /// Create a new PcapReader from a stream of packet records without global pcap header.
+    ///
+    /// The packets are parsed according to the given header, which replaces the missing one.
+    /// The header can come from anywhere: built by hand, or read with `PcapHeader::from_reader` from another stream,
+    /// such as the control channel of a protocol carrying the packet records on a data channel.
+    ///
+    /// The header is checked like a global header read from a file, with the default `SnaplenCheck`.
+    ///
+    /// # Errors
+    /// Return an `UnknownMagic` error if the magic number of the header is unknown,
+    /// or an `InvalidSnaplen` error if its snaplen exceeds `DEFAULT_MAX_PACKET_LEN`.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::io::stdin;
+    /// use pcap_file::{DataLink, PcapHeader, PcapReader};
+    ///
+    /// // Little endian packet records of raw IP packets, without global header
+    /// let header = PcapHeader { magic_number: 0xd4c3b2a1, datalink: DataLink::RAW, ..PcapHeader::default() };
+    /// let pcap_reader = PcapReader::from_parts(stdin(), header).unwrap();
+    /// ```
+    pub fn from_parts(reader: T, header: PcapHeader) -> ResultChain<PcapReader<T>> {
+
+        if magic_format(header.magic_number).is_none() {
+            bail!(ErrorKind::UnknownMagic(header.magic_number));
+        }
+        header.check_snaplen(SnaplenCheck::default())?;
+
+        Ok(PcapReader::with_peek_reader(header, PeekReader::new(reader)))
+    }
+
+    /// Create a new PcapReader reading the packet records of `data_reader` with a global header obtained elsewhere.
+    ///
+    /// Same as `from_parts`, for the protocols sending the global header apart from the packet records,
+    /// on a control channel for example. The data stream must not start with a global header.
+    ///
+    /// # Errors
+    /// Return an `UnknownMagic` error if the magic number of the header is unknown,
+    /// or an `InvalidSnaplen` error if its snaplen exceeds `DEFAULT_MAX_PACKET_LEN`.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::net::TcpStream;
+    /// use pcap_file::{PcapHeader, PcapReader};
+    ///
+    /// // The global header is received on the control channel, the packet records on the data channel
+    /// let mut control = TcpStream::connect("127.0.0.1:2000").expect("Error connecting");
+    /// let header = PcapHeader::from_reader(&mut control).unwrap();
+    ///
+    /// let data = TcpStream::connect("127.0.0.1:2001").expect("Error connecting");
+    /// let pcap_reader = PcapReader::with_external_header(data, header).unwrap();
+    /// ```
+    pub fn with_external_header(data_reader: T, header: PcapHeader) -> ResultChain<PcapReader<T>> {
+        PcapReader::from_parts(data_reader, header)
+    }
+
+    /// Creates the `PcapReader` of a stream positioned after its global header
+    fn with_peek_reader(header: PcapHeader, reader: PeekReader<T>) -> PcapReader<T> {
+
+        PcapReader {
+
+            header,
+            data_offset : reader.pos(),
+            reader,
+            max_packet_len: DEFAULT_MAX_PACKET_LEN,
+            peeked: None,
+            stream_len: None,
+            next_packet: None,
+            lenient: false,
+            trailing_bytes: 0,
+            fix_orig_len: false,
+            swap_len_fields: false
+        }
     }
 
     /// Consumes the `PcapReader`, returning the wrapped reader.
@@ -127,28 +285,1546 @@ impl <T:Read> PcapReader<T>{
     pub fn get_mut(&mut self) -> &mut T{
         &mut self.reader.inner
     }
-}
 
-impl <T:Read> Iterator for PcapReader<T> {
+    /// Returns the snaplen of the global header, the maximum length of the packets saved.
+    ///
+    /// A snaplen of 0 is returned as 65535, see `PcapHeader::effective_snaplen()`.
+    /// Like libpcap, only the first `snaplen()` bytes of a longer packet are read, the others are skipped
+    /// and the incl_len of its header lowered to the snaplen. The packet headers alone, such as those yielded
+    /// by `headers()`, are returned as written.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// println!("Packets truncated to {} bytes", pcap_reader.snaplen());
+    /// ```
+    pub fn snaplen(&self) -> u32 {
+        self.header.effective_snaplen()
+    }
 
-    type Item = ResultChain<Packet<'static>>;
+    /// Returns the DataLink type of the global header, the first layer of every packet.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// println!("{:?} packets", pcap_reader.datalink());
+    /// ```
+    pub fn datalink(&self) -> DataLink {
+        self.header.datalink
+    }
 
-    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+    /// Returns the endianness of the file.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// println!("{:?} file", pcap_reader.endianness());
+    /// ```
+    pub fn endianness(&self) -> Endianness {
+        self.header.endianness()
+    }
 
-        match self.reader.is_empty() {
-            Ok(is_empty) if is_empty => {
-                return None;
-            },
-            Err(err) => return Some(Err(err.into())),
-            _ => {}
-        }
+    /// Returns the timestamp resolution of the packets.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// println!("Timestamps in {:?}", pcap_reader.ts_resolution());
+    /// ```
+    pub fn ts_resolution(&self) -> TsResolution {
+        self.header.ts_resolution()
+    }
+
+    /// Returns the maximum length of the packets read, `DEFAULT_MAX_PACKET_LEN` (256 KiB) by default.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{PcapReader, DEFAULT_MAX_PACKET_LEN};
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// assert_eq!(pcap_reader.max_packet_len(), DEFAULT_MAX_PACKET_LEN);
+    /// ```
+    pub fn max_packet_len(&self) -> u32 {
+        self.max_packet_len
+    }
+
+    /// Sets the maximum length of the packets read.
+    ///
+    /// The payload of a packet is allocated from the length declared in its header,
+    /// a longer packet is refused with a `PacketTooLarge` error instead.
+    /// A packet within this length but longer than the snaplen is truncated to the snaplen, see `snaplen()`.
+    /// Raise it to read files with huge packets, lower it to limit the memory used by untrusted files.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// pcap_reader.set_max_packet_len(16 * 1024 * 1024);
+    /// ```
+    pub fn set_max_packet_len(&mut self, max_packet_len: u32) {
+        self.max_packet_len = max_packet_len;
+    }
+
+    /// Returns true if the reader is in lenient mode, see `set_lenient()`.
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// Switches between the strict mode, the default, and the lenient mode.
+    ///
+    /// The lenient mode only tolerates a capture which was cut short, like a file still being written:
+    /// * a last packet whose payload is truncated is yielded with the available bytes, its incl_len is lowered to their number;
+    /// * a last packet header which is truncated, such as a few stray bytes after the last packet, is ignored:
+    ///   the iteration ends cleanly and their number is given by `trailing_bytes()`.
+    ///
+    /// Both are `TruncatedPacket` errors in strict mode.
+    /// The other anomalies, such as an incl_len greater than the orig_len or than the maximum packet length,
+    /// are errors in both modes.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("capture_in_progress.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    /// pcap_reader.set_lenient(true);
+    ///
+    /// for pcap in pcap_reader {
+    ///     let pcap = pcap.unwrap();
+    /// }
+    /// ```
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Returns the number of bytes ignored at the end of the stream in lenient mode, the start of a truncated packet header.
+    ///
+    /// It is 0 until the end of the stream is reached, or if the stream ends cleanly after the last packet.
+    /// These bytes are often left by a capture tool which crashed, they give a `TruncatedPacket` error in strict mode.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("crashed.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    /// pcap_reader.set_lenient(true);
+    ///
+    /// for pcap in pcap_reader.by_ref() {
+    ///     let pcap = pcap.unwrap();
+    /// }
+    ///
+    /// if pcap_reader.trailing_bytes() > 0 {
+    ///     eprintln!("Warning: {} stray bytes at the end of the file", pcap_reader.trailing_bytes());
+    /// }
+    /// ```
+    pub fn trailing_bytes(&self) -> usize {
+        self.trailing_bytes
+    }
+
+    /// Returns true if the invalid orig_len are fixed, see `set_fix_orig_len()`.
+    pub fn fixes_orig_len(&self) -> bool {
+        self.fix_orig_len
+    }
+
+    /// Fixes the packets whose orig_len is lower than their incl_len, instead of returning an error.
+    ///
+    /// Their orig_len is raised to their incl_len, see `PacketHeader::fix_orig_len`.
+    /// It is meant for the captures of tools writing an orig_len of 0, and is disabled by default.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("no_orig_len.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    /// pcap_reader.set_fix_orig_len(true);
+    ///
+    /// for pcap in pcap_reader {
+    ///     let pcap = pcap.unwrap();
+    ///     assert!(pcap.orig_len() >= pcap.incl_len());
+    /// }
+    /// ```
+    pub fn set_fix_orig_len(&mut self, fix_orig_len: bool) {
+        self.fix_orig_len = fix_orig_len;
+    }
+
+    /// Returns true if the swapped incl_len and orig_len are repaired, see `set_swap_len_fields()`.
+    pub fn swaps_len_fields(&self) -> bool {
+        self.swap_len_fields
+    }
+
+    /// Swaps the incl_len and the orig_len of the packets whose incl_len is greater than their orig_len,
+    /// instead of returning an error.
+    ///
+    /// The smaller of the two lengths is then the length of the payload, see `PacketHeader::swap_len_fields`.
+    /// It is meant for the captures of a buggy tool writing the two fields in the wrong order, and is disabled by default:
+    /// a normal file never needs it. The swap happens before `set_fix_orig_len()` applies.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("swapped_lengths.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    /// pcap_reader.set_swap_len_fields(true);
+    ///
+    /// for pcap in pcap_reader {
+    ///     let pcap = pcap.unwrap();
+    ///     assert!(pcap.orig_len() >= pcap.incl_len());
+    /// }
+    /// ```
+    pub fn set_swap_len_fields(&mut self, swap_len_fields: bool) {
+        self.swap_len_fields = swap_len_fields;
+    }
+
+    /// Returns the byte offset of the next packet to be read.
+    ///
+    /// The offset is counted from the position of the underlying reader when the `PcapReader` was created,
+    /// which is the start of the file in most cases, whatever the seeks done since: it can be given to `seek_to()`.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// // The global header has been read
+    /// assert_eq!(pcap_reader.offset(), 24);
+    /// ```
+    pub fn offset(&self) -> u64 {
+        self.reader.pos()
+    }
+
+    /// Returns the total number of bytes consumed from the underlying reader, global header included.
+    ///
+    /// The bytes buffered by `peek_packet()` are only counted once their packet is read.
+    /// Unlike `offset()`, the count keeps growing when the reader seeks.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let file_len = file.metadata().unwrap().len();
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// while let Some(pcap) = pcap_reader.next() {
+    ///     let pcap = pcap.unwrap();
+    ///     println!("{}%", pcap_reader.bytes_read() * 100 / file_len);
+    /// }
+    /// ```
+    pub fn bytes_read(&self) -> u64 {
+        self.reader.consumed()
+    }
 
-        Some(
-            match self.header.endianness() {
-                Endianness::Big => Packet::from_reader::<_, BigEndian>(&mut self.reader),
-                Endianness::Little => Packet::from_reader::<_, LittleEndian>(&mut self.reader)
+    /// Returns true if there is no more packet to read, without consuming anything.
+    ///
+    /// The next bytes of the stream are only peeked, so that a loop can check for data before calling `next()`,
+    /// or a streaming source be polled again later. In lenient mode, a truncated packet header at the end
+    /// of the stream counts as no more data, see `trailing_bytes()`.
+    ///
+    /// # Errors
+    /// Return an error if the underlying reader can't be read.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// while !pcap_reader.is_eof().unwrap() {
+    ///     let pcap = pcap_reader.next().unwrap().unwrap();
+    /// }
+    /// ```
+    pub fn is_eof(&mut self) -> ResultChain<bool> {
+
+        // In lenient mode, a truncated packet header at the end is ignored
+        if self.lenient {
+            let header_len = self.header.packet_header_len();
+            let available = self.reader.peek(header_len)?.len();
+            if available < header_len {
+                self.trailing_bytes = available;
+                return Ok(true);
             }
-        )
+
+            return Ok(false);
+        }
+
+        Ok(self.reader.is_empty()?)
     }
 
-}
\ No newline at end of file
+    /// Reads the next packet into the provided buffer and returns its header.
+    ///
+    /// The buffer is resized to the length of the packet, so the same buffer can be reused for every packet
+    /// without allocating each time.
+    ///
+    /// Returns None at the end of the stream.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// let mut buf = Vec::new();
+    /// while let Some(header) = pcap_reader.next_into(&mut buf) {
+    ///
+    ///     let header = header.unwrap();
+    ///     assert_eq!(buf.len(), header.incl_len as usize);
+    /// }
+    /// ```
+    pub fn next_into(&mut self, buf: &mut Vec<u8>) -> Option<ResultChain<PacketHeader>> {
+
+        self.locate(|reader| {
+
+            if reader.is_eof()? {
+                return Ok(None);
+            }
+
+            let mut header = reader.read_packet_header()?;
+
+            buf.clear();
+            reader.read_payload(&mut header, buf)?;
+            Ok(Some(header))
+        }).transpose()
+    }
+
+    /// Reads the next packet into a buffer of `pool` and returns it, the buffer going back to the pool when the packet is dropped.
+    ///
+    /// Unlike `next_into()`, the packets are owned and can be kept or sent to another thread,
+    /// while no allocation is needed once the pool holds enough buffers. See `PacketPool` for the details.
+    ///
+    /// Returns None at the end of the stream.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{PacketPool, PcapReader};
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    /// let mut pool = PacketPool::new(64);
+    ///
+    /// while let Some(pcap) = pcap_reader.next_pooled(&mut pool) {
+    ///     let pcap = pcap.unwrap();
+    ///     println!("{:?}", pcap.as_packet());
+    /// }
+    /// ```
+    pub fn next_pooled(&mut self, pool: &mut PacketPool) -> Option<ResultChain<PooledPacket>> {
+
+        let mut buf = pool.take();
+
+        match self.next_into(&mut buf) {
+            Some(Ok(header)) => Some(Ok(pool.wrap(header, buf))),
+            Some(Err(err)) => {
+                pool.put(buf);
+                Some(Err(err))
+            },
+            None => {
+                pool.put(buf);
+                None
+            }
+        }
+    }
+
+    /// Reads the next packet record, its packet header followed by its payload, into the provided buffer
+    /// and returns its parsed header.
+    ///
+    /// The record is kept as encoded in the file, so that it can be written as is by `PcapWriter::write_raw_record`
+    /// to a file of the same format, without decoding and encoding every packet.
+    /// The lengths of the header are only rewritten if they were fixed by the reader,
+    /// in lenient mode, with `set_fix_orig_len` or with `set_swap_len_fields`, or truncated to the snaplen. The buffer can be reused for every packet.
+    ///
+    /// Returns None at the end of the stream.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{PcapReader, PcapWriter};
+    ///
+    /// let mut pcap_reader = PcapReader::new(File::open("test.pcap").unwrap()).unwrap();
+    /// let mut pcap_writer = PcapWriter::with_header(pcap_reader.header, File::create("out.pcap").unwrap()).unwrap();
+    ///
+    /// let mut record = Vec::new();
+    /// while let Some(header) = pcap_reader.next_raw_into(&mut record) {
+    ///
+    ///     let header = header.unwrap();
+    ///     if header.incl_len > 100 {
+    ///         pcap_writer.write_raw_record(&record).unwrap();
+    ///     }
+    /// }
+    /// ```
+    pub fn next_raw_into(&mut self, buf: &mut Vec<u8>) -> Option<ResultChain<PacketHeader>> {
+
+        self.locate(|reader| {
+
+            if reader.is_eof()? {
+                return Ok(None);
+            }
+
+            reader.read_raw_record(buf).map(Some)
+        }).transpose()
+    }
+
+    /// Reads the packet record at the current position into `buf`
+    fn read_raw_record(&mut self, buf: &mut Vec<u8>) -> ResultChain<PacketHeader> {
+
+        buf.clear();
+        buf.resize(self.header.packet_header_len(), 0);
+        read_full(&mut self.reader, buf)?;
+
+        let mut header = self.parse_packet_header(buf)?;
+        self.read_payload(&mut header, buf)?;
+
+        // Only the lengths can differ from the bytes read
+        let endianness = self.header.endianness();
+        let mut raw = [0u8; 16];
+        raw.copy_from_slice(&buf[..16]);
+        let raw = PacketHeader::from_slice(&raw, endianness);
+
+        if (raw.incl_len, raw.orig_len) != (header.incl_len, header.orig_len) {
+            buf[8..16].copy_from_slice(&header.to_bytes(endianness)[8..16]);
+        }
+
+        Ok(header)
+    }
+
+    /// Reads up to `max` packets at once.
+    ///
+    /// Less packets are returned if the stream ends before, an empty `Vec` meaning that the end of the stream was already reached.
+    ///
+    /// # Errors
+    /// Return an error if a packet is invalid or if the stream ends in the middle of a packet,
+    /// the packets read before are lost.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// loop {
+    ///
+    ///     let batch = pcap_reader.read_batch(100).unwrap();
+    ///     if batch.is_empty() {
+    ///         break;
+    ///     }
+    ///
+    ///     println!("{} packets", batch.len());
+    /// }
+    /// ```
+    pub fn read_batch(&mut self, max: usize) -> ResultChain<Vec<Packet<'static>>> {
+
+        // Don't trust a huge max for the preallocation
+        let mut packets = Vec::with_capacity(max.min(1024));
+
+        while packets.len() < max {
+
+            match self.read_next()? {
+                Some(packet) => packets.push(packet),
+                None => break
+            }
+        }
+
+        Ok(packets)
+    }
+
+    /// Returns the header of the next packet without consuming it.
+    ///
+    /// The whole packet is buffered, so the following call to `next()` or `next_into()` returns this packet.
+    /// In lenient mode, the end of the stream and the truncated packets are handled as by `next()`.
+    ///
+    /// # Errors
+    /// Return an error if the packet header is invalid or if the packet is truncated while the reader isn't lenient.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// let incl_len = pcap_reader.peek_packet().unwrap().unwrap().incl_len;
+    ///
+    /// let packet = pcap_reader.next().unwrap().unwrap();
+    /// assert_eq!(packet.data.len(), incl_len as usize);
+    /// ```
+    pub fn peek_packet(&mut self) -> Option<ResultChain<&PacketHeader>> {
+
+        let header = self.locate(|reader| {
+
+            if reader.is_eof()? {
+                return Ok(None);
+            }
+
+            reader.peek_packet_header().map(Some)
+        });
+
+        match header {
+            Ok(Some(header)) => {
+                self.peeked = Some(header);
+                self.peeked.as_ref().map(Ok)
+            },
+            Ok(None) => None,
+            Err(err) => Some(Err(err))
+        }
+    }
+
+    /// Parses the next packet header and buffers the whole packet, without consuming it
+    fn peek_packet_header(&mut self) -> ResultChain<PacketHeader> {
+
+        let header_len = self.header.packet_header_len();
+        let mut header = {
+
+            let bytes = self.reader.peek(header_len)?;
+            if bytes.len() < header_len {
+                bail!(ErrorKind::TruncatedPacket(header_len, bytes.len()));
+            }
+
+            self.header.read_packet_header(&mut &bytes[..], self.fix_orig_len, self.swap_len_fields)?
+        };
+        self.check_packet_len(&header)?;
+
+        let len = header.incl_len as usize;
+        let got = self.reader.peek(header_len + len)?.len() - header_len;
+        if got < len {
+
+            // The truncated payload is kept in lenient mode, as by `read_payload`
+            if !self.lenient {
+                bail!(ErrorKind::TruncatedPacket(len, got));
+            }
+
+            header.incl_len = got as u32;
+        }
+
+        // The bytes past the effective snaplen are buffered but skipped, as by `read_payload`
+        header.incl_len = header.incl_len.min(self.header.effective_snaplen());
+
+        Ok(header)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over the packets whose timestamp is in [start, end).
+    ///
+    /// The payloads of the packets out of the range are skipped without being allocated.
+    /// Because the packets of a capture are generally sorted by timestamp, the iteration stops at the first
+    /// packet past the end. Call `unsorted()` on the returned iterator to read the whole file instead.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use std::time::Duration;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// let start = Duration::from_secs(1_331_901_000);
+    /// let end = start + Duration::from_secs(60);
+    ///
+    /// for pcap in pcap_reader.in_time_range(start, end) {
+    ///     let pcap = pcap.unwrap();
+    /// }
+    /// ```
+    pub fn in_time_range(self, start: Duration, end: Duration) -> TimeRange<T> {
+        TimeRange::new(self, start, end)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over the header and the payload of each packet.
+    ///
+    /// The metadata and the payloads can then be stored separately, the payloads aren't copied.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// for record in pcap_reader.headers_and_data() {
+    ///
+    ///     let (header, data) = record.unwrap();
+    ///     assert_eq!(data.len(), header.incl_len as usize);
+    /// }
+    /// ```
+    pub fn headers_and_data(self) -> HeadersAndData<T> {
+        HeadersAndData::new(self)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over its packets whose timestamp is replaced by `f(timestamp)`.
+    ///
+    /// The timestamps are `Duration` since the epoch, the timestamp resolution of the packets is kept.
+    /// A packet whose new timestamp doesn't fit in a packet header is yielded as an error.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// // Replay the capture twice as fast
+    /// for pcap in pcap_reader.retimestamp(|ts| ts / 2) {
+    ///     let pcap = pcap.unwrap();
+    /// }
+    /// ```
+    pub fn retimestamp<F: FnMut(Duration) -> Duration>(self, f: F) -> Retimestamp<T, F> {
+        Retimestamp::new(self, f)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over its packets shifted in time so that the first one is at `start`.
+    ///
+    /// The gaps between the packets are kept. A packet older than the first one is shifted too,
+    /// but its timestamp can't go before the epoch.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use std::time::{SystemTime, UNIX_EPOCH};
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// // The capture starts now
+    /// let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    /// for pcap in pcap_reader.shift_to(now) {
+    ///     let pcap = pcap.unwrap();
+    /// }
+    /// ```
+    pub fn shift_to(self, start: Duration) -> Retimestamp<T, impl FnMut(Duration) -> Duration> {
+
+        let mut first = None;
+        self.retimestamp(move |ts| {
+
+            let first = *first.get_or_insert(ts);
+            match ts.checked_sub(first) {
+                Some(offset) => start + offset,
+                None => start.checked_sub(first - ts).unwrap_or_default()
+            }
+        })
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over the packets which can be parsed, the errors being kept aside.
+    ///
+    /// The reading goes on after an invalid packet, until the end of the stream or an I/O error.
+    /// See `Lossy` for the errors kept.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut packets = PcapReader::new(file).unwrap().lossy();
+    ///
+    /// for pcap in packets.by_ref() {
+    ///     println!("{:?}", pcap);
+    /// }
+    ///
+    /// for err in packets.errors() {
+    ///     println!("{}", err);
+    /// }
+    /// ```
+    pub fn lossy(self) -> Lossy<T> {
+        Lossy::new(self)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over its packets along with the time elapsed since the previous one.
+    ///
+    /// The delta is None for the first packet, and zero for a packet older than the previous one. See `Deltas` for the details.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// for pcap in pcap_reader.with_deltas() {
+    ///     let (packet, delta) = pcap.unwrap();
+    ///     println!("{} bytes, {:?} after the previous packet", packet.data.len(), delta);
+    /// }
+    /// ```
+    pub fn with_deltas(self) -> Deltas<T> {
+        Deltas::new(self)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over its next `n` packets at most.
+    ///
+    /// Unlike `take()`, the `PcapReader` can be recovered with `Limit::into_inner`, and nothing is read past
+    /// the last packet yielded. A seekable reader can then be moved to the end of the file with `seek_to_end()`.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// // Preview of the capture
+    /// for pcap in pcap_reader.limit(10) {
+    ///     println!("{:?}", pcap.unwrap());
+    /// }
+    /// ```
+    pub fn limit(self, n: usize) -> Limit<T> {
+        Limit::new(self, n)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over the packets which were not truncated to the snaplen.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// for pcap in pcap_reader.only_complete() {
+    ///     let pcap = pcap.unwrap();
+    ///     assert_eq!(pcap.incl_len(), pcap.orig_len());
+    /// }
+    /// ```
+    pub fn only_complete(self) -> Truncation<T> {
+        Truncation::new(self, false)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over the packets which were truncated to the snaplen.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// println!("{} truncated packets", pcap_reader.only_truncated().count());
+    /// ```
+    pub fn only_truncated(self) -> Truncation<T> {
+        Truncation::new(self, true)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over the packets without the consecutive duplicates.
+    ///
+    /// A packet whose payload is the same as the one of the packet before it is dropped, the timestamps are ignored.
+    /// `Dedup::within` only drops the duplicates close in time to the previous packet.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use std::time::Duration;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// // Drop the frames mirrored twice, less than 1 ms apart
+    /// for pcap in pcap_reader.dedup().within(Duration::from_millis(1)) {
+    ///     let pcap = pcap.unwrap();
+    /// }
+    /// ```
+    pub fn dedup(self) -> Dedup<T> {
+        Dedup::new(self)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over its packets grouped by intervals of `interval`.
+    ///
+    /// Each group is yielded with the start of its bucket, a multiple of `interval` since the epoch.
+    /// The timestamps are expected to be roughly sorted: a packet out of order by less than one interval
+    /// still joins the bucket of its timestamp. See `Bucketed` for the details.
+    ///
+    /// # Panics
+    /// Panics if `interval` is 0.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use std::time::Duration;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// // Packets per second
+    /// for bucket in pcap_reader.bucketed(Duration::from_secs(1)) {
+    ///     let (start, packets) = bucket.unwrap();
+    ///     println!("{:?}: {} packets", start, packets.len());
+    /// }
+    /// ```
+    pub fn bucketed(self, interval: Duration) -> Bucketed<T> {
+        Bucketed::new(self, interval)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over the packets read during the next `wall` of real time.
+    ///
+    /// Unlike `take_until()`, the timestamps of the packets don't matter: the iteration stops once `wall`
+    /// has elapsed since this call, which suits sampling a live stream for a bounded period.
+    /// The deadline is checked between the packets, see `TakeFor`.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::io::stdin;
+    /// use std::time::Duration;
+    /// use pcap_file::PcapReader;
+    ///
+    /// // tcpdump -w - | sample
+    /// let pcap_reader = PcapReader::new(stdin()).unwrap();
+    ///
+    /// let sample: Vec<_> = pcap_reader.take_for(Duration::from_secs(5)).map(|pcap| pcap.unwrap()).collect();
+    /// println!("{} packets in 5 seconds", sample.len());
+    /// ```
+    pub fn take_for(self, wall: Duration) -> TakeFor<T> {
+        TakeFor::new(self, wall)
+    }
+
+    /// Returns an iterator over the next packets whose timestamp is before `ts`.
+    ///
+    /// The iteration stops at the first packet at or past `ts`, which isn't consumed:
+    /// it is the first packet yielded by the next call to `take_until()` or `next()`.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use std::time::Duration;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// // Process the capture in windows of 10 seconds
+    /// let mut end = Duration::from_secs(1_331_901_010);
+    /// while pcap_reader.peek_packet().is_some() {
+    ///
+    ///     let window: Vec<_> = pcap_reader.take_until(end).map(|pcap| pcap.unwrap()).collect();
+    ///     println!("{} packets before {:?}", window.len(), end);
+    ///
+    ///     end += Duration::from_secs(10);
+    /// }
+    /// ```
+    pub fn take_until(&mut self, ts: Duration) -> TakeUntil<'_, T> {
+        TakeUntil::new(self, ts)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over the packets which match a filter expression.
+    ///
+    /// The expression is compiled for the datalink of the file, see the `filter` module for the supported syntax.
+    /// This method isn't named `filter` to leave `Iterator::filter` usable on a `PcapReader`.
+    ///
+    /// # Errors
+    /// Return an `InvalidFilter` error if the expression can't be compiled.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// for pcap in pcap_reader.filter_expr("tcp port 443").unwrap() {
+    ///     let pcap = pcap.unwrap();
+    /// }
+    /// ```
+    #[cfg(feature = "bpf")]
+    pub fn filter_expr(self, expr: &str) -> ResultChain<Filtered<T>> {
+
+        let filter = Filter::new(expr, self.header.datalink)?;
+        Ok(Filtered::new(self, filter))
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over the headers of the remaining packets.
+    ///
+    /// The payloads are read and discarded without being allocated.
+    /// If the underlying reader can seek, `headers_seek` jumps over the payloads instead.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// let total: u64 = pcap_reader.headers().map(|header| u64::from(header.unwrap().orig_len)).sum();
+    /// println!("{} bytes on the wire", total);
+    /// ```
+    pub fn headers(self) -> Headers<T> {
+        Headers::new(self)
+    }
+
+    /// Consumes the `PcapReader`, returning the number of remaining packets.
+    ///
+    /// Only the packet headers are parsed, the payloads are read and discarded without being allocated.
+    /// If the underlying reader can seek, `count_packets_seek` jumps over the payloads instead.
+    ///
+    /// # Errors
+    /// Return an error if a packet header is invalid or if the last packet is truncated.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// println!("{} packets", pcap_reader.count_packets().unwrap());
+    /// ```
+    pub fn count_packets(mut self) -> ResultChain<u64> {
+
+        let mut count = 0;
+        while !self.is_eof()? {
+
+            self.locate(|reader| {
+                let header = reader.read_packet_header()?;
+                reader.skip_packet_data(&header)
+            })?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Consumes the `PcapReader`, returning the statistics of the remaining packets.
+    ///
+    /// Only the packet headers are parsed, the payloads are read and discarded without being allocated.
+    /// If the underlying reader can seek, `summarize_seek` jumps over the payloads instead.
+    ///
+    /// # Errors
+    /// Return an error if a packet header is invalid or if the last packet is truncated.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// let stats = pcap_reader.summarize().unwrap();
+    /// println!("{} packets in {:?}, {:?} pps", stats.packet_count, stats.duration(), stats.packets_per_second());
+    /// ```
+    pub fn summarize(mut self) -> ResultChain<CaptureStats> {
+
+        let mut stats = CaptureStats::default();
+        while !self.is_eof()? {
+
+            let header = self.locate(|reader| {
+                let header = reader.read_packet_header()?;
+                reader.skip_packet_data(&header)?;
+                Ok(header)
+            })?;
+            stats.add(&header);
+        }
+
+        Ok(stats)
+    }
+
+    /// Parses the packet header at the current position
+    pub(crate) fn read_packet_header(&mut self) -> ResultChain<PacketHeader> {
+
+        let mut header = [0u8; PcapHeader::MAX_PACKET_HEADER_LEN];
+        let header = &mut header[..self.header.packet_header_len()];
+        read_full(&mut self.reader, header)?;
+
+        self.parse_packet_header(header)
+    }
+
+    /// Parses the packet header bytes just read
+    fn parse_packet_header(&mut self, bytes: &[u8]) -> ResultChain<PacketHeader> {
+
+        let header = self.header.read_packet_header(&mut &bytes[..], self.fix_orig_len, self.swap_len_fields)?;
+        self.check_packet_len(&header)?;
+
+        // The packet following a located one is located too
+        let start = self.offset() - bytes.len() as u64;
+        if let Some(index) = self.packet_index(start) {
+            self.next_packet = Some((self.offset() + u64::from(header.incl_len), index + 1));
+        }
+
+        Ok(header)
+    }
+
+    /// Returns the index of the packet at `offset` in the file, if it is known
+    fn packet_index(&self, offset: u64) -> Option<u64> {
+
+        match self.next_packet {
+            _ if offset == self.data_offset => Some(0),
+            Some((next_offset, index)) if next_offset == offset => Some(index),
+            _ => None
+        }
+    }
+
+    /// Runs `read` at the current position, chaining its error to a `ReadPacket` error locating the packet
+    pub(crate) fn locate<R, F>(&mut self, read: F) -> ResultChain<R> where F: FnOnce(&mut PcapReader<T>) -> ResultChain<R> {
+
+        let offset = self.offset();
+        let index = self.packet_index(offset);
+
+        read(self).chain_err(|| ErrorKind::ReadPacket(index, offset))
+    }
+
+    /// Refuses the packets longer than the maximum packet length, before their payload is allocated
+    ///
+    /// The ones only longer than the effective snaplen are truncated by `read_payload`.
+    fn check_packet_len(&self, header: &PacketHeader) -> ResultChain<()> {
+
+        if header.incl_len > self.max_packet_len {
+            bail!(ErrorKind::PacketTooLarge(header.incl_len, self.max_packet_len));
+        }
+
+        Ok(())
+    }
+
+    /// Reads the payload of the packet whose header has just been parsed
+    pub(crate) fn read_packet_data(&mut self, mut header: PacketHeader) -> ResultChain<Packet<'static>> {
+
+        let mut data = Vec::new();
+        self.read_payload(&mut header, &mut data)?;
+
+        Ok(
+            Packet {
+                header,
+                data: Cow::Owned(data)
+            }
+        )
+    }
+
+    /// Appends the payload of the packet whose header has just been parsed to `buf`
+    ///
+    /// The bytes past the effective snaplen are skipped and the incl_len of the header lowered to it.
+    /// In lenient mode, a truncated payload is kept and the incl_len of the header lowered to its length.
+    pub(crate) fn read_payload(&mut self, header: &mut PacketHeader, buf: &mut Vec<u8>) -> ResultChain<()> {
+
+        let len = header.incl_len.min(self.header.effective_snaplen());
+        let start = buf.len();
+        buf.resize(start + len as usize, 0);
+
+        match read_full(&mut self.reader, &mut buf[start..]) {
+            Err(Error(ErrorKind::TruncatedPacket(_, got), _)) if self.lenient => {
+                buf.truncate(start + got);
+                header.incl_len = got as u32;
+                return Ok(());
+            },
+            result => result?
+        }
+
+        // Like libpcap, only the first snaplen bytes of a longer packet are kept
+        let excess = u64::from(header.incl_len - len);
+        if excess > 0 {
+
+            let skipped = io::copy(&mut (&mut self.reader).take(excess), &mut io::sink())?;
+            if skipped < excess && !self.lenient {
+                bail!(ErrorKind::TruncatedPacket(header.incl_len as usize, (u64::from(len) + skipped) as usize));
+            }
+
+            header.incl_len = len;
+        }
+
+        Ok(())
+    }
+
+    /// Skips the payload of the packet whose header has just been parsed, without allocating it
+    pub(crate) fn skip_packet_data(&mut self, header: &PacketHeader) -> ResultChain<()> {
+
+        let len = u64::from(header.incl_len);
+        let skipped = io::copy(&mut (&mut self.reader).take(len), &mut io::sink())?;
+
+        if skipped < len && !self.lenient {
+            bail!(ErrorKind::TruncatedPacket(len as usize, skipped as usize));
+        }
+
+        Ok(())
+    }
+
+    /// Parses the packet at the current position
+    fn read_packet(&mut self) -> ResultChain<Packet<'static>> {
+
+        let header = self.read_packet_header()?;
+        self.read_packet_data(header)
+    }
+
+    /// Parses the next packet, if any, its error being located
+    fn read_next(&mut self) -> ResultChain<Option<Packet<'static>>> {
+
+        self.locate(|reader| {
+
+            if reader.is_eof()? {
+                return Ok(None);
+            }
+
+            reader.read_packet().map(Some)
+        })
+    }
+}
+
+impl <T:Read + Seek> PcapReader<T> {
+
+    /// Create a new PcapReader from an existing seekable reader.
+    ///
+    /// Same as `new()`, but the length of the stream is measured once so that `size_hint()`
+    /// reports an upper bound of the remaining number of packets.
+    ///
+    /// # Errors
+    /// Return an error if the data stream is not in a valid pcap file format.
+    /// Or if the underlying data are not readable or seekable.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file_in = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new_seek(file_in).unwrap();
+    ///
+    /// let (_, max_packets) = pcap_reader.size_hint();
+    /// println!("At most {} packets", max_packets.unwrap());
+    /// ```
+    pub fn new_seek(reader: T) -> ResultChain<PcapReader<T>> {
+
+        let mut pcap_reader = PcapReader::new(reader)?;
+
+        let position = pcap_reader.offset();
+        let end = pcap_reader.reader.seek(SeekFrom::End(0))?;
+        pcap_reader.seek_to(position)?;
+
+        pcap_reader.stream_len = Some(end);
+        Ok(pcap_reader)
+    }
+
+    /// Moves the underlying reader to the end of the stream, so that it is exhausted.
+    ///
+    /// The bytes buffered to peek at the next packet are dropped, the wrapped reader returned by `into_reader()`
+    /// is then positioned at the end of the stream.
+    ///
+    /// # Errors
+    /// Return an error if the underlying reader can't seek.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// let mut preview = pcap_reader.limit(10);
+    /// let packets: Vec<_> = preview.by_ref().collect();
+    ///
+    /// let mut pcap_reader = preview.into_inner();
+    /// pcap_reader.seek_to_end().unwrap();
+    /// let file = pcap_reader.into_reader();
+    /// ```
+    pub fn seek_to_end(&mut self) -> ResultChain<()> {
+
+        self.reader.seek(SeekFrom::End(0))?;
+        Ok(())
+    }
+
+    /// Moves the underlying reader to the given byte offset, which must be the start of a packet.
+    ///
+    /// The offset is counted like `offset()`, from the position of the underlying reader when the `PcapReader`
+    /// was created, even if it wasn't the start of the stream.
+    ///
+    /// The reading state is reset so the next call to `next()` parses the packet at this offset:
+    /// the peeked bytes are dropped, while the global header is kept and never read again.
+    ///
+    /// # Errors
+    /// Return an error if the underlying reader can't seek.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// let offset = pcap_reader.offset();
+    /// let first = pcap_reader.next().unwrap().unwrap();
+    ///
+    /// // Read the first packet again
+    /// pcap_reader.seek_to(offset).unwrap();
+    /// let first_again = pcap_reader.next().unwrap().unwrap();
+    /// ```
+    pub fn seek_to(&mut self, offset: u64) -> ResultChain<()> {
+
+        self.reader.seek(SeekFrom::Start(offset))?;
+        Ok(())
+    }
+
+    /// Scans the whole file once and records the offset and the timestamp of every packet.
+    ///
+    /// Only the packet headers are read, the payloads are skipped.
+    /// The position of the reader is restored afterwards.
+    ///
+    /// # Errors
+    /// Return an error if a packet header is invalid, if the last packet is truncated while the reader isn't lenient,
+    /// or if the underlying reader can't seek.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// let index = pcap_reader.index().unwrap();
+    /// println!("{} packets", index.len());
+    /// ```
+    pub fn index(&mut self) -> ResultChain<PacketIndex> {
+
+        let position = self.offset();
+        let next_packet = self.next_packet;
+        let end = match self.stream_len {
+            Some(end) => end,
+            None => self.reader.seek(SeekFrom::End(0))?
+        };
+
+        let data_offset = self.data_offset;
+        self.seek_to(data_offset)?;
+
+        let mut index = PacketIndex::default();
+        while !self.is_eof()? {
+
+            let offset = self.offset();
+            let header = self.locate(|reader| {
+
+                let mut header = reader.read_packet_header()?;
+
+                // A truncated last packet is an error, unless the reader is lenient
+                reader.seek_packet_data(&mut header, end)?;
+                Ok(header)
+            })?;
+
+            let timestamp = u64::from(header.ts_sec) * 1_000_000_000 + u64::from(header.ts_nsec());
+            index.entries.push((offset, timestamp));
+        }
+
+        self.seek_to(position)?;
+        self.next_packet = match index.entries.iter().position(|&(offset, _)| offset == position) {
+            Some(n) => Some((position, n as u64)),
+            None => next_packet
+        };
+
+        Ok(index)
+    }
+
+    /// Consumes the `PcapReader`, returning the number of remaining packets.
+    ///
+    /// Only the packet headers are read, the payloads are skipped by seeking.
+    ///
+    /// # Errors
+    /// Return an error if a packet header is invalid or if the last packet is truncated.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// println!("{} packets", pcap_reader.count_packets_seek().unwrap());
+    /// ```
+    pub fn count_packets_seek(mut self) -> ResultChain<u64> {
+
+        // Seeking past the end succeeds, the payloads must be checked against the length of the stream
+        let position = self.offset();
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        self.seek_to(position)?;
+
+        let mut count = 0;
+        while !self.is_eof()? {
+
+            self.locate(|reader| {
+                let mut header = reader.read_packet_header()?;
+                reader.seek_packet_data(&mut header, end)
+            })?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Consumes the `PcapReader`, returning the statistics of the remaining packets.
+    ///
+    /// Same as `summarize`, but the payloads are skipped by seeking, which is much faster than reading them on big files.
+    ///
+    /// # Errors
+    /// Return an error if a packet header is invalid, if the last packet is truncated or if the underlying reader can't seek.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// let stats = pcap_reader.summarize_seek().unwrap();
+    /// println!("{} bytes, {:.1} bytes per packet", stats.total_bytes, stats.avg_len());
+    /// ```
+    pub fn summarize_seek(mut self) -> ResultChain<CaptureStats> {
+
+        let position = self.offset();
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        self.seek_to(position)?;
+
+        let mut stats = CaptureStats::default();
+        while !self.is_eof()? {
+
+            let header = self.locate(|reader| {
+                let mut header = reader.read_packet_header()?;
+                reader.seek_packet_data(&mut header, end)?;
+                Ok(header)
+            })?;
+            stats.add(&header);
+        }
+
+        Ok(stats)
+    }
+
+    /// Consumes the `PcapReader`, returning an iterator over the headers of the remaining packets.
+    ///
+    /// The payloads are skipped by seeking, which is much faster than reading them on big files.
+    ///
+    /// # Errors
+    /// Return an error if the underlying reader can't seek.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// // Histogram of the packet sizes, by 100 bytes
+    /// let mut histogram = vec![0; 700];
+    /// for header in pcap_reader.headers_seek().unwrap() {
+    ///     histogram[header.unwrap().orig_len as usize / 100] += 1;
+    /// }
+    /// ```
+    pub fn headers_seek(mut self) -> ResultChain<SeekHeaders<T>> {
+
+        let end = match self.stream_len {
+            Some(end) => end,
+            None => {
+                let position = self.offset();
+                let end = self.reader.seek(SeekFrom::End(0))?;
+                self.seek_to(position)?;
+                end
+            }
+        };
+
+        Ok(SeekHeaders::new(self, end))
+    }
+
+    /// Seeks past the payload of the packet whose header has just been parsed, in a stream of `end` bytes
+    ///
+    /// In lenient mode, the incl_len of a truncated payload is lowered to its length.
+    pub(crate) fn seek_packet_data(&mut self, header: &mut PacketHeader, end: u64) -> ResultChain<()> {
+
+        // Seeking past the end succeeds, the payload must be checked against the length of the stream
+        let available = end.saturating_sub(self.offset());
+        if u64::from(header.incl_len) > available {
+
+            if !self.lenient {
+                bail!(ErrorKind::TruncatedPacket(header.incl_len as usize, available as usize));
+            }
+            header.incl_len = available as u32;
+        }
+
+        self.reader.seek(SeekFrom::Current(i64::from(header.incl_len)))?;
+        Ok(())
+    }
+
+    /// Reads the nth packet of a `PacketIndex` built by `index()`.
+    ///
+    /// The reader stays positioned after this packet.
+    ///
+    /// # Errors
+    /// Return an error if the packet isn't in the index or if it can't be read.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// let index = pcap_reader.index().unwrap();
+    /// let packet = pcap_reader.read_packet_at(&index, 5).unwrap();
+    /// ```
+    pub fn read_packet_at(&mut self, index: &PacketIndex, n: usize) -> ResultChain<Packet<'static>> {
+
+        let offset = match index.offset(n) {
+            Some(offset) => offset,
+            None => bail!(ErrorKind::PacketIndexOutOfRange(n, index.len()))
+        };
+
+        self.seek_to(offset)?;
+        self.next_packet = Some((offset, n as u64));
+
+        self.locate(|reader| reader.read_packet())
+    }
+
+    /// Returns an iterator over the packets of a `PacketIndex` built by `index()`, from the last one to the first one.
+    ///
+    /// Each packet is read by seeking to its offset, the packets are the same as in the forward order.
+    /// The reader stays positioned after the last packet yielded, which is the first one of the file once the iterator is done.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file = File::open("test.pcap").expect("Error opening file");
+    /// let mut pcap_reader = PcapReader::new(file).unwrap();
+    ///
+    /// // The last 100 packets, the most recent first
+    /// let index = pcap_reader.index().unwrap();
+    /// for pcap in pcap_reader.rev_from_index(&index).take(100) {
+    ///     println!("{:?}", pcap.unwrap());
+    /// }
+    /// ```
+    pub fn rev_from_index<'a>(&'a mut self, index: &'a PacketIndex) -> RevFromIndex<'a, T> {
+        RevFromIndex::new(self, index)
+    }
+}
+
+impl PcapReader<Box<dyn Read>> {
+
+    /// Opens the pcap file at the given path and creates a new PcapReader from it.
+    ///
+    /// The file is buffered. If the `gzip` feature is enabled and the file starts with the gzip magic,
+    /// it is transparently decompressed.
+    ///
+    /// # Errors
+    /// Return an error if the file can't be opened or if it is not in a valid pcap file format.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use pcap_file::PcapReader;
+    ///
+    /// let pcap_reader = PcapReader::from_path("test.pcap.gz").unwrap();
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> ResultChain<PcapReader<Box<dyn Read>>> {
+
+        let mut file = BufReader::new(File::open(path)?);
+
+        if detect_format(file.fill_buf()?) == Format::Gzip {
+
+            #[cfg(feature = "gzip")]
+            return PcapReader::new(Box::new(GzDecoder::new(file)));
+
+            #[cfg(not(feature = "gzip"))]
+            bail!(ErrorKind::WrongField("The file is gzip-compressed, the gzip feature is required to read it".to_string()));
+        }
+
+        PcapReader::new(Box::new(file))
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl <T:Read> PcapReader<GzDecoder<T>> {
+
+    /// Create a new PcapReader from a reader of a gzip-compressed pcap file.
+    /// The stream is decompressed before reading the global pcap header.
+    ///
+    /// # Errors
+    /// Return an error if the decompressed stream is not in a valid pcap file format.
+    /// Or if the underlying data are not readable.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapReader;
+    ///
+    /// let file_in = File::open("test.pcap.gz").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new_gz(file_in).unwrap();
+    /// ```
+    pub fn new_gz(reader: T) -> ResultChain<PcapReader<GzDecoder<T>>> {
+        PcapReader::new(GzDecoder::new(reader))
+    }
+}
+
+impl <T:Read> Iterator for PcapReader<T> {
+
+    type Item = ResultChain<Packet<'static>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'static>>> {
+
+        self.read_next().transpose()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+
+        let end = match self.stream_len {
+            Some(end) => end,
+            None => return (0, None)
+        };
+
+        // Every record is at least a packet header long, except a truncated last one
+        let remaining = end.saturating_sub(self.offset());
+        let header_len = self.header.packet_header_len() as u64;
+        let max = (remaining + header_len - 1) / header_len;
+
+        // A truncated packet header at the end is an error, an item, unless the reader is lenient and ignores it
+        let min = match self.lenient {
+            false => remaining > 0,
+            true => remaining >= header_len
+        };
+        (min as usize, Some(max as usize))
+    }
+}
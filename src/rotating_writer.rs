@@ -0,0 +1,181 @@
+//! This module contains the `RotatingWriter` struct which is used to write a capture into a ring of pcap files
+
+use std::io::Write;
+
+use errors::*;
+
+use packet::Packet;
+use pcap_header::PcapHeader;
+use writer::PcapWriter;
+
+
+/// This struct writes packets into a sequence of outputs, rolling over to a new one when the current one is full,
+/// like the `-C` and `-W` options of tcpdump.
+///
+/// Each output starts with the configured global header. New outputs are requested from a closure, with the index
+/// of their slot: at most `max_files` slots are used, the oldest output being replaced once they are all used.
+/// Opening the output with `File::create` truncates the file of the recycled slot.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use std::io::BufWriter;
+/// use pcap_file::{PcapHeader, RotatingWriter};
+///
+/// // Keep the last 5 files of 10 MiB
+/// let mut rotating_writer = RotatingWriter::by_size(PcapHeader::default(), 10 * 1024 * 1024, 5, |slot| {
+///     BufWriter::new(File::create(format!("ring_{}.pcap", slot)).expect("Error creating file"))
+/// }).unwrap();
+///
+/// rotating_writer.write(0, 0, &[0u8; 10]).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct RotatingWriter<W: Write, F> {
+
+    /// Global header written at the start of each output
+    pub header: PcapHeader,
+    new_writer: F,
+    max_packets: usize,
+    max_bytes: u64,
+    max_files: usize,
+    current: Option<PcapWriter<W>>,
+    outputs: usize,
+    packets: usize,
+    bytes: u64
+}
+
+impl<W: Write, F: FnMut(usize) -> W> RotatingWriter<W, F> {
+
+    /// Create a new `RotatingWriter` whose outputs hold at most `max_packets` packets, in at most `max_files` slots.
+    ///
+    /// # Errors
+    /// Return an error if `max_packets` or `max_files` is 0.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{PcapHeader, RotatingWriter};
+    ///
+    /// let rotating_writer = RotatingWriter::by_count(PcapHeader::default(), 1000, 10, |slot| {
+    ///     File::create(format!("ring_{}.pcap", slot)).expect("Error creating file")
+    /// }).unwrap();
+    /// ```
+    pub fn by_count(header: PcapHeader, max_packets: usize, max_files: usize, new_writer: F) -> ResultChain<RotatingWriter<W, F>> {
+
+        if max_packets == 0 {
+            bail!(ErrorKind::WrongField("Can't write outputs of 0 packets".to_string()));
+        }
+
+        RotatingWriter::with_limits(header, max_packets, u64::MAX, max_files, new_writer)
+    }
+
+    /// Create a new `RotatingWriter` whose outputs hold at most `max_bytes` octets, in at most `max_files` slots.
+    ///
+    /// The size of an output includes its global header and the header of each of its packets.
+    /// A packet which doesn't fit in an empty output is written alone, its output exceeds the limit.
+    ///
+    /// # Errors
+    /// Return an error if `max_bytes` can't hold the global header or if `max_files` is 0.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{PcapHeader, RotatingWriter};
+    ///
+    /// let rotating_writer = RotatingWriter::by_size(PcapHeader::default(), 1024 * 1024, 10, |slot| {
+    ///     File::create(format!("ring_{}.pcap", slot)).expect("Error creating file")
+    /// }).unwrap();
+    /// ```
+    pub fn by_size(header: PcapHeader, max_bytes: u64, max_files: usize, new_writer: F) -> ResultChain<RotatingWriter<W, F>> {
+
+        if max_bytes <= PcapHeader::SIZE as u64 {
+            bail!(ErrorKind::WrongField(format!("Can't write outputs of {} bytes, the global header takes {} bytes", max_bytes, PcapHeader::SIZE)));
+        }
+
+        RotatingWriter::with_limits(header, usize::MAX, max_bytes, max_files, new_writer)
+    }
+
+    /// Creates the `RotatingWriter`, no output is requested before the first packet
+    fn with_limits(header: PcapHeader, max_packets: usize, max_bytes: u64, max_files: usize, new_writer: F) -> ResultChain<RotatingWriter<W, F>> {
+
+        if max_files == 0 {
+            bail!(ErrorKind::WrongField("Can't write packets into 0 files".to_string()));
+        }
+
+        Ok(
+            RotatingWriter {
+                header,
+                new_writer,
+                max_packets,
+                max_bytes,
+                max_files,
+                current: None,
+                outputs: 0,
+                packets: 0,
+                bytes: 0
+            }
+        )
+    }
+
+    /// Returns the number of outputs created so far, recycled slots included.
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+
+    /// Writes a packet, the same way as `PcapWriter::write`.
+    ///
+    /// # Errors
+    /// Return an error if an output can't be written to.
+    pub fn write(&mut self, ts_sec: u32, ts_usec: u32, data: &[u8]) -> ResultChain<()> {
+        self.write_packet(&Packet::new(ts_sec, ts_usec, data.len() as u32, data))
+    }
+
+    /// Writes a `Packet` into the current output, rolling over to a new output first if it doesn't fit.
+    ///
+    /// The full output is flushed before the new one is requested.
+    ///
+    /// # Errors
+    /// Return an error if the packet is invalid or if an output can't be written to.
+    pub fn write_packet(&mut self, packet: &Packet) -> ResultChain<()> {
+
+        let len = packet.record_len_in(&self.header) as u64;
+
+        let full = self.packets >= self.max_packets || (self.packets > 0 && self.bytes + len > self.max_bytes);
+        if full {
+            if let Some(mut writer) = self.current.take() {
+                writer.flush()?;
+            }
+        }
+
+        let writer = match self.current {
+            Some(ref mut writer) => writer,
+            None => {
+                let slot = self.outputs % self.max_files;
+                self.packets = 0;
+                self.bytes = PcapHeader::SIZE as u64;
+                self.outputs += 1;
+
+                self.current.get_or_insert(PcapWriter::with_header(self.header, (self.new_writer)(slot))?)
+            }
+        };
+
+        writer.write_packet(packet)?;
+        self.packets += 1;
+        self.bytes += len;
+
+        Ok(())
+    }
+
+    /// Consumes the `RotatingWriter`, flushing and returning the current output if any.
+    ///
+    /// # Errors
+    /// Return an error if the current output can't be flushed.
+    pub fn into_writer(self) -> ResultChain<Option<W>> {
+
+        match self.current {
+            Some(writer) => Ok(Some(writer.into_writer()?)),
+            None => Ok(None)
+        }
+    }
+}
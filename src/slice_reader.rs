@@ -0,0 +1,167 @@
+//! This module contains the `PcapSliceReader` struct which is used to read from an in-memory pcap
+
+use std::borrow::Cow;
+
+use errors::*;
+
+use iter::SliceHeadersAndData;
+use packet::{Packet, PacketHeader};
+use pcap_header::PcapHeader;
+
+
+/// This struct wraps a byte slice containing a whole pcap file and enables it to be read without copy.
+///
+/// It implements the Iterator trait in order to read one packet at a time.
+/// The yielded packets borrow their payload from the slice, no allocation is done per packet.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use std::io::Read;
+/// use pcap_file::PcapSliceReader;
+///
+/// let mut data = Vec::new();
+/// File::open("test.pcap").unwrap().read_to_end(&mut data).unwrap();
+///
+/// let pcap_reader = PcapSliceReader::new(&data).unwrap();
+///
+/// // Read test.pcap
+/// for pcap in pcap_reader {
+///
+///     //Check if there is no error
+///     let pcap = pcap.unwrap();
+///
+///     //The payload is borrowed from data
+///     println!("{} bytes", pcap.data.len());
+/// }
+/// ```
+///
+/// Parsing the packets is cheap since their payload isn't copied, so the packets of a large capture can be
+/// collected first and then processed in parallel, for example with rayon:
+///
+/// ```rust,ignore
+/// let packets = PcapSliceReader::new(&data)?.collect::<ResultChain<Vec<_>>>()?;
+///
+/// // The results are in the order of the packets
+/// let results: Vec<_> = packets.par_iter().map(|packet| analyze(packet)).collect();
+/// ```
+#[derive(Debug)]
+pub struct PcapSliceReader<'a> {
+
+    pub header: PcapHeader,
+    data: &'a [u8]
+}
+
+impl<'a> PcapSliceReader<'a> {
+
+    /// Create a new PcapSliceReader from a byte slice.
+    /// This function read the global pcap header of the slice to verify its integrity.
+    ///
+    /// # Errors
+    /// Return an error if the slice doesn't start with a valid pcap global header.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use pcap_file::PcapSliceReader;
+    ///
+    /// let data = std::fs::read("test.pcap").expect("Error reading file");
+    /// let pcap_reader = PcapSliceReader::new(&data).unwrap();
+    /// ```
+    pub fn new(mut data: &'a [u8]) -> ResultChain<PcapSliceReader<'a>> {
+
+        Ok(
+            PcapSliceReader {
+
+                header : PcapHeader::from_reader(&mut data)?,
+                data
+            }
+        )
+    }
+
+    /// Returns the part of the slice which hasn't been read yet.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use pcap_file::PcapSliceReader;
+    ///
+    /// let data = std::fs::read("test.pcap").expect("Error reading file");
+    /// let pcap_reader = PcapSliceReader::new(&data).unwrap();
+    ///
+    /// assert_eq!(pcap_reader.remaining().len(), data.len() - 24);
+    /// ```
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Consumes the `PcapSliceReader`, returning an iterator over the header and the payload of each packet,
+    /// the payload borrowing the slice.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use pcap_file::PcapSliceReader;
+    ///
+    /// let data = std::fs::read("test.pcap").expect("Error reading file");
+    /// let pcap_reader = PcapSliceReader::new(&data).unwrap();
+    ///
+    /// let (headers, payloads): (Vec<_>, Vec<_>) = pcap_reader.headers_and_data()
+    ///     .map(|record| record.unwrap())
+    ///     .unzip();
+    /// ```
+    pub fn headers_and_data(self) -> SliceHeadersAndData<'a> {
+        SliceHeadersAndData::new(self)
+    }
+
+    /// Reads the header and the payload of the next packet, stopping there on error
+    pub(crate) fn next_record(&mut self) -> Option<ResultChain<(PacketHeader, &'a [u8])>> {
+
+        if self.data.is_empty() {
+            return None;
+        }
+
+        match self.read_record() {
+            Ok(record) => Some(Ok(record)),
+
+            // The position of the next packet is unknown, stop there
+            Err(err) => {
+                self.data = &[];
+                Some(Err(err))
+            }
+        }
+    }
+
+    /// Parses the packet at the start of the remaining slice and advances past it
+    fn read_record(&mut self) -> ResultChain<(PacketHeader, &'a [u8])> {
+
+        let header_len = self.header.packet_header_len();
+        if self.data.len() < header_len {
+            bail!(ErrorKind::BufferUnderflow(header_len as u64, self.data.len() as u64));
+        }
+
+        let (header, len) = self.header.read_record_header(self.data)?;
+        let data = &self.data[header_len..];
+
+        if len > data.len() {
+            bail!(ErrorKind::BufferUnderflow(len as u64, data.len() as u64));
+        }
+
+        // The bytes past the effective snaplen are skipped
+        self.data = &data[len..];
+        Ok((header, &data[..header.incl_len as usize]))
+    }
+}
+
+impl<'a> Iterator for PcapSliceReader<'a> {
+
+    type Item = ResultChain<Packet<'a>>;
+
+    fn next(&mut self) -> Option<ResultChain<Packet<'a>>> {
+
+        self.next_record().map(|record| record.map(|(header, data)| {
+            Packet {
+                header,
+                data: Cow::Borrowed(data)
+            }
+        }))
+    }
+}
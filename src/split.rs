@@ -0,0 +1,87 @@
+//! This module contains functions to split a pcap file into several smaller pcap files.
+//!
+//! Each output starts with the global header of the input, a new output is requested from a closure
+//! every time the current one reaches the threshold.
+
+use std::io::{Read, Write};
+
+use errors::*;
+
+use reader::PcapReader;
+use rotating_writer::RotatingWriter;
+
+
+/// Copies the packets of `reader` into outputs of at most `count` packets.
+///
+/// `new_writer` is called with the index of each output, starting at 0.
+/// Returns the number of outputs created, no output is created for an empty capture.
+///
+/// # Errors
+/// Return an error if `count` is 0, if a packet can't be read or if an output can't be written to.
+///
+/// # Examples
+/// ```rust,no_run
+/// use std::fs::File;
+/// use std::io::BufWriter;
+/// use pcap_file::PcapReader;
+/// use pcap_file::split::split_by_count;
+///
+/// let file_in = File::open("test.pcap").expect("Error opening file");
+/// let pcap_reader = PcapReader::new(file_in).unwrap();
+///
+/// let outputs = split_by_count(pcap_reader, 1000, |idx| {
+///     BufWriter::new(File::create(format!("out_{}.pcap", idx)).expect("Error creating file out"))
+/// }).unwrap();
+/// ```
+pub fn split_by_count<R, W, F>(reader: PcapReader<R>, count: usize, new_writer: F) -> ResultChain<usize>
+    where R: Read, W: Write, F: FnMut(usize) -> W
+{
+    let writer = RotatingWriter::by_count(reader.header, count, usize::MAX, new_writer)?;
+    split(reader, writer)
+}
+
+/// Copies the packets of `reader` into outputs of at most `bytes` octets.
+///
+/// The size of an output includes its global header and the header of each of its packets.
+/// A packet which doesn't fit in an empty output is written alone, its output exceeds the limit.
+///
+/// `new_writer` is called with the index of each output, starting at 0.
+/// Returns the number of outputs created, no output is created for an empty capture.
+///
+/// # Errors
+/// Return an error if `bytes` can't hold the global header, if a packet can't be read or if an output can't be written to.
+///
+/// # Examples
+/// ```rust,no_run
+/// use std::fs::File;
+/// use std::io::BufWriter;
+/// use pcap_file::PcapReader;
+/// use pcap_file::split::split_by_size;
+///
+/// let file_in = File::open("test.pcap").expect("Error opening file");
+/// let pcap_reader = PcapReader::new(file_in).unwrap();
+///
+/// let outputs = split_by_size(pcap_reader, 10 * 1024 * 1024, |idx| {
+///     BufWriter::new(File::create(format!("out_{}.pcap", idx)).expect("Error creating file out"))
+/// }).unwrap();
+/// ```
+pub fn split_by_size<R, W, F>(reader: PcapReader<R>, bytes: u64, new_writer: F) -> ResultChain<usize>
+    where R: Read, W: Write, F: FnMut(usize) -> W
+{
+    let writer = RotatingWriter::by_size(reader.header, bytes, usize::MAX, new_writer)?;
+    split(reader, writer)
+}
+
+/// Copies the packets of `reader` into `writer`, whose slots are never recycled
+fn split<R, W, F>(reader: PcapReader<R>, mut writer: RotatingWriter<W, F>) -> ResultChain<usize>
+    where R: Read, W: Write, F: FnMut(usize) -> W
+{
+    for packet in reader {
+        writer.write_packet(&packet?)?;
+    }
+
+    let outputs = writer.outputs();
+    writer.into_writer()?;
+
+    Ok(outputs)
+}
@@ -0,0 +1,80 @@
+//! This module contains the `CaptureStats` struct which summarizes the packets of a pcap file.
+
+use std::time::Duration;
+
+use packet::PacketHeader;
+
+
+/// Summary of the packets of a pcap file, built from their headers only.
+///
+/// It is returned by `PcapReader::summarize` and `PcapReader::summarize_seek`.
+/// The lengths are the captured lengths, the packet headers excluded.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CaptureStats {
+
+    /// Earliest timestamp, as a `Duration` since the epoch, None if there is no packet
+    pub first_ts: Option<Duration>,
+
+    /// Latest timestamp, as a `Duration` since the epoch, None if there is no packet
+    pub last_ts: Option<Duration>,
+
+    /// Number of packets
+    pub packet_count: u64,
+
+    /// Sum of the captured lengths of the packets
+    pub total_bytes: u64,
+
+    /// Captured length of the shortest packet, 0 if there is no packet
+    pub min_len: u32,
+
+    /// Captured length of the longest packet, 0 if there is no packet
+    pub max_len: u32
+}
+
+impl CaptureStats {
+
+    /// Accounts for one more packet
+    pub(crate) fn add(&mut self, header: &PacketHeader) {
+
+        // The packets may not be sorted by timestamp
+        let ts = header.duration_since_epoch();
+        self.first_ts = Some(self.first_ts.map_or(ts, |first| first.min(ts)));
+        self.last_ts = Some(self.last_ts.map_or(ts, |last| last.max(ts)));
+
+        self.min_len = if self.packet_count == 0 { header.incl_len } else { self.min_len.min(header.incl_len) };
+        self.max_len = self.max_len.max(header.incl_len);
+
+        self.packet_count += 1;
+        self.total_bytes += u64::from(header.incl_len);
+    }
+
+    /// Returns the time elapsed between the earliest and the latest packets.
+    pub fn duration(&self) -> Duration {
+
+        match (self.first_ts, self.last_ts) {
+            (Some(first), Some(last)) => last - first,
+            _ => Duration::default()
+        }
+    }
+
+    /// Returns the average captured length of the packets, 0 if there is no packet.
+    pub fn avg_len(&self) -> f64 {
+
+        if self.packet_count == 0 {
+            return 0.0;
+        }
+
+        self.total_bytes as f64 / self.packet_count as f64
+    }
+
+    /// Returns the average number of packets per second, None if the capture lasts less than a nanosecond.
+    pub fn packets_per_second(&self) -> Option<f64> {
+
+        let duration = self.duration();
+        if duration == Duration::default() {
+            return None;
+        }
+
+        Some(self.packet_count as f64 / duration.as_secs_f64())
+    }
+}
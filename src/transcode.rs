@@ -0,0 +1,50 @@
+//! This module contains the `transcode` function which rewrites a pcap file in another endianness
+
+use std::io::{Read, Write};
+
+use errors::*;
+
+use pcap_header::Endianness;
+use reader::PcapReader;
+use writer::PcapWriter;
+
+
+/// Copies a pcap file from `reader` to `writer`, its global header and its packet headers written in the `target` endianness.
+///
+/// The other fields of the headers, the timestamp resolution and the modified pcap format are kept,
+/// and the payloads are copied byte for byte. The file is copied as is if it already is in the `target` endianness.
+/// As with `PcapWriter`, a packet longer than the snaplen is truncated to it.
+///
+/// Returns the writer, flushed.
+///
+/// # Errors
+/// Return an error if the file is not in a valid pcap file format, if a packet is invalid,
+/// or if the writer can't be written to.
+///
+/// # Examples
+/// ```rust,no_run
+/// use std::fs::File;
+/// use pcap_file::{transcode, Endianness};
+///
+/// let file_in = File::open("test.pcap").expect("Error opening file");
+/// let file_out = File::create("out.pcap").expect("Error creating file");
+///
+/// transcode(file_in, file_out, Endianness::Big).unwrap();
+/// ```
+pub fn transcode<R: Read, W: Write>(reader: R, writer: W, target: Endianness) -> ResultChain<W> {
+
+    let reader = PcapReader::new(reader)?;
+
+    // The magic number of the other endianness is the byte swap of this one
+    let mut header = reader.header;
+    if header.endianness() != target {
+        header.magic_number = header.magic_number.swap_bytes();
+    }
+
+    let mut writer = PcapWriter::with_header(header, writer)?;
+    for packet in reader {
+        writer.write_packet(&packet?)?;
+    }
+
+    writer.into_writer()
+}
@@ -0,0 +1,121 @@
+//! This module contains the `validate` function which checks a whole pcap file without yielding its packets
+
+use std::io::Read;
+
+use errors::*;
+
+use pcap_header::PcapHeader;
+use reader::PcapReader;
+
+
+/// Result of the validation of a pcap file by `validate`.
+#[derive(Clone, Debug)]
+pub struct ValidationReport {
+
+    /// Global header of the file
+    pub header: PcapHeader,
+
+    /// Number of complete packets before the end of the file or the first fatal anomaly
+    pub packet_count: u64,
+
+    /// Number of bytes read, global header included
+    pub total_bytes: u64,
+
+    /// True if the file ends between two packets
+    pub clean_end: bool,
+
+    /// First anomaly found in the file, if any
+    pub first_anomaly: Option<Anomaly>
+}
+
+impl ValidationReport {
+
+    /// Returns true if the file ends cleanly and no anomaly has been found.
+    pub fn is_valid(&self) -> bool {
+        self.clean_end && self.first_anomaly.is_none()
+    }
+}
+
+/// Describes an anomaly found by `validate`.
+#[derive(Clone, Debug)]
+pub struct Anomaly {
+
+    /// Byte offset of the packet header of the faulty packet
+    pub offset: u64,
+
+    /// Description of the anomaly
+    pub description: String
+}
+
+/// Checks a whole pcap file: its global header, then every packet header.
+///
+/// The payloads are skipped without being allocated, whatever their length.
+/// A packet longer than the snaplen is an anomaly but the validation goes on,
+/// an invalid packet header or a truncated packet ends it.
+///
+/// # Errors
+/// Return an error if the global header is invalid or if the reader fails.
+/// The anomalies of the packets are reported in the `ValidationReport`.
+///
+/// # Examples
+/// ```rust,no_run
+/// use std::fs::File;
+/// use pcap_file::validate;
+///
+/// let file = File::open("test.pcap").expect("Error opening file");
+/// let report = validate(file).unwrap();
+///
+/// if let Some(anomaly) = report.first_anomaly {
+///     println!("{} at offset {}", anomaly.description, anomaly.offset);
+/// }
+/// ```
+pub fn validate<T: Read>(reader: T) -> ResultChain<ValidationReport> {
+
+    let mut reader = PcapReader::new(reader)?;
+    reader.set_max_packet_len(u32::MAX);
+
+    let mut report = ValidationReport {
+        header: reader.header,
+        packet_count: 0,
+        total_bytes: reader.bytes_read(),
+        clean_end: false,
+        first_anomaly: None
+    };
+
+    loop {
+
+        if reader.is_eof()? {
+            report.clean_end = true;
+            break;
+        }
+
+        let offset = reader.offset();
+        let header = reader.read_packet_header().and_then(|header| {
+            reader.skip_packet_data(&header)?;
+            Ok(header)
+        });
+
+        let header = match header {
+            Ok(header) => header,
+            Err(Error(ErrorKind::Io(err), _)) => return Err(err.into()),
+            Err(err) => {
+                report.first_anomaly.get_or_insert(Anomaly { offset, description: err.to_string() });
+                break;
+            }
+        };
+
+        if header.incl_len > report.header.effective_snaplen() && report.first_anomaly.is_none() {
+            report.first_anomaly = Some(
+                Anomaly {
+                    offset,
+                    description: format!("PacketHeader.incl_len ({}) > PcapHeader.snaplen ({})", header.incl_len, report.header.effective_snaplen())
+                }
+            );
+        }
+
+        report.packet_count += 1;
+    }
+
+    report.total_bytes = reader.bytes_read();
+    Ok(report)
+}
@@ -1,14 +1,20 @@
 //! This module contains the `PcapWriter` struct which is used to write to a pcap file
 
 use std::borrow::Cow;
-use std::io::Write;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::time::Duration;
 
-use byteorder::{BigEndian, LittleEndian};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
 
-use packet::{Packet, PacketHeader};
-use pcap_header::{DataLink, Endianness, PcapHeader};
+use index::PacketLocation;
+use packet::{Packet, PacketBuilder, PacketHeader};
+use pcap_header::{magic_format, DataLink, Endianness, PcapHeader, TsResolution};
+use reader::PcapReader;
 use errors::*;
 
+/// Default capacity of the write buffer of a `PcapWriter`, 8 KiB.
+pub const DEFAULT_BUFFER_CAPACITY: usize = 8 * 1024;
+
 /// This struct wraps another writer and enables it to write a Pcap formated stream.
 ///
 /// # Examples
@@ -37,7 +43,17 @@ use errors::*;
 #[derive(Debug)]
 pub struct PcapWriter<T: Write> {
     pub header: PcapHeader,
-    writer: T,
+    /// Only taken by `into_writer`, so that `Drop` doesn't flush it again.
+    /// Its buffer holds `DEFAULT_BUFFER_CAPACITY` bytes by default, a capacity of 0 writes straight through.
+    writer: Option<BufWriter<T>>,
+    /// Number of bytes written since the start of the global header
+    written: u64,
+    /// Greatest incl_len of the written packets
+    max_incl_len: u32,
+    /// Greatest incl_len of the packets already in the file, only set by `append`
+    existing_max_incl_len: u32,
+    /// `finalize` returns an error if no packet was written
+    error_if_empty: bool
 }
 
 
@@ -81,6 +97,36 @@ impl<T: Write> PcapWriter<T> {
         PcapWriter::with_header(header, writer)
     }
 
+    /// Create a new `PcapWriter` from an existing writer, with nanosecond timestamps.
+    ///
+    /// It writes the default global pcap header with the 0xa1b23c4d magic number.
+    /// The `ts_usec` given to `write()` is then a number of nanoseconds, and the packets given to `write_packet()`
+    /// are converted to nanoseconds. `PcapWriterBuilder::ts_resolution` sets the resolution of the other headers.
+    ///
+    /// # Errors
+    ///
+    /// Return an error if the writer can't be written to.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapWriter;
+    ///
+    /// let file_out = File::create("out.pcap").expect("Error creating file");
+    /// let mut pcap_writer = PcapWriter::new_nanos(file_out).unwrap();
+    ///
+    /// pcap_writer.write(1, 123_456_789, &[0u8; 10]).unwrap();
+    /// ```
+    pub fn new_nanos(writer: T) -> ResultChain<PcapWriter<T>> {
+
+        let mut header = PcapHeader::with_datalink(DataLink::ETHERNET);
+        header.magic_number = 0xa1b23c4d;
+
+        PcapWriter::with_header(header, writer)
+    }
+
     /// Create a new `PcapWriter` from an existing writer with the given endianness.
     ///
     /// It Automatically writes this default global pcap header to the file:
@@ -124,6 +170,44 @@ impl<T: Write> PcapWriter<T> {
         PcapWriter::with_header(header, writer)
     }
 
+    /// Create a new `PcapWriter` from an existing writer with the given DataLink type.
+    ///
+    /// It Automatically writes the default global pcap header to the file, with the given datalink:
+    ///
+    /// ```ignore
+    /// PcapHeader {
+    ///
+    ///     magic_number : 0xa1b2c3d4,
+    ///     version_major : 2,
+    ///     version_minor : 4,
+    ///     ts_correction : 0,
+    ///     ts_accuracy : 0,
+    ///     snaplen : 65535,
+    ///     datalink : datalink
+    /// };
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Return an error if the writer can't be written to.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{DataLink, PcapWriter};
+    ///
+    /// let file_out = File::create("out.pcap").expect("Error creating file");
+    /// let mut pcap_writer = PcapWriter::with_datalink(DataLink::RAW, file_out);
+    /// ```
+    pub fn with_datalink(datalink: DataLink, writer: T) -> ResultChain<PcapWriter<T>> {
+
+        let header = PcapHeader::with_datalink(datalink);
+
+        PcapWriter::with_header(header, writer)
+    }
+
     /// Create a new `PcapWriter` from an existing writer with a user defined global pcap header.
     /// The endianness is chosen by the magic number of the header.
     ///
@@ -131,7 +215,7 @@ impl<T: Write> PcapWriter<T> {
     ///
     /// # Errors
     ///
-    /// Return an error if the writer can't be written to.
+    /// Return an error if the magic number of the header is unknown or if the writer can't be written to.
     ///
     ///
     /// # Examples
@@ -155,7 +239,49 @@ impl<T: Write> PcapWriter<T> {
     ///
     /// let mut pcap_writer = PcapWriter::with_header(header, file);
     /// ```
-    pub fn with_header(header: PcapHeader, mut writer: T) -> ResultChain<PcapWriter<T>> {
+    pub fn with_header(header: PcapHeader, writer: T) -> ResultChain<PcapWriter<T>> {
+        PcapWriter::with_header_and_capacity(header, writer, DEFAULT_BUFFER_CAPACITY)
+    }
+
+    /// Create a new `PcapWriter` with the default global header, buffering the writes in `capacity` bytes.
+    ///
+    /// The other constructors buffer the writes in `DEFAULT_BUFFER_CAPACITY` bytes. The buffer also holds
+    /// the global header, so that the small writes of the header and of each packet are grouped into big writes
+    /// to the underlying writer, a capacity of 0 writes them straight through.
+    /// `flush()` and `into_writer()` write the buffered bytes, `Drop` too but ignoring the errors.
+    ///
+    /// # Errors
+    ///
+    /// Return an error if the writer can't be written to.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapWriter;
+    ///
+    /// let file_out = File::create("out.pcap").expect("Error creating file");
+    /// let mut pcap_writer = PcapWriter::with_buffer_capacity(64 * 1024, file_out).unwrap();
+    ///
+    /// for _ in 0..1_000_000 {
+    ///     pcap_writer.write(0, 0, &[0u8; 10]).unwrap();
+    /// }
+    ///
+    /// let file_out = pcap_writer.into_writer().unwrap();
+    /// ```
+    pub fn with_buffer_capacity(capacity: usize, writer: T) -> ResultChain<PcapWriter<T>> {
+        PcapWriter::with_header_and_capacity(PcapHeader::default(), writer, capacity)
+    }
+
+    /// Writes the global header into a buffer of `capacity` bytes wrapping `writer`
+    pub(crate) fn with_header_and_capacity(header: PcapHeader, writer: T, capacity: usize) -> ResultChain<PcapWriter<T>> {
+
+        // The endianness and the resolution of the packets are given by the magic number
+        if magic_format(header.magic_number).is_none() {
+            bail!(ErrorKind::UnknownMagic(header.magic_number));
+        }
+
+        let mut writer = BufWriter::with_capacity(capacity, writer);
 
         match header.endianness() {
             Endianness::Big => writer.write_all(&header.to_array::<BigEndian>()?)?,
@@ -164,15 +290,25 @@ impl<T: Write> PcapWriter<T> {
 
         Ok(
             PcapWriter {
-                header: header,
-                writer: writer,
+                header,
+                writer: Some(writer),
+                written: PcapHeader::SIZE as u64,
+                max_incl_len: 0,
+                existing_max_incl_len: 0,
+                error_if_empty: false
             }
         )
     }
 
 
 
-    /// Consumes the `PcapWriter`, returning the wrapped writer.
+    /// Writes the buffered bytes, if any, and flushes the underlying writer.
+    ///
+    /// The writes are buffered unless the `PcapWriter` was created with a buffer capacity of 0,
+    /// see `with_buffer_capacity()`.
+    ///
+    /// # Errors
+    /// Return an error if the underlying writer can't be flushed.
     ///
     /// # Examples
     /// ```rust,no_run
@@ -180,17 +316,19 @@ impl<T: Write> PcapWriter<T> {
     /// use pcap_file::PcapWriter;
     ///
     /// let file = File::create("out.pcap").expect("Error creating file");
-    /// let pcap_writer = PcapWriter::new(file).unwrap();
+    /// let mut pcap_writer = PcapWriter::new(file).unwrap();
     ///
-    /// let file2 = pcap_writer.into_writer();
+    /// pcap_writer.write(0, 0, &[0u8; 10]).unwrap();
+    /// pcap_writer.flush().unwrap();
     /// ```
-    pub fn into_writer(self) -> T {
-        self.writer
+    pub fn flush(&mut self) -> ResultChain<()> {
+        self.buf_writer().flush().map_err(|err| err.into())
     }
 
-
-
-    /// Gets a reference to the underlying writer.
+    /// Consumes the `PcapWriter`, writing the buffered bytes, flushing and returning the wrapped writer.
+    ///
+    /// # Errors
+    /// Return an error if the underlying writer can't be flushed.
     ///
     /// # Examples
     /// ```rust,no_run
@@ -200,15 +338,70 @@ impl<T: Write> PcapWriter<T> {
     /// let file = File::create("out.pcap").expect("Error creating file");
     /// let pcap_writer = PcapWriter::new(file).unwrap();
     ///
-    /// let file_ref = pcap_writer.get_ref();
+    /// let file2 = pcap_writer.into_writer().unwrap();
+    /// ```
+    pub fn into_writer(mut self) -> ResultChain<T> {
+
+        self.flush()?;
+        let writer = self.writer.take().expect("The writer is only taken by into_writer");
+        writer.into_inner().map_err(|err| err.into_error().into())
+    }
+
+    /// Consumes the `PcapWriter` like `into_writer()`, but fails if no packet was written
+    /// when it was built with `PcapWriterBuilder::error_if_empty`.
+    ///
+    /// # Errors
+    /// Return an `EmptyCapture` error if the file only holds its global header and `error_if_empty` is set,
+    /// or an error if the underlying writer can't be flushed.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapWriterBuilder;
+    ///
+    /// let file = File::create("out.pcap").expect("Error creating file");
+    /// let pcap_writer = PcapWriterBuilder::new().error_if_empty(true).build(file).unwrap();
+    ///
+    /// // No packet written
+    /// assert!(pcap_writer.finalize().is_err());
+    /// ```
+    pub fn finalize(self) -> ResultChain<T> {
+
+        // The global header is still flushed when the writer is dropped
+        if self.error_if_empty && self.written <= PcapHeader::SIZE as u64 {
+            bail!(ErrorKind::EmptyCapture);
+        }
+
+        self.into_writer()
+    }
+
+
+
+    /// Gets a reference to the underlying writer.
+    ///
+    /// Useful to inspect the bytes written so far into an in-memory writer.
+    /// The bytes still buffered are not in it yet, call `flush()` first.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use pcap_file::PcapWriter;
+    ///
+    /// let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    /// pcap_writer.write(0, 0, &[0u8; 10]).unwrap();
+    /// assert!(pcap_writer.get_ref().is_empty());
+    ///
+    /// // Global header, packet header and payload
+    /// pcap_writer.flush().unwrap();
+    /// assert_eq!(pcap_writer.get_ref().len(), 24 + 16 + 10);
     /// ```
     pub fn get_ref(&self) -> &T {
-        &self.writer
+        self.writer.as_ref().expect("The writer is only taken by into_writer").get_ref()
     }
 
-    /// Gets a mutable reference to the underlying writer.
+    /// Gets a mutable reference to the underlying writer, to sync a file to the disk for instance.
     ///
     /// It is inadvisable to directly write to the underlying writer.
+    /// Call `flush()` first, the writes are buffered.
     ///
     /// # Examples
     /// ```rust,no_run
@@ -221,11 +414,22 @@ impl<T: Write> PcapWriter<T> {
     /// let file_mut = pcap_writer.get_mut();
     /// ```
     pub fn get_mut(&mut self) -> &mut T {
-        &mut self.writer
+        self.writer.as_mut().expect("The writer is only taken by into_writer").get_mut()
+    }
+
+    /// Gets the buffer wrapping the underlying writer, through which everything is written
+    fn buf_writer(&mut self) -> &mut BufWriter<T> {
+        self.writer.as_mut().expect("The writer is only taken by into_writer")
     }
 
     /// Writes some raw data, converting it to the pcap file format.
     ///
+    /// `ts_usec` is the sub-second part of the timestamp in the resolution of the global header,
+    /// microseconds or nanoseconds.
+    ///
+    /// # Errors
+    /// Return an error if `ts_usec` is out of range for this resolution or if the writer can't be written to.
+    ///
     /// # Examples
     /// ```rust,no_run
     /// use std::fs::File;
@@ -241,7 +445,10 @@ impl<T: Write> PcapWriter<T> {
 
         let packet = Packet {
 
-            header: PacketHeader::new(ts_sec, ts_usec, data.len() as u32),
+            header: PacketHeader {
+                ts_resolution: self.header.ts_resolution(),
+                ..PacketHeader::new(ts_sec, ts_usec, data.len() as u32)
+            },
             data: Cow::Borrowed(data)
         };
 
@@ -250,6 +457,20 @@ impl<T: Write> PcapWriter<T> {
 
     /// Writes a `Packet`.
     ///
+    /// The number of octets saved in the file is the length of the payload.
+    /// If it exceeds the snaplen of the global header, the payload is truncated to the snaplen,
+    /// the original length of the packet is kept. An original length lower than the length of the payload
+    /// is raised to it, so that the record is valid.
+    ///
+    /// In the modified pcap format, the missing `ifindex`, `protocol` and `pkt_type` are written as 0.
+    ///
+    /// The sub-second part of the timestamp is converted from the resolution of the packet to the one
+    /// of the global header, a nanosecond timestamp written in a microsecond file is truncated.
+    ///
+    /// # Errors
+    /// Return an error if the sub-second part of the timestamp is out of range for the resolution of the packet,
+    /// or if the writer can't be written to.
+    ///
     /// # Examples
     /// ```rust,no_run
     /// use std::fs::File;
@@ -265,11 +486,610 @@ impl<T: Write> PcapWriter<T> {
     /// ```
     pub fn write_packet(&mut self, packet: &Packet) -> ResultChain<()> {
 
-        match self.header.endianness() {
+        let incl_len = packet.data.len().min(self.header.effective_snaplen() as usize);
+
+        let ts_usec = match (packet.header.ts_resolution, self.header.ts_resolution()) {
+            (TsResolution::MicroSecond, _) if packet.header.ts_usec >= 1_000_000 => {
+                bail!(ErrorKind::WrongField(format!("PacketHeader.ts_usec = {} >= 1_000_000 microseconds", packet.header.ts_usec)))
+            },
+            (TsResolution::NanoSecond, _) if packet.header.ts_usec >= 1_000_000_000 => {
+                bail!(ErrorKind::WrongField(format!("PacketHeader.ts_usec = {} >= 1_000_000_000 nanoseconds", packet.header.ts_usec)))
+            },
+            (TsResolution::MicroSecond, TsResolution::NanoSecond) => packet.header.ts_usec * 1000,
+            (TsResolution::NanoSecond, TsResolution::MicroSecond) => packet.header.ts_usec / 1000,
+            _ => packet.header.ts_usec
+        };
+
+        // The original length can't be lower than the payload given, the reader would refuse the record
+        let data_len = packet.data.len().min(u32::MAX as usize) as u32;
+
+        let header = PacketHeader {
+            ts_usec,
+            incl_len: incl_len as u32,
+            orig_len: packet.header.orig_len.max(data_len),
+            ts_resolution: self.header.ts_resolution(),
+            ..packet.header
+        };
+
+        let pcap_header = self.header;
+        let writer = self.buf_writer();
+
+        writer.write_all(&header.to_bytes(pcap_header.endianness()))?;
+
+        if pcap_header.is_modified() {
+            match pcap_header.endianness() {
+
+                Endianness::Big => writer.write_all(&header.modified_fields_to_array::<BigEndian>()?)?,
+                Endianness::Little => writer.write_all(&header.modified_fields_to_array::<LittleEndian>()?)?
+            }
+        }
+
+        writer.write_all(&packet.data[..incl_len])?;
+
+        self.written += (pcap_header.packet_header_len() + incl_len) as u64;
+        self.max_incl_len = self.max_incl_len.max(incl_len as u32);
+
+        Ok(())
+    }
+
+    /// Writes an already encoded packet record, its packet header followed by its payload, as is.
+    ///
+    /// The record must be in the format of the global header of the writer, such as the records read by
+    /// `PcapReader::next_raw_into` from a file of the same format: only its length is checked.
+    /// Unlike `write_packet`, the payload is not truncated to the snaplen.
+    ///
+    /// # Errors
+    /// Return an error if the length of the record doesn't match the incl_len of its header,
+    /// or if the writer can't be written to.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapWriter;
+    ///
+    /// let file = File::create("out.pcap").expect("Error creating file");
+    /// let mut pcap_writer = PcapWriter::new(file).unwrap();
+    ///
+    /// // Big endian header: 1 second, 2 bytes captured out of 2, followed by the payload
+    /// let record = [0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0xAB, 0xCD];
+    /// pcap_writer.write_raw_record(&record).unwrap();
+    /// ```
+    pub fn write_raw_record(&mut self, bytes: &[u8]) -> ResultChain<()> {
+
+        let header_len = self.header.packet_header_len();
+        if bytes.len() < header_len {
+            bail!(ErrorKind::TruncatedPacket(header_len, bytes.len()));
+        }
 
-            Endianness::Big => self.writer.write_all(&packet.header.to_array::<BigEndian>()?)?,
-            Endianness::Little => self.writer.write_all(&packet.header.to_array::<LittleEndian>()?)?
+        let incl_len = match self.header.endianness() {
+            Endianness::Big => BigEndian::read_u32(&bytes[8..12]),
+            Endianness::Little => LittleEndian::read_u32(&bytes[8..12])
+        };
+
+        if header_len as u64 + u64::from(incl_len) != bytes.len() as u64 {
+            bail!(ErrorKind::WrongField(format!("PacketHeader.incl_len = {} but the record holds {} bytes of payload", incl_len, bytes.len() - header_len)));
         }
-        self.writer.write_all(&packet.data).map_err(|err| err.into())
+
+        self.buf_writer().write_all(bytes)?;
+
+        self.written += bytes.len() as u64;
+        self.max_incl_len = self.max_incl_len.max(incl_len);
+
+        Ok(())
+    }
+
+    /// Writes a `Packet` with the timestamp `ts`, a `Duration` since the epoch, instead of its own.
+    ///
+    /// The nanoseconds of `ts` are kept if the file has a nanosecond resolution.
+    ///
+    /// # Errors
+    /// Return an error if `ts` doesn't fit in a packet header or if the packet can't be written.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use std::time::{SystemTime, UNIX_EPOCH};
+    /// use pcap_file::{PcapReader, PcapWriter};
+    ///
+    /// let pcap_reader = PcapReader::new(File::open("test.pcap").unwrap()).unwrap();
+    /// let mut pcap_writer = PcapWriter::new(File::create("out.pcap").unwrap()).unwrap();
+    ///
+    /// // All the packets happen now
+    /// let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    /// for pcap in pcap_reader {
+    ///     pcap_writer.write_packet_with_timestamp(&pcap.unwrap(), now).unwrap();
+    /// }
+    /// ```
+    pub fn write_packet_with_timestamp(&mut self, packet: &Packet, ts: Duration) -> ResultChain<()> {
+
+        let mut header = PacketHeader {
+            ts_resolution: TsResolution::NanoSecond,
+            ..packet.header
+        };
+        header.set_timestamp(ts)?;
+
+        self.write_packet(&Packet { header, data: Cow::Borrowed(&packet.data) })
+    }
+
+    /// Writes a `Packet` like `write_packet` and returns the location of its record.
+    ///
+    /// The offset is counted from the start of the global header, like `PcapReader::offset`,
+    /// so the locations can be used to build a `PacketIndex` without scanning the file again.
+    ///
+    /// # Errors
+    /// Return an error if the packet can't be written.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{Packet, PcapWriter};
+    ///
+    /// let file = File::create("out.pcap").expect("Error creating file");
+    /// let mut pcap_writer = PcapWriter::new(file).unwrap();
+    ///
+    /// let data = [0u8; 10];
+    /// let location = pcap_writer.write_packet_indexed(&Packet::new(0, 0, 10, &data)).unwrap();
+    ///
+    /// assert_eq!(location.offset, 24);
+    /// assert_eq!(location.len, 16 + 10);
+    /// ```
+    pub fn write_packet_indexed(&mut self, packet: &Packet) -> ResultChain<PacketLocation> {
+
+        let offset = self.written;
+        self.write_packet(packet)?;
+
+        Ok(
+            PacketLocation {
+                offset,
+                len: self.written - offset
+            }
+        )
+    }
+
+    /// Writes every `Packet` of an iterator.
+    ///
+    /// # Errors
+    /// Return an error as soon as a packet can't be written, the following packets are not written.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{Packet, PcapWriter};
+    ///
+    /// let data = [0u8; 10];
+    /// let packets = (0..5).map(|ts_sec| Packet::new(ts_sec, 0, 10, &data));
+    ///
+    /// let file = File::create("out.pcap").expect("Error creating file");
+    /// let mut pcap_writer = PcapWriter::new(file).unwrap();
+    ///
+    /// pcap_writer.write_all(packets).unwrap();
+    /// ```
+    pub fn write_all<'a, I: IntoIterator<Item = Packet<'a>>>(&mut self, packets: I) -> ResultChain<()> {
+
+        for packet in packets {
+            self.write_packet(&packet)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a packet for every timestamp, a `Duration` since the epoch, and payload of an iterator.
+    ///
+    /// The timestamps are written with the resolution of the file, and the orig_len of each packet is the length of its payload.
+    ///
+    /// # Errors
+    /// Return an error as soon as a packet can't be written, the following packets are not written.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use std::time::Duration;
+    /// use pcap_file::PcapWriter;
+    ///
+    /// let packets = (0..5).map(|i| (Duration::from_millis(1_331_901_000_000 + i * 10), vec![0u8; 60]));
+    ///
+    /// let file = File::create("out.pcap").expect("Error creating file");
+    /// let mut pcap_writer = PcapWriter::new(file).unwrap();
+    ///
+    /// pcap_writer.write_tuples(packets).unwrap();
+    /// ```
+    pub fn write_tuples<I: IntoIterator<Item = (Duration, Vec<u8>)>>(&mut self, packets: I) -> ResultChain<()> {
+
+        for (ts, data) in packets {
+
+            // Built in nanoseconds, write_packet converts them to the resolution of the file
+            let packet = PacketBuilder::new()
+                .timestamp(ts)
+                .ts_resolution(TsResolution::NanoSecond)
+                .data(data)
+                .build()?;
+
+            self.write_packet(&packet)?;
+        }
+
+        Ok(())
+    }
+
+    /// Copies every remaining packet of a `PcapReader`.
+    ///
+    /// The records are copied as they are encoded in the file, with `PcapReader::next_raw_into` and `write_raw_record`,
+    /// except the ones longer than the snaplen of the writer, which are truncated to it.
+    ///
+    /// # Errors
+    /// Return an `IncompatibleHeaders` error, before writing anything, if the reader and the writer don't use
+    /// the same datalink, endianness, timestamp resolution and packet header format.
+    /// Otherwise return an error as soon as a packet can't be read or written.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::{PcapReader, PcapWriter};
+    ///
+    /// let file_in = File::open("test.pcap").expect("Error opening file");
+    /// let pcap_reader = PcapReader::new(file_in).unwrap();
+    ///
+    /// let file_out = File::create("out.pcap").expect("Error creating file");
+    /// let mut pcap_writer = PcapWriter::with_header(pcap_reader.header, file_out).unwrap();
+    ///
+    /// pcap_writer.copy_from(pcap_reader).unwrap();
+    /// ```
+    pub fn copy_from<R: Read>(&mut self, mut reader: PcapReader<R>) -> ResultChain<()> {
+
+        check_compatible(&reader.header, &self.header)?;
+
+        let mut record = Vec::new();
+        while let Some(header) = reader.next_raw_into(&mut record) {
+
+            let header = header?;
+            if header.incl_len <= self.header.effective_snaplen() {
+                self.write_raw_record(&record)?;
+            }
+            else {
+                let data = &record[self.header.packet_header_len()..];
+                self.write_packet(&Packet { header, data: Cow::Borrowed(data) })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Write + Seek> PcapWriter<T> {
+
+    /// Consumes the `PcapWriter`, lowering the snaplen of the global header already written
+    /// to the greatest length of the written packets, then returns the wrapped writer.
+    ///
+    /// The underlying writer is moved back to the snaplen field, then to the end of the written data.
+    /// The snaplen is left as is if no packet has been written.
+    ///
+    /// # Errors
+    /// Return an error if the underlying writer can't seek, be written to or be flushed.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use pcap_file::PcapWriter;
+    ///
+    /// let file = File::create("out.pcap").expect("Error creating file");
+    /// let mut pcap_writer = PcapWriter::new(file).unwrap();
+    ///
+    /// pcap_writer.write(0, 0, &[0u8; 10]).unwrap();
+    ///
+    /// // The snaplen of out.pcap is now 10
+    /// let file = pcap_writer.finalize_with_snaplen_fixup().unwrap();
+    /// ```
+    pub fn finalize_with_snaplen_fixup(mut self) -> ResultChain<T> {
+
+        // The packets already in an appended file must still fit in the new snaplen
+        let max_incl_len = self.max_incl_len.max(self.existing_max_incl_len);
+
+        if self.max_incl_len > 0 && max_incl_len != self.header.snaplen {
+
+            self.header.snaplen = max_incl_len;
+
+            // The snaplen is the 5th field of the global header, at offset 16
+            let snaplen = self.header.snaplen;
+            let endianness = self.header.endianness();
+            let written = self.written as i64;
+            let writer = self.buf_writer();
+
+            // Seeking writes the buffered bytes first
+            writer.seek(SeekFrom::Current(16 - written))?;
+            match endianness {
+                Endianness::Big => writer.write_u32::<BigEndian>(snaplen)?,
+                Endianness::Little => writer.write_u32::<LittleEndian>(snaplen)?
+            }
+            writer.seek(SeekFrom::Current(written - 20))?;
+        }
+
+        self.into_writer()
+    }
+}
+
+impl<T: Read + Write + Seek> PcapWriter<T> {
+
+    /// Create a new `PcapWriter` appending packets to an existing pcap file.
+    ///
+    /// The global header at the start of the file is read and kept as is, the packets are written
+    /// at the end of the file with its endianness and timestamp resolution.
+    ///
+    /// The writer has no way to know the datalink of the packets given to `write_packet`,
+    /// but `copy_from` refuses the readers whose datalink isn't the one of the file.
+    /// Use `append_with_datalink` to check it when the file is opened.
+    ///
+    /// Every packet record of the file is checked, without reading the packet data, so that the
+    /// new packets aren't written after an incomplete record where no reader could find them.
+    ///
+    /// # Errors
+    /// Return an error if the file doesn't start with a valid global pcap header, if its last packet
+    /// record is incomplete or followed by stray bytes, or if it can't seek.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::OpenOptions;
+    /// use pcap_file::PcapWriter;
+    ///
+    /// let file = OpenOptions::new().read(true).write(true).open("out.pcap").expect("Error opening file");
+    /// let mut pcap_writer = PcapWriter::append(file).unwrap();
+    ///
+    /// pcap_writer.write(0, 0, &[0u8; 10]).unwrap();
+    /// ```
+    pub fn append(writer: T) -> ResultChain<PcapWriter<T>> {
+        PcapWriter::append_checked(writer, None)
+    }
+
+    /// Create a new `PcapWriter` appending packets of the given `datalink` to an existing pcap file.
+    ///
+    /// Same as `append`, but refuses the files whose datalink isn't `datalink`.
+    ///
+    /// # Errors
+    /// Return an `IncompatibleHeaders` error if the datalink of the file isn't `datalink`,
+    /// and the same errors as `append`.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use std::fs::OpenOptions;
+    /// use pcap_file::{DataLink, PcapWriter};
+    ///
+    /// let file = OpenOptions::new().read(true).write(true).open("out.pcap").expect("Error opening file");
+    /// let mut pcap_writer = PcapWriter::append_with_datalink(file, DataLink::ETHERNET).unwrap();
+    ///
+    /// pcap_writer.write(0, 0, &[0u8; 10]).unwrap();
+    /// ```
+    pub fn append_with_datalink(writer: T, datalink: DataLink) -> ResultChain<PcapWriter<T>> {
+        PcapWriter::append_checked(writer, Some(datalink))
+    }
+
+    fn append_checked(mut writer: T, datalink: Option<DataLink>) -> ResultChain<PcapWriter<T>> {
+
+        writer.seek(SeekFrom::Start(0))?;
+
+        let (header, existing_max_incl_len) = {
+
+            let mut reader = PcapReader::new(&mut writer)?;
+            // The packet data is skipped, not read, its length doesn't matter
+            reader.set_max_packet_len(u32::MAX);
+            let header = reader.header;
+
+            let mut max_incl_len = 0;
+            for packet_header in reader.headers_seek()? {
+                max_incl_len = max_incl_len.max(packet_header?.incl_len);
+            }
+
+            (header, max_incl_len)
+        };
+
+        if let Some(datalink) = datalink {
+            if header.datalink != datalink {
+                bail!(ErrorKind::IncompatibleHeaders(format!("datalink {:?} can't be appended to a file with datalink {:?}", datalink, header.datalink)));
+            }
+        }
+
+        let end = writer.seek(SeekFrom::End(0))?;
+
+        Ok(
+            PcapWriter {
+                header,
+                writer: Some(BufWriter::with_capacity(DEFAULT_BUFFER_CAPACITY, writer)),
+                written: end,
+                // The snaplen is only rewritten by the fixup if packets are written
+                max_incl_len: header.snaplen,
+                existing_max_incl_len,
+                error_if_empty: false
+            }
+        )
+    }
+}
+
+impl<T: Write> Drop for PcapWriter<T> {
+
+    /// Flushes the underlying writer, the errors are ignored because they can't be returned.
+    ///
+    /// Call `flush()` or `into_writer()` before dropping the `PcapWriter` to handle them.
+    fn drop(&mut self) {
+
+        if let Some(ref mut writer) = self.writer {
+            let _ = writer.flush();
+        }
+    }
+}
+
+/// Checks that the packets of a file with the `input` header can be written as is in a file with the `output` header
+pub(crate) fn check_compatible(input: &PcapHeader, output: &PcapHeader) -> ResultChain<()> {
+
+    if input.datalink != output.datalink {
+        bail!(ErrorKind::IncompatibleHeaders(format!("datalink {:?} can't be written as datalink {:?}", input.datalink, output.datalink)));
+    }
+
+    if input.endianness() != output.endianness() {
+        bail!(ErrorKind::IncompatibleHeaders(format!("endianness {:?} can't be written as endianness {:?}", input.endianness(), output.endianness())));
+    }
+
+    if input.ts_resolution() != output.ts_resolution() {
+        bail!(ErrorKind::IncompatibleHeaders(format!("timestamp resolution {:?} can't be written as {:?}", input.ts_resolution(), output.ts_resolution())));
+    }
+
+    if input.is_modified() != output.is_modified() {
+        bail!(ErrorKind::IncompatibleHeaders("the modified pcap format can't be mixed with the standard one".to_string()));
+    }
+
+    Ok(())
+}
+
+
+/// Builds a `PcapWriter` with a custom global pcap header.
+///
+/// The fields which are not set keep the values of the default header:
+///
+/// ```ignore
+/// PcapHeader {
+///
+///     magic_number : 0xa1b2c3d4,
+///     version_major : 2,
+///     version_minor : 4,
+///     ts_correction : 0,
+///     ts_accuracy : 0,
+///     snaplen : 65535,
+///     datalink : DataLink::ETHERNET
+/// };
+/// ```
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use pcap_file::{DataLink, PcapWriterBuilder, TsResolution};
+///
+/// let file_out = File::create("out.pcap").expect("Error creating file");
+///
+/// let mut pcap_writer = PcapWriterBuilder::new()
+///     .snaplen(1500)
+///     .datalink(DataLink::RAW)
+///     .ts_resolution(TsResolution::NanoSecond)
+///     .build(file_out)
+///     .unwrap();
+/// ```
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PcapWriterBuilder {
+    snaplen: Option<u32>,
+    datalink: Option<DataLink>,
+    ts_resolution: TsResolution,
+    endianness: Option<Endianness>,
+    modified: bool,
+    thiszone: i32,
+    sigfigs: u32,
+    buffer_capacity: Option<usize>,
+    error_if_empty: bool
+}
+
+impl PcapWriterBuilder {
+
+    /// Create a new `PcapWriterBuilder` with the default global pcap header.
+    pub fn new() -> PcapWriterBuilder {
+        PcapWriterBuilder::default()
+    }
+
+    /// Sets the max length of captured packets.
+    ///
+    /// A snaplen of 0 is written as is, the packets being truncated to 65535 bytes like libpcap does.
+    pub fn snaplen(mut self, snaplen: u32) -> PcapWriterBuilder {
+        self.snaplen = Some(snaplen);
+        self
+    }
+
+    /// Sets the DataLink type of the packets.
+    pub fn datalink(mut self, datalink: DataLink) -> PcapWriterBuilder {
+        self.datalink = Some(datalink);
+        self
+    }
+
+    /// Sets the timestamp resolution of the packets.
+    pub fn ts_resolution(mut self, ts_resolution: TsResolution) -> PcapWriterBuilder {
+        self.ts_resolution = ts_resolution;
+        self
+    }
+
+    /// Sets the endianness of the file, BigEndian by default.
+    pub fn endianness(mut self, endianness: Endianness) -> PcapWriterBuilder {
+        self.endianness = Some(endianness);
+        self
+    }
+
+    /// Uses the modified pcap format (magic number 0xa1b2cd34), whose packet headers hold
+    /// the interface index, the protocol and the packet type.
+    pub fn modified(mut self, modified: bool) -> PcapWriterBuilder {
+        self.modified = modified;
+        self
+    }
+
+    /// Sets the thiszone field, the GMT to local timezone correction in seconds, 0 by default.
+    pub fn thiszone(mut self, thiszone: i32) -> PcapWriterBuilder {
+        self.thiszone = thiszone;
+        self
+    }
+
+    /// Sets the sigfigs field, the accuracy of the timestamps, 0 by default.
+    pub fn sigfigs(mut self, sigfigs: u32) -> PcapWriterBuilder {
+        self.sigfigs = sigfigs;
+        self
+    }
+
+    /// Buffers the writes in `capacity` bytes, `DEFAULT_BUFFER_CAPACITY` by default, see `PcapWriter::with_buffer_capacity`.
+    pub fn buffer_capacity(mut self, capacity: usize) -> PcapWriterBuilder {
+        self.buffer_capacity = Some(capacity);
+        self
+    }
+
+    /// Makes `PcapWriter::finalize` return an error if no packet was written, false by default.
+    pub fn error_if_empty(mut self, error_if_empty: bool) -> PcapWriterBuilder {
+        self.error_if_empty = error_if_empty;
+        self
+    }
+
+    /// Returns the global pcap header which will be written.
+    ///
+    /// # Errors
+    ///
+    /// Return an error if the modified format is used with a nanosecond resolution, which it doesn't support.
+    pub fn header(&self) -> ResultChain<PcapHeader> {
+
+        let default = PcapHeader::default();
+
+        let snaplen = self.snaplen.unwrap_or(default.snaplen);
+
+        let magic_number = match (self.endianness.unwrap_or(Endianness::Big), self.ts_resolution, self.modified) {
+            (Endianness::Big, TsResolution::MicroSecond, false) => 0xa1b2c3d4,
+            (Endianness::Big, TsResolution::NanoSecond, false) => 0xa1b23c4d,
+            (Endianness::Little, TsResolution::MicroSecond, false) => 0xd4c3b2a1,
+            (Endianness::Little, TsResolution::NanoSecond, false) => 0x4d3cb2a1,
+            (Endianness::Big, TsResolution::MicroSecond, true) => 0xa1b2cd34,
+            (Endianness::Little, TsResolution::MicroSecond, true) => 0x34cdb2a1,
+            (_, TsResolution::NanoSecond, true) => bail!(ErrorKind::WrongField("The modified pcap format only supports microsecond timestamps".to_string()))
+        };
+
+        Ok(
+            PcapHeader {
+                magic_number,
+                ts_correction: self.thiszone,
+                ts_accuracy: self.sigfigs,
+                snaplen,
+                datalink: self.datalink.unwrap_or(default.datalink),
+                ..default
+            }
+        )
+    }
+
+    /// Create the `PcapWriter`, writing the global pcap header immediately.
+    ///
+    /// # Errors
+    ///
+    /// Return an error if the header is invalid, see `header()`, or if the writer can't be written to.
+    pub fn build<T: Write>(self, writer: T) -> ResultChain<PcapWriter<T>> {
+
+        let capacity = self.buffer_capacity.unwrap_or(DEFAULT_BUFFER_CAPACITY);
+        let mut pcap_writer = PcapWriter::with_header_and_capacity(self.header()?, writer, capacity)?;
+        pcap_writer.error_if_empty = self.error_if_empty;
+
+        Ok(pcap_writer)
     }
 }
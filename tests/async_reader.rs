@@ -0,0 +1,133 @@
+#![cfg(feature = "tokio")]
+
+extern crate futures_core;
+extern crate pcap_file;
+extern crate tokio;
+
+use std::future::Future;
+use std::io::Result as IoResult;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use pcap_file::{AsyncPcapReader, PacketHeader, PcapHeader, PcapReader, PcapWriter};
+
+static DATA: &[u8; 1455] = include_bytes!("test_in.pcap");
+
+/// Async reader yielding one byte at a time, and pending every other poll
+struct Trickle<'a> {
+    data: &'a [u8],
+    pending: bool
+}
+
+impl<'a> AsyncRead for Trickle<'a> {
+
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<IoResult<()>> {
+
+        self.pending = !self.pending;
+        if self.pending {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        if let Some((first, rest)) = self.data.split_first() {
+            buf.put_slice(&[*first]);
+            self.data = rest;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn read_trickle() {
+
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let reader = Trickle { data: &DATA[..], pending: false };
+    let mut future = Box::pin(AsyncPcapReader::new(reader));
+    let mut pcap_reader = loop {
+        if let Poll::Ready(pcap_reader) = future.as_mut().poll(&mut cx) {
+            break pcap_reader.unwrap();
+        }
+    };
+
+    let mut expected = PcapReader::new(&DATA[..]).unwrap();
+    loop {
+
+        let pcap = match Pin::new(&mut pcap_reader).poll_next(&mut cx) {
+            Poll::Pending => continue,
+            Poll::Ready(pcap) => pcap
+        };
+
+        match (pcap, expected.next()) {
+            (Some(pcap), Some(expected)) => assert_eq!(pcap.unwrap().data, expected.unwrap().data),
+            (None, None) => break,
+            _ => panic!("The async reader and the sync reader must yield the same packets")
+        }
+    }
+}
+
+#[test]
+fn read_truncated() {
+
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let reader = Trickle { data: &DATA[..DATA.len() - 3], pending: false };
+    let mut future = Box::pin(AsyncPcapReader::new(reader));
+    let mut pcap_reader = loop {
+        if let Poll::Ready(pcap_reader) = future.as_mut().poll(&mut cx) {
+            break pcap_reader.unwrap();
+        }
+    };
+
+    let mut results = Vec::new();
+    loop {
+        match Pin::new(&mut pcap_reader).poll_next(&mut cx) {
+            Poll::Pending => continue,
+            Poll::Ready(Some(pcap)) => results.push(pcap.is_ok()),
+            Poll::Ready(None) => break
+        }
+    }
+
+    assert_eq!(results, vec![true, true, true, true, true, true, true, true, true, false]);
+}
+
+#[test]
+fn read_past_snaplen() {
+
+    let mut cx = Context::from_waker(Waker::noop());
+
+    // Records longer than the snaplen, as written by some broken tools
+    let header = PcapHeader { snaplen: 1000, ..PcapHeader::default() };
+    let mut out = PcapWriter::with_header(header, Vec::new()).unwrap().into_writer().unwrap();
+    for &(len, byte) in &[(70_000, 1), (100, 2)] {
+        out.extend_from_slice(&PacketHeader::new(0, 0, len).to_bytes(header.endianness()));
+        out.extend(vec![byte; len as usize]);
+    }
+
+    let reader = Trickle { data: &out[..], pending: false };
+    let mut future = Box::pin(AsyncPcapReader::new(reader));
+    let mut pcap_reader = loop {
+        if let Poll::Ready(pcap_reader) = future.as_mut().poll(&mut cx) {
+            break pcap_reader.unwrap();
+        }
+    };
+
+    let mut packets = Vec::new();
+    loop {
+        match Pin::new(&mut pcap_reader).poll_next(&mut cx) {
+            Poll::Pending => continue,
+            Poll::Ready(Some(pcap)) => packets.push(pcap.unwrap()),
+            Poll::Ready(None) => break
+        }
+    }
+
+    // Truncated to the snaplen like PcapReader does
+    let expected: Vec<_> = PcapReader::new(&out[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+    assert_eq!(packets, expected);
+    assert_eq!((packets[0].header.incl_len, packets[0].header.orig_len), (1000, 70_000));
+    assert_eq!(packets[0].data.len(), 1000);
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "checksum")]
+
+extern crate pcap_file;
+
+use pcap_file::errors::ErrorKind;
+use pcap_file::{ChecksumReader, ChecksumWriter, PcapReader};
+
+static DATA: &[u8; 1455] = include_bytes!("test_in.pcap");
+
+#[test]
+fn round_trip() {
+
+    let pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    let mut checksum_writer = ChecksumWriter::with_header(pcap_reader.header, Vec::new()).unwrap();
+    for pcap in pcap_reader {
+        checksum_writer.write_packet(&pcap.unwrap()).unwrap();
+    }
+
+    let current = checksum_writer.current_digest();
+    let (out, digest) = checksum_writer.into_writer().unwrap();
+    assert_eq!(current, digest);
+    assert_eq!(&out[..], &DATA[..]);
+
+    // The digest covers the packet records only
+    let empty = ChecksumWriter::new(Vec::new()).unwrap().digest().unwrap();
+    assert_eq!(empty, [0; 4]);
+
+    let mut checksum_reader = ChecksumReader::new(&DATA[..]).unwrap();
+    assert_eq!(checksum_reader.by_ref().count(), 10);
+    assert_eq!(checksum_reader.current_digest(), digest);
+
+    // Verifying reads the remaining packets
+    ChecksumReader::new(&DATA[..]).unwrap().verify(&digest).unwrap();
+}
+
+#[test]
+fn corrupted() {
+
+    let digest = ChecksumReader::new(&DATA[..]).unwrap().verify(&[0; 4]).unwrap_err();
+    let digest = match *digest.kind() {
+        ErrorKind::ChecksumMismatch(_, ref got) => got.clone(),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    };
+
+    // A flipped payload bit is detected
+    let mut data = DATA.to_vec();
+    let last = data.len() - 1;
+    data[last] ^= 1;
+
+    match *ChecksumReader::new(&data[..]).unwrap().verify(&digest).unwrap_err().kind() {
+        ErrorKind::ChecksumMismatch(ref expected, ref got) => {
+            assert_eq!(expected, &digest);
+            assert_ne!(got, &digest);
+        },
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+}
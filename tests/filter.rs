@@ -0,0 +1,78 @@
+#![cfg(feature = "bpf")]
+
+extern crate pcap_file;
+
+use pcap_file::filter::Filter;
+use pcap_file::{DataLink, Packet, PcapReader, PcapWriter};
+
+/// Builds an Ethernet frame holding an IPv4 packet with the given transport protocol and ports
+fn ipv4_frame(proto: u8, src: [u8; 4], dst: [u8; 4], src_port: u16, dst_port: u16) -> Vec<u8> {
+
+    let mut frame = vec![0u8; 12];
+    frame.extend_from_slice(&[0x08, 0x00]);
+
+    frame.extend_from_slice(&[0x45, 0, 0, 40, 0, 0, 0, 0, 64, proto, 0, 0]);
+    frame.extend_from_slice(&src);
+    frame.extend_from_slice(&dst);
+
+    frame.extend_from_slice(&src_port.to_be_bytes());
+    frame.extend_from_slice(&dst_port.to_be_bytes());
+    frame.extend_from_slice(&[0u8; 16]);
+
+    frame
+}
+
+fn matches(expr: &str, frame: &[u8]) -> bool {
+
+    let filter = Filter::new(expr, DataLink::ETHERNET).unwrap();
+    filter.matches(&Packet::new(0, 0, frame.len() as u32, frame))
+}
+
+#[test]
+fn primitives() {
+
+    let https = ipv4_frame(6, [10, 0, 0, 1], [192, 168, 1, 2], 51000, 443);
+    let dns = ipv4_frame(17, [10, 0, 0, 1], [10, 0, 0, 53], 51000, 53);
+
+    assert!(matches("", &dns));
+    assert!(matches("ip", &https));
+    assert!(!matches("ip6 or arp", &https));
+
+    assert!(matches("tcp port 443", &https));
+    assert!(!matches("tcp port 443", &dns));
+    assert!(matches("dst port 443", &https));
+    assert!(!matches("src port 443", &https));
+    assert!(matches("udp and not port 443", &dns));
+
+    assert!(matches("host 192.168.1.2", &https));
+    assert!(matches("src host 10.0.0.1 && dst net 10.0.0.0/24", &dns));
+    assert!(!matches("dst net 10.0.0.0/24", &https));
+
+    assert!(matches("(tcp or udp) and !(port 80)", &https));
+    assert!(matches("greater 54 and less 54", &https));
+}
+
+#[test]
+fn invalid() {
+
+    for expr in &["tcp and", "port", "port 70000", "host 300.1.1.1", "net 10.0.0.1/8", "(tcp", "tcp)", "foo"] {
+        assert!(Filter::new(expr, DataLink::ETHERNET).is_err(), "{}", expr);
+    }
+
+    assert!(Filter::new("tcp", DataLink::USB_LINUX).is_err());
+}
+
+#[test]
+fn filter_expr() {
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for port in 440..450 {
+        pcap_writer.write(0, 0, &ipv4_frame(6, [10, 0, 0, 1], [10, 0, 0, 2], 51000, port)).unwrap();
+    }
+    let out = pcap_writer.into_writer().unwrap();
+
+    let matching = PcapReader::new(&out[..]).unwrap().filter_expr("tcp port 443 or port 447").unwrap().count();
+    assert_eq!(matching, 2);
+
+    assert!(PcapReader::new(&out[..]).unwrap().filter_expr("tcp port").is_err());
+}
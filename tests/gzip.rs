@@ -0,0 +1,39 @@
+extern crate pcap_file;
+
+use pcap_file::PcapReader;
+
+static DATA: &[u8; 1455] = include_bytes!("test_in.pcap");
+
+#[cfg(feature = "gzip")]
+static DATA_GZ: &[u8] = include_bytes!("test_in.pcap.gz");
+
+fn assert_same_packets<I: Iterator<Item = pcap_file::errors::ResultChain<pcap_file::Packet<'static>>>>(packets: I) {
+
+    let expected = PcapReader::new(&DATA[..]).unwrap();
+
+    let mut nb_packets = 0;
+    for (pcap, expected) in packets.zip(expected) {
+        assert_eq!(pcap.unwrap().data, expected.unwrap().data);
+        nb_packets += 1;
+    }
+
+    assert_eq!(nb_packets, 10);
+}
+
+#[test]
+fn from_path() {
+
+    let pcap_reader = PcapReader::from_path(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/test_in.pcap")).unwrap();
+    assert_same_packets(pcap_reader);
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn read_gz() {
+
+    let pcap_reader = PcapReader::new_gz(DATA_GZ).unwrap();
+    assert_same_packets(pcap_reader);
+
+    let pcap_reader = PcapReader::from_path(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/test_in.pcap.gz")).unwrap();
+    assert_same_packets(pcap_reader);
+}
@@ -0,0 +1,81 @@
+extern crate pcap_file;
+
+use pcap_file::{detect_format, DataLink, Endianness, Format, PcapReader};
+use pcap_file::errors::ErrorKind;
+use pcap_file::pcapng::{Block, PcapNgReader, DEFAULT_MAX_BLOCK_LEN};
+
+static DATA: &[u8; 1740] = include_bytes!("test_in.pcapng");
+static DATA_PCAP: &[u8; 1455] = include_bytes!("test_in.pcap");
+
+#[test]
+fn read() {
+
+    let mut pcapng_reader = PcapNgReader::new(&DATA[..]).unwrap();
+    let mut pcap_reader = PcapReader::new(&DATA_PCAP[..]).unwrap();
+
+    let mut nb_packets = 0;
+    while let Some(block) = pcapng_reader.next() {
+
+        if let Block::EnhancedPacket(packet) = block.unwrap() {
+
+            let pcap = pcap_reader.next().unwrap().unwrap();
+
+            assert_eq!(packet.interface_id, 0);
+            assert_eq!(packet.timestamp.as_secs(), pcap.header.ts_sec as u64);
+            assert_eq!(packet.timestamp.subsec_nanos(), pcap.header.ts_usec * 1000);
+            assert_eq!(packet.original_len, pcap.header.orig_len);
+            assert_eq!(&packet.data[..], &pcap.data[..]);
+
+            match pcapng_reader.packet_interface(&packet).unwrap().linktype {
+                DataLink::ETHERNET => {},
+                _ => panic!("Wrong interface linktype")
+            }
+
+            nb_packets += 1;
+        }
+    }
+
+    assert_eq!(nb_packets, 10);
+    assert!(pcap_reader.next().is_none());
+}
+
+#[test]
+fn detect() {
+
+    assert_eq!(detect_format(&DATA[..]), Format::PcapNg);
+    assert_eq!(detect_format(&DATA_PCAP[..4]), Format::Pcap(Endianness::Little));
+    assert_eq!(detect_format(&[0xa1, 0xb2, 0x3c, 0x4d]), Format::Pcap(Endianness::Big));
+    assert_eq!(detect_format(include_bytes!("test_in.pcap.gz")), Format::Gzip);
+    assert_eq!(detect_format(&[0x28, 0xb5, 0x2f, 0xfd, 0]), Format::Zstd);
+    assert_eq!(detect_format(b"GMBU\x00\x02"), Format::NetMon);
+    assert_eq!(detect_format(b"RTSS"), Format::NetMon);
+    assert_eq!(detect_format(b"snoop\0\0\0\0\0\0\x02"), Format::Snoop);
+    assert_eq!(detect_format(b"snoopy\0\0"), Format::Unknown);
+
+    assert_eq!(detect_format(&DATA_PCAP[..3]), Format::Unknown);
+    assert_eq!(detect_format(&[0u8; 8]), Format::Unknown);
+}
+
+#[test]
+fn block_too_large() {
+
+    // The Section Header Block, followed by a block declaring 2 GiB and a valid block
+    let shb_len = u32::from_le_bytes([DATA[4], DATA[5], DATA[6], DATA[7]]) as usize;
+    let mut data = DATA[..shb_len].to_vec();
+    data.extend_from_slice(&[6, 0, 0, 0, 0, 0, 0, 0x80]);
+    data.extend_from_slice(&DATA[shb_len..]);
+
+    let mut pcapng_reader = PcapNgReader::new(&data[..]).unwrap();
+    match *pcapng_reader.next().unwrap().unwrap_err().kind() {
+        ErrorKind::PacketTooLarge(len, max) => assert_eq!((len, max), (0x8000_0000, DEFAULT_MAX_BLOCK_LEN)),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+
+    // The position of the following blocks is unknown
+    assert!(pcapng_reader.next().is_none());
+
+    // The maximum can be lowered
+    let mut pcapng_reader = PcapNgReader::new(&DATA[..]).unwrap();
+    pcapng_reader.set_max_block_len(64);
+    assert!(pcapng_reader.any(|block| block.is_err()));
+}
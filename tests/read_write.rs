@@ -1,8 +1,18 @@
+extern crate byteorder;
 extern crate pcap_file;
 
-use pcap_file::{PcapReader, PcapWriter};
+use byteorder::LittleEndian;
 
-static DATA: &'static[u8; 1455] = include_bytes!("test_in.pcap");
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, Write};
+use std::time::{Duration, UNIX_EPOCH};
+
+use pcap_file::errors::{Error, ErrorKind, ResultChain};
+use pcap_file::split::{split_by_count, split_by_size};
+use pcap_file::{concat_bytes, diff, read_packet, read_packet_with_max, ArenaReader, DataLink, DiffEntry, DiffReason, Endianness, MultiFileReader, Packet, PacketBuilder, PacketHeader, PacketIndex, PacketPool, PcapHeader, PcapMerger, PcapReader, PcapSliceReader, PcapWriter, PcapWriterBuilder, RotatingWriter, SnaplenCheck, TsResolution, DEFAULT_MAX_PACKET_LEN, loopback_af, ppi_len, radiotap_len, transcode, validate};
+
+static DATA: &[u8; 1455] = include_bytes!("test_in.pcap");
 
 #[test]
 fn read() {
@@ -36,7 +46,2572 @@ fn read_write() {
         pcap_writer.write_packet(&pcap.unwrap()).unwrap();
     }
 
-    out = pcap_writer.into_writer();
+    out = pcap_writer.into_writer().unwrap();
 
     assert_eq!(&DATA[..], &out[..]);
-}
\ No newline at end of file
+}
+#[test]
+fn read_write_nanoseconds() {
+
+    let header = PcapHeader {
+        magic_number: 0xa1b23c4d,
+        ..Default::default()
+    };
+
+    let data = [0u8; 10];
+    let mut pcap_writer = PcapWriter::with_header(header, Vec::new()).unwrap();
+    pcap_writer.write(1, 123_456_789, &data).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let pcap_reader = PcapReader::new(&out[..]).unwrap();
+    assert_eq!(pcap_reader.header.ts_resolution(), TsResolution::NanoSecond);
+    let header = pcap_reader.header;
+
+    let mut pcap_writer = PcapWriter::with_header(header, Vec::new()).unwrap();
+    for pcap in pcap_reader {
+
+        let pcap = pcap.unwrap();
+        assert_eq!(pcap.header.ts_resolution, TsResolution::NanoSecond);
+        assert_eq!(pcap.header.ts_nsec(), 123_456_789);
+
+        pcap_writer.write_packet(&pcap).unwrap();
+    }
+
+    assert_eq!(out, pcap_writer.into_writer().unwrap());
+}
+
+#[test]
+fn datalink() {
+
+    let pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    assert_eq!(pcap_reader.header.datalink(), DataLink::ETHERNET);
+
+    let pcap_writer = PcapWriter::with_datalink(DataLink::LINUX_SLL, Vec::new()).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let pcap_reader = PcapReader::new(&out[..]).unwrap();
+    assert_eq!(pcap_reader.header.datalink(), DataLink::LINUX_SLL);
+    assert_eq!(u32::from(pcap_reader.header.datalink()), 113);
+    assert_eq!(DataLink::from(4242), DataLink::Unknown(4242));
+}
+
+#[test]
+fn read_slice() {
+
+    let pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    let pcap_slice_reader = PcapSliceReader::new(&DATA[..]).unwrap();
+
+    let mut nb_packets = 0;
+    for (pcap, pcap_slice) in pcap_reader.zip(pcap_slice_reader) {
+
+        let pcap = pcap.unwrap();
+        let pcap_slice = pcap_slice.unwrap();
+
+        match pcap_slice.data {
+            Cow::Borrowed(_) => {},
+            Cow::Owned(_) => panic!("The payload must be borrowed")
+        }
+
+        assert_eq!(pcap.header.ts_sec, pcap_slice.header.ts_sec);
+        assert_eq!(pcap.header.orig_len, pcap_slice.header.orig_len);
+        assert_eq!(pcap.data, pcap_slice.data);
+
+        nb_packets += 1;
+    }
+
+    assert_eq!(nb_packets, 10);
+}
+
+#[test]
+fn seek() {
+
+    let mut pcap_reader = PcapReader::new(Cursor::new(&DATA[..])).unwrap();
+
+    let mut offsets = Vec::new();
+    let mut packets = Vec::new();
+    loop {
+
+        let offset = pcap_reader.offset();
+        match pcap_reader.next() {
+            Some(pcap) => {
+                offsets.push(offset);
+                packets.push(pcap.unwrap());
+            },
+            None => break
+        }
+    }
+
+    assert_eq!(offsets[0], 24);
+    assert_eq!(pcap_reader.offset(), DATA.len() as u64);
+
+    for (i, offset) in offsets.iter().enumerate().rev() {
+
+        pcap_reader.seek_to(*offset).unwrap();
+        assert_eq!(pcap_reader.offset(), *offset);
+        assert_eq!(pcap_reader.next().unwrap().unwrap().data, packets[i].data);
+    }
+}
+
+/// Seekable reader counting the reads starting inside the global header
+struct HeaderReads<'a> {
+    cursor: Cursor<&'a [u8]>,
+    header_reads: usize
+}
+
+impl<'a> Read for HeaderReads<'a> {
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+
+        if self.cursor.position() < 24 {
+            self.header_reads += 1;
+        }
+
+        self.cursor.read(buf)
+    }
+}
+
+impl<'a> Seek for HeaderReads<'a> {
+
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.cursor.seek(pos)
+    }
+}
+
+#[test]
+fn seek_not_at_start() {
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+
+    // The pcap file starts after 100 bytes of other data
+    let mut data = vec![0xff; 100];
+    data.extend_from_slice(DATA);
+    let mut cursor = Cursor::new(data);
+    cursor.set_position(100);
+
+    let mut pcap_reader = PcapReader::new(cursor).unwrap();
+    assert_eq!(pcap_reader.offset(), 24);
+    pcap_reader.next().unwrap().unwrap();
+
+    // The offsets stay relative to the creation after a seek
+    let second = pcap_reader.offset();
+    pcap_reader.seek_to(24).unwrap();
+    assert_eq!(pcap_reader.offset(), 24);
+    assert_eq!(pcap_reader.next().unwrap().unwrap(), expected[0]);
+    assert_eq!(pcap_reader.offset(), second);
+
+    pcap_reader.seek_to(second).unwrap();
+    assert_eq!(pcap_reader.next().unwrap().unwrap(), expected[1]);
+
+    let index = pcap_reader.index().unwrap();
+    assert_eq!(index.offset(0), Some(24));
+    assert_eq!(pcap_reader.read_packet_at(&index, 5).unwrap(), expected[5]);
+    assert_eq!(pcap_reader.offset(), index.offset(6).unwrap());
+
+    // With bytes peeked
+    pcap_reader.peek_packet().unwrap().unwrap();
+    pcap_reader.seek_to(index.offset(2).unwrap()).unwrap();
+    assert_eq!(pcap_reader.next().unwrap().unwrap(), expected[2]);
+
+    // The wrapped reader keeps its absolute position
+    assert_eq!(pcap_reader.into_reader().position(), 100 + index.offset(3).unwrap());
+
+    // The stream length is relative to the creation too
+    let mut cursor = Cursor::new([vec![0xff; 100], DATA.to_vec()].concat());
+    cursor.set_position(100);
+    let pcap_reader = PcapReader::new_seek(cursor).unwrap();
+    assert_eq!(pcap_reader.size_hint().1, Some((DATA.len() - 24).div_ceil(16)));
+    assert_eq!(pcap_reader.count(), 10);
+}
+
+#[test]
+fn seek_back_to_first_packet() {
+
+    let reads = HeaderReads { cursor: Cursor::new(&DATA[..]), header_reads: 0 };
+    let mut pcap_reader = PcapReader::new(reads).unwrap();
+    let header = pcap_reader.header;
+    let header_reads = pcap_reader.get_ref().header_reads;
+
+    let first = pcap_reader.next().unwrap().unwrap();
+    pcap_reader.next().unwrap().unwrap();
+
+    // The bytes peeked past the second packet are dropped by the seek
+    assert!(pcap_reader.peek_packet().is_some());
+    pcap_reader.seek_to(24).unwrap();
+
+    let first_again = pcap_reader.next().unwrap().unwrap();
+    assert_eq!(first_again.header, first.header);
+    assert_eq!(first_again.data, first.data);
+
+    // The global header is kept, it is never read again
+    assert_eq!(pcap_reader.header, header);
+    assert_eq!(pcap_reader.get_ref().header_reads, header_reads);
+    assert_eq!(pcap_reader.count(), 9);
+}
+
+#[test]
+fn index() {
+
+    let mut pcap_reader = PcapReader::new(Cursor::new(&DATA[..])).unwrap();
+    let first = pcap_reader.next().unwrap().unwrap();
+
+    let index = pcap_reader.index().unwrap();
+    assert_eq!(index.len(), 10);
+    assert_eq!(index.offset(0), Some(24));
+    assert_eq!(index.timestamp(0), Some(first.header.ts_sec as u64 * 1_000_000_000));
+
+    // The position is restored after indexing
+    let second = pcap_reader.next().unwrap().unwrap();
+
+    assert_eq!(pcap_reader.read_packet_at(&index, 1).unwrap().data, second.data);
+    assert_eq!(pcap_reader.read_packet_at(&index, 0).unwrap().data, first.data);
+    assert!(pcap_reader.read_packet_at(&index, 10).is_err());
+}
+
+#[test]
+fn index_truncated() {
+
+    // Truncated payload of the last packet
+    let truncated = &DATA[..DATA.len() - 3];
+    assert!(PcapReader::new(Cursor::new(truncated)).unwrap().index().is_err());
+
+    let mut pcap_reader = PcapReader::new(Cursor::new(truncated)).unwrap();
+    pcap_reader.set_lenient(true);
+    assert_eq!(pcap_reader.index().unwrap().len(), 10);
+
+    // Stray bytes after the last packet
+    let stray = [DATA, &[0; 3][..]].concat();
+    assert!(PcapReader::new(Cursor::new(&stray[..])).unwrap().index().is_err());
+
+    let mut pcap_reader = PcapReader::new(Cursor::new(&stray[..])).unwrap();
+    pcap_reader.set_lenient(true);
+    assert_eq!(pcap_reader.index().unwrap().len(), 10);
+    assert_eq!(pcap_reader.count(), 10);
+}
+
+#[test]
+fn rev_from_index() {
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+
+    let mut pcap_reader = PcapReader::new(Cursor::new(&DATA[..])).unwrap();
+    let index = pcap_reader.index().unwrap();
+
+    let rev = pcap_reader.rev_from_index(&index);
+    assert_eq!(rev.size_hint(), (10, Some(10)));
+
+    let mut packets = rev.map(|pcap| pcap.unwrap()).collect::<Vec<_>>();
+    packets.reverse();
+    assert_eq!(packets, expected);
+
+    // Positioned after the first packet
+    assert_eq!(pcap_reader.next().unwrap().unwrap(), expected[1]);
+    assert_eq!(pcap_reader.rev_from_index(&PacketIndex::default()).count(), 0);
+}
+
+#[test]
+fn write_packet_indexed() {
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    let mut index = PacketIndex::default();
+
+    for pcap in PcapReader::new(&DATA[..]).unwrap() {
+
+        let pcap = pcap.unwrap();
+        let location = pcap_writer.write_packet_indexed(&pcap).unwrap();
+        assert_eq!(location.len, 16 + pcap.data.len() as u64);
+
+        index.entries.push((location.offset, pcap.duration_since_epoch().as_nanos() as u64));
+    }
+    let out = pcap_writer.into_writer().unwrap();
+
+    // The locations match the ones found by scanning the file
+    let mut pcap_reader = PcapReader::new(Cursor::new(&out[..])).unwrap();
+    assert_eq!(pcap_reader.index().unwrap().entries, index.entries);
+    assert_eq!(pcap_reader.read_packet_at(&index, 9).unwrap().data, PcapReader::new(&DATA[..]).unwrap().nth(9).unwrap().unwrap().data);
+}
+
+#[test]
+fn timestamp_parts() {
+
+    let packet = Packet::new(1_331_901_000, 999_999, 0, &[]);
+    assert_eq!(packet.timestamp_parts(TsResolution::MicroSecond), (1_331_901_000, 999_999_000));
+    assert_eq!(packet.timestamp_parts(TsResolution::NanoSecond), (1_331_901_000, 999_999));
+
+    // Out of range sub-second parts are saturated
+    let packet = Packet::new(1, 1_200_000_000, 0, &[]);
+    assert_eq!(packet.timestamp_parts(TsResolution::MicroSecond), (1, 999_999_000));
+    assert_eq!(packet.timestamp_parts(TsResolution::NanoSecond), (1, 999_999_999));
+
+    // Read from a nanosecond file
+    let ts = Duration::new(3, 123_456_789);
+    let mut pcap_writer = PcapWriter::new_nanos(Vec::new()).unwrap();
+    pcap_writer.write_tuples(vec![(ts, vec![])]).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let pcap_reader = PcapReader::new(&out[..]).unwrap();
+    let res = pcap_reader.header.ts_resolution();
+    let packet = pcap_reader.map(|pcap| pcap.unwrap()).next().unwrap();
+    assert_eq!(packet.timestamp_parts(res), (3, 123_456_789));
+}
+
+#[test]
+fn packet_builder() {
+
+    let data = [0xAAu8; 32];
+
+    let packet = PacketBuilder::new().data(&data[..]).build().unwrap();
+    assert_eq!((packet.incl_len(), packet.orig_len()), (32, 32));
+    assert_eq!(packet.duration_since_epoch(), Duration::default());
+    assert!(matches!(packet.data, Cow::Borrowed(_)));
+
+    let packet = PacketBuilder::new()
+        .timestamp(Duration::new(10, 123_456_789))
+        .ts_resolution(TsResolution::NanoSecond)
+        .data(data.to_vec())
+        .orig_len(1500)
+        .build()
+        .unwrap();
+    assert_eq!(packet.duration_since_epoch(), Duration::new(10, 123_456_789));
+    assert_eq!((packet.incl_len(), packet.orig_len()), (32, 1500));
+    assert!(packet.is_truncated());
+
+    // Truncated to the microsecond by default
+    let packet = PacketBuilder::new().timestamp(Duration::new(10, 123_456_789)).build().unwrap();
+    assert_eq!(packet.header, PacketHeader::new(10, 123_456, 0));
+
+    assert!(PacketBuilder::new().data(&data[..]).orig_len(31).build().is_err());
+    assert!(PacketBuilder::new().timestamp(Duration::from_secs(1 << 32)).build().is_err());
+}
+
+#[test]
+fn packet_header_bytes() {
+
+    let expected = PcapReader::new(&DATA[..]).unwrap().next().unwrap().unwrap().header;
+
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&DATA[24..40]);
+    let header = PacketHeader::from_slice(&bytes, Endianness::Little);
+    assert_eq!(header, expected);
+    assert_eq!(header.to_bytes(Endianness::Little), bytes);
+    assert_eq!(&header.to_bytes(Endianness::Little)[..], &header.to_array::<LittleEndian>().unwrap()[..]);
+
+    let big = header.to_bytes(Endianness::Big);
+    assert_eq!(&big[..4], &[bytes[3], bytes[2], bytes[1], bytes[0]]);
+    assert_eq!(PacketHeader::from_slice(&big, Endianness::Big), header);
+}
+
+#[test]
+fn timestamp() {
+
+    let mut header = PacketHeader::new(1, 999_999, 0);
+    assert_eq!(header.duration_since_epoch(), Duration::new(1, 999_999_000));
+
+    // Malformed sub-second parts are saturated
+    header.ts_usec = 4_000_000;
+    assert_eq!(header.duration_since_epoch(), Duration::new(1, 999_999_000));
+
+    header.ts_resolution = TsResolution::NanoSecond;
+    header.ts_usec = 4_000_000_000;
+    assert_eq!(header.duration_since_epoch(), Duration::new(1, 999_999_999));
+
+    header.ts_usec = 123;
+    assert_eq!(header.timestamp(), UNIX_EPOCH + Duration::new(1, 123));
+}
+
+#[test]
+fn read_into() {
+
+    let mut pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    let expected = PcapReader::new(&DATA[..]).unwrap();
+
+    // The packets of the file shrink and grow, the buffer must follow
+    let mut buf = Vec::new();
+    for expected in expected {
+
+        let expected = expected.unwrap();
+        let header = pcap_reader.next_into(&mut buf).unwrap().unwrap();
+
+        assert_eq!(header.incl_len, expected.header.incl_len);
+        assert_eq!(&buf[..], &expected.data[..]);
+    }
+
+    assert!(pcap_reader.next_into(&mut buf).is_none());
+}
+
+#[test]
+fn writer_builder() {
+
+    let pcap_writer = PcapWriterBuilder::new()
+        .snaplen(1500)
+        .datalink(DataLink::RAW)
+        .ts_resolution(TsResolution::NanoSecond)
+        .endianness(Endianness::Little)
+        .build(Vec::new())
+        .unwrap();
+
+    let out = pcap_writer.into_writer().unwrap();
+    assert_eq!(out.len(), 24);
+
+    let header = PcapReader::new(&out[..]).unwrap().header;
+    assert_eq!(header.magic_number, 0x4d3cb2a1);
+    assert_eq!(header.snaplen, 1500);
+    assert_eq!(header.datalink, DataLink::RAW);
+
+    assert_eq!(PcapWriterBuilder::new().snaplen(0).header().unwrap().snaplen, 0);
+}
+
+#[test]
+fn write_orig_len_lower_than_data() {
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write_packet(&Packet::new(0, 0, 10, &[1; 20])).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let packet = PcapReader::new(&out[..]).unwrap().next().unwrap().unwrap();
+    assert_eq!((packet.header.incl_len, packet.header.orig_len), (20, 20));
+    assert_eq!(&packet.data[..], &[1; 20][..]);
+
+    // Truncated to the snaplen, the orig_len is the length of the payload given
+    let mut pcap_writer = PcapWriterBuilder::new().snaplen(8).build(Vec::new()).unwrap();
+    pcap_writer.write_packet(&Packet::new(0, 0, 10, &[1; 20])).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let packet = PcapReader::new(&out[..]).unwrap().next().unwrap().unwrap();
+    assert_eq!((packet.header.incl_len, packet.header.orig_len), (8, 20));
+}
+
+#[test]
+fn finalize_error_if_empty() {
+
+    // Permissive by default
+    let pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    assert_eq!(pcap_writer.finalize().unwrap().len(), 24);
+
+    let pcap_writer = PcapWriterBuilder::new().error_if_empty(true).build(Vec::new()).unwrap();
+    assert!(matches!(*pcap_writer.finalize().unwrap_err().kind(), ErrorKind::EmptyCapture));
+
+    let mut pcap_writer = PcapWriterBuilder::new().error_if_empty(true).buffer_capacity(4096).build(Vec::new()).unwrap();
+    pcap_writer.write(0, 0, &[0; 10]).unwrap();
+    let out = pcap_writer.finalize().unwrap();
+    assert_eq!(PcapReader::new(&out[..]).unwrap().count(), 1);
+}
+
+#[test]
+fn write_snaplen_zero() {
+
+    // Written as is, but the packets are truncated to 65535 bytes
+    let mut pcap_writer = PcapWriterBuilder::new().snaplen(0).build(Vec::new()).unwrap();
+    pcap_writer.write(0, 0, &[1; 70_000]).unwrap();
+    pcap_writer.write(0, 0, &[2; 100]).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let pcap_reader = PcapReader::new(&out[..]).unwrap();
+    assert_eq!(pcap_reader.header.raw_snaplen(), 0);
+
+    let packets: Vec<_> = pcap_reader.map(|pcap| pcap.unwrap()).collect();
+    assert_eq!((packets[0].header.incl_len, packets[0].header.orig_len), (65535, 70_000));
+    assert_eq!((packets[1].header.incl_len, packets[1].header.orig_len), (100, 100));
+
+    // No packet is longer than the effective snaplen
+    assert!(validate(&out[..]).unwrap().first_anomaly.is_none());
+}
+
+#[test]
+fn read_past_snaplen() {
+
+    // Records longer than the snaplen, as written by some broken tools
+    let capture = |snaplen| {
+
+        let header = PcapHeader { snaplen, ..PcapHeader::default() };
+        let mut out = PcapWriter::with_header(header, Vec::new()).unwrap().into_writer().unwrap();
+        for &(len, byte) in &[(70_000, 1), (100, 2)] {
+            out.extend_from_slice(&PacketHeader::new(0, 0, len).to_bytes(header.endianness()));
+            out.extend(vec![byte; len as usize]);
+        }
+        out
+    };
+
+    for &(snaplen, effective) in &[(0, 65535), (1000, 1000)] {
+
+        let out = capture(snaplen);
+
+        let packets: Vec<_> = PcapReader::new(&out[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+        assert_eq!((packets[0].header.incl_len, packets[0].header.orig_len), (effective, 70_000));
+        assert_eq!(packets[0].data.len(), effective as usize);
+        assert_eq!(&packets[1].data[..], &[2; 100][..]);
+
+        let slice_packets: Vec<_> = PcapSliceReader::new(&out[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+        assert_eq!(slice_packets, packets);
+
+        // The peeked and the raw records are truncated the same way
+        let mut pcap_reader = PcapReader::new(&out[..]).unwrap();
+        assert_eq!(pcap_reader.peek_packet().unwrap().unwrap().incl_len, effective);
+        let mut record = Vec::new();
+        assert_eq!(pcap_reader.next_raw_into(&mut record).unwrap().unwrap().incl_len, effective);
+        assert_eq!(record.len(), 16 + effective as usize);
+        let mut raw = [0u8; 16];
+        raw.copy_from_slice(&record[..16]);
+        assert_eq!(PacketHeader::from_slice(&raw, Endianness::Big).incl_len, effective);
+
+        // Only the headers keep the length written
+        let headers: Vec<_> = PcapReader::new(&out[..]).unwrap().headers().map(|header| header.unwrap().incl_len).collect();
+        assert_eq!(headers, vec![70_000, 100]);
+
+        // A record cut in the skipped bytes is truncated
+        let cut = &out[..24 + 16 + effective as usize + 10];
+        assert!(PcapReader::new(cut).unwrap().next().unwrap().is_err());
+        let mut pcap_reader = PcapReader::new(cut).unwrap();
+        pcap_reader.set_lenient(true);
+        assert_eq!(pcap_reader.next().unwrap().unwrap().header.incl_len, effective);
+        assert!(pcap_reader.next().is_none());
+    }
+}
+
+#[test]
+fn write_truncated() {
+
+    let data = [0xAAu8; 117];
+    let mut pcap_writer = PcapWriterBuilder::new().snaplen(50).build(Vec::new()).unwrap();
+    pcap_writer.write(0, 0, &data).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    assert_eq!(out.len(), 24 + 16 + 50);
+
+    let pcap = PcapReader::new(&out[..]).unwrap().next().unwrap().unwrap();
+    assert_eq!(pcap.incl_len(), 50);
+    assert_eq!(pcap.orig_len(), 117);
+    assert_eq!(&pcap.data[..], &data[..50]);
+}
+
+#[test]
+fn time_range() {
+
+    // Packets at 1, 2, 5 and 3 seconds, the last one is out of order
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for (i, &ts_sec) in [1, 2, 5, 3].iter().enumerate() {
+        pcap_writer.write(ts_sec, 0, &[i as u8; 10]).unwrap();
+    }
+    let out = pcap_writer.into_writer().unwrap();
+
+    let start = Duration::from_secs(2);
+    let end = Duration::from_secs(4);
+
+    let sorted: Vec<_> = PcapReader::new(&out[..]).unwrap()
+        .in_time_range(start, end)
+        .map(|pcap| pcap.unwrap().data[0])
+        .collect();
+    assert_eq!(sorted, vec![1]);
+
+    let unsorted: Vec<_> = PcapReader::new(&out[..]).unwrap()
+        .in_time_range(start, end)
+        .unsorted()
+        .map(|pcap| pcap.unwrap().data[0])
+        .collect();
+    assert_eq!(unsorted, vec![1, 3]);
+}
+
+#[test]
+fn limit() {
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+
+    let mut limit = PcapReader::new_seek(Cursor::new(&DATA[..])).unwrap().limit(3);
+    assert_eq!(limit.size_hint().1, Some(3));
+    let packets: Vec<_> = limit.by_ref().map(|pcap| pcap.unwrap()).collect();
+    assert_eq!(packets, &expected[..3]);
+    assert!(limit.next().is_none());
+
+    // The reader resumes after the last packet yielded
+    let mut pcap_reader = limit.into_inner();
+    assert_eq!(pcap_reader.next().unwrap().unwrap(), expected[3]);
+
+    pcap_reader.seek_to_end().unwrap();
+    assert!(pcap_reader.next().is_none());
+    let mut cursor = pcap_reader.into_reader();
+    assert_eq!(cursor.position(), DATA.len() as u64);
+    assert_eq!(cursor.read(&mut [0u8; 1]).unwrap(), 0);
+
+    assert_eq!(PcapReader::new(&DATA[..]).unwrap().limit(20).count(), 10);
+}
+
+#[test]
+fn truncation() {
+
+    let mut pcap_writer = PcapWriterBuilder::new().snaplen(8).build(Vec::new()).unwrap();
+    pcap_writer.write(1, 0, &[0u8; 4]).unwrap();
+    pcap_writer.write_packet(&Packet::from_data(2, 0, vec![0u8; 8].into(), 60).unwrap()).unwrap();
+    pcap_writer.write(3, 0, &[0u8; 8]).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let ts = |packets: Vec<ResultChain<Packet>>| packets.into_iter().map(|pcap| pcap.unwrap().header.ts_sec).collect::<Vec<_>>();
+    assert_eq!(ts(PcapReader::new(&out[..]).unwrap().only_complete().collect()), vec![1, 3]);
+    assert_eq!(ts(PcapReader::new(&out[..]).unwrap().only_truncated().collect()), vec![2]);
+
+    assert!(PcapReader::new(&DATA[..]).unwrap().all(|pcap| !pcap.unwrap().is_truncated()));
+    assert!(!Packet::new(0, 0, 4, &[0u8; 4]).is_truncated());
+}
+
+/// Reader failing once its data is read
+struct Broken<'a>(&'a [u8]);
+
+impl<'a> Read for Broken<'a> {
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+
+        if self.0.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::Other, "broken"));
+        }
+
+        self.0.read(buf)
+    }
+}
+
+#[test]
+fn with_deltas() {
+
+    // The third packet is out of order
+    let ts = [Duration::from_secs(1), Duration::from_secs(3), Duration::from_secs(2), Duration::from_millis(2500)];
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write_tuples(ts.iter().map(|&ts| (ts, vec![0u8; 4]))).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let deltas: Vec<_> = PcapReader::new(&out[..]).unwrap().with_deltas().map(|pcap| pcap.unwrap().1).collect();
+    assert_eq!(deltas, vec![None, Some(Duration::from_secs(2)), Some(Duration::default()), Some(Duration::from_millis(500))]);
+
+    // Same timestamps
+    let deltas: Vec<_> = PcapReader::new(&DATA[..]).unwrap().with_deltas().map(|pcap| pcap.unwrap().1).collect();
+    assert_eq!(deltas.len(), 10);
+    assert_eq!(deltas[0], None);
+    assert!(deltas[1..].iter().all(|&delta| delta == Some(Duration::default())));
+}
+
+#[test]
+fn lossy() {
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write(1, 0, &[1u8; 4]).unwrap();
+    pcap_writer.write(2, 0, &[0u8; 16]).unwrap();
+    pcap_writer.write(3, 0, &[3u8; 4]).unwrap();
+    let mut out = pcap_writer.into_writer().unwrap();
+
+    // incl_len > orig_len for the second packet, its payload is then parsed as an empty packet
+    let orig_len = 24 + 16 + 4 + 12;
+    out[orig_len..orig_len + 4].copy_from_slice(&[0; 4]);
+
+    let mut packets = PcapReader::new(&out[..]).unwrap().lossy();
+    let ts: Vec<_> = packets.by_ref().map(|pcap| pcap.header.ts_sec).collect();
+    assert_eq!(ts, vec![1, 0, 3]);
+    assert_eq!(packets.errors().len(), 1);
+    assert!(matches!(*packets.errors()[0].root_kind(), ErrorKind::WrongField(_)));
+
+    // Only the first errors are kept
+    let mut packets = PcapReader::new(&out[..out.len() - 2]).unwrap().lossy().max_errors(1);
+    assert_eq!(packets.by_ref().count(), 2);
+    assert_eq!(packets.dropped_errors(), 1);
+    assert!(matches!(*packets.into_errors()[0].root_kind(), ErrorKind::WrongField(_)));
+
+    // An I/O error ends the iteration
+    let mut packets = PcapReader::new(Broken(&DATA[..100])).unwrap().lossy();
+    assert_eq!(packets.by_ref().count(), 0);
+    assert!(packets.next().is_none());
+    assert!(matches!(*packets.errors()[0].root_kind(), ErrorKind::Io(_)));
+}
+
+#[test]
+fn dedup() {
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write(10, 0, &[1u8; 4]).unwrap();
+    pcap_writer.write(10, 100, &[1u8; 4]).unwrap();
+    pcap_writer.write(11, 0, &[1u8; 4]).unwrap();
+    pcap_writer.write(11, 0, &[2u8; 4]).unwrap();
+    pcap_writer.write(12, 0, &[1u8; 4]).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let ts = |packets: Vec<ResultChain<Packet>>| packets.into_iter().map(|pcap| pcap.unwrap().duration_since_epoch()).collect::<Vec<_>>();
+
+    // Only the consecutive duplicates are dropped, whatever their timestamp
+    let deduped = ts(PcapReader::new(&out[..]).unwrap().dedup().collect());
+    assert_eq!(deduped, vec![Duration::new(10, 0), Duration::new(11, 0), Duration::new(12, 0)]);
+
+    let deduped = ts(PcapReader::new(&out[..]).unwrap().dedup().within(Duration::from_millis(1)).collect());
+    assert_eq!(deduped, vec![Duration::new(10, 0), Duration::new(11, 0), Duration::new(11, 0), Duration::new(12, 0)]);
+
+    // The test capture has no duplicate
+    assert_eq!(PcapReader::new(&DATA[..]).unwrap().dedup().count(), 10);
+}
+
+#[test]
+fn bucketed() {
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write(10, 0, &[0u8; 4]).unwrap();
+    pcap_writer.write(10, 500_000, &[0u8; 4]).unwrap();
+    pcap_writer.write(11, 200_000, &[0u8; 4]).unwrap();
+    // Out of order, but still in an open bucket
+    pcap_writer.write(10, 900_000, &[0u8; 4]).unwrap();
+    pcap_writer.write(13, 0, &[0u8; 4]).unwrap();
+    // Too late, its bucket was already yielded
+    pcap_writer.write(11, 500_000, &[0u8; 4]).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let buckets = PcapReader::new(&out[..]).unwrap()
+        .bucketed(Duration::from_secs(1))
+        .map(|bucket| {
+            let (start, packets) = bucket.unwrap();
+            (start.as_secs(), packets.iter().map(|pcap| pcap.duration_since_epoch().subsec_millis()).collect::<Vec<_>>())
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(buckets, vec![(10, vec![0, 500, 900]), (11, vec![200]), (11, vec![500]), (13, vec![0])]);
+
+    // The buckets start on multiples of the interval
+    let starts = PcapReader::new(&DATA[..]).unwrap()
+        .bucketed(Duration::from_millis(1500))
+        .map(|bucket| bucket.unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(starts.iter().map(|bucket| bucket.1.len()).sum::<usize>(), 10);
+    assert!(starts.iter().all(|bucket| bucket.0.as_millis() % 1500 == 0));
+}
+
+#[test]
+fn retimestamp() {
+
+    let mut pcap_writer = PcapWriterBuilder::new().ts_resolution(TsResolution::NanoSecond).build(Vec::new()).unwrap();
+    pcap_writer.write(10, 500, &[0u8; 4]).unwrap();
+    pcap_writer.write(12, 0, &[0u8; 4]).unwrap();
+    pcap_writer.write(9, 0, &[0u8; 4]).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let start = Duration::new(1000, 0);
+    let shifted: Vec<_> = PcapReader::new(&out[..]).unwrap()
+        .shift_to(start)
+        .map(|pcap| pcap.unwrap().duration_since_epoch())
+        .collect();
+    assert_eq!(shifted, vec![start, Duration::new(1001, 999_999_500), Duration::new(998, 999_999_500)]);
+
+    let scaled: Vec<_> = PcapReader::new(&out[..]).unwrap()
+        .retimestamp(|ts| ts * 2)
+        .map(|pcap| pcap.unwrap().header.ts_sec)
+        .collect();
+    assert_eq!(scaled, vec![20, 24, 18]);
+
+    let mut overflow = PcapReader::new(&out[..]).unwrap().retimestamp(|_| Duration::from_secs(1 << 40));
+    assert!(overflow.next().unwrap().is_err());
+
+    // The sub-second part is written with the resolution of the writer
+    let packet = PcapReader::new(&out[..]).unwrap().next().unwrap().unwrap();
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write_packet_with_timestamp(&packet, Duration::new(5, 123_456_789)).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let packet = PcapReader::new(&out[..]).unwrap().next().unwrap().unwrap();
+    assert_eq!((packet.header.ts_sec, packet.header.ts_usec), (5, 123_456));
+}
+
+#[test]
+fn take_for() {
+
+    assert_eq!(PcapReader::new(&DATA[..]).unwrap().take_for(Duration::default()).count(), 0);
+    assert_eq!(PcapReader::new(&DATA[..]).unwrap().take_for(Duration::from_secs(60)).count(), 10);
+
+    // Each read takes longer than the deadline
+    struct Slow<'a>(&'a [u8]);
+    impl<'a> Read for Slow<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            std::thread::sleep(Duration::from_millis(10));
+            self.0.read(buf)
+        }
+    }
+
+    let pcap_reader = PcapReader::new(Slow(&DATA[..])).unwrap();
+    let mut take_for = pcap_reader.take_for(Duration::from_millis(5));
+    assert_eq!(take_for.by_ref().count(), 1);
+
+    let packets: Vec<_> = take_for.into_inner().map(|pcap| pcap.unwrap()).collect();
+    assert_eq!(packets.len(), 9);
+}
+
+#[test]
+fn take_until() {
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for &ts_sec in &[1, 2, 5, 6, 9] {
+        pcap_writer.write(ts_sec, 0, &[ts_sec as u8; 10]).unwrap();
+    }
+    let out = pcap_writer.into_writer().unwrap();
+
+    let mut pcap_reader = PcapReader::new(&out[..]).unwrap();
+    let window = |pcap_reader: &mut PcapReader<&[u8]>, end| {
+        pcap_reader.take_until(Duration::from_secs(end)).map(|pcap| pcap.unwrap().header.ts_sec).collect::<Vec<_>>()
+    };
+
+    assert_eq!(window(&mut pcap_reader, 3), vec![1, 2]);
+    assert_eq!(window(&mut pcap_reader, 3), vec![]);
+    assert_eq!(window(&mut pcap_reader, 6), vec![5]);
+
+    // The packet past the boundary isn't lost
+    assert_eq!(pcap_reader.next().unwrap().unwrap().header.ts_sec, 6);
+    assert_eq!(window(&mut pcap_reader, 100), vec![9]);
+    assert!(pcap_reader.next().is_none());
+
+    // In lenient mode, the truncated last packet is kept and the stray bytes ignored
+    for (cut, expected) in [(3, vec![1, 2, 5, 6, 9]), (16 + 10 + 3, vec![1, 2, 5, 6])] {
+
+        let mut pcap_reader = PcapReader::new(&out[..out.len() - cut]).unwrap();
+        pcap_reader.set_lenient(true);
+        assert_eq!(window(&mut pcap_reader, 100), expected);
+        assert!(pcap_reader.next().is_none());
+    }
+}
+
+#[test]
+fn merge() {
+
+    let capture = |timestamps: &[u32], datalink| {
+
+        let mut pcap_writer = PcapWriter::with_datalink(datalink, Vec::new()).unwrap();
+        for &ts_sec in timestamps {
+            pcap_writer.write(ts_sec, 0, &[ts_sec as u8; 4]).unwrap();
+        }
+
+        pcap_writer.into_writer().unwrap()
+    };
+
+    let eth0 = capture(&[1, 4, 5], DataLink::ETHERNET);
+    let eth1 = capture(&[2, 3, 6, 7], DataLink::ETHERNET);
+    let tun0 = capture(&[0], DataLink::RAW);
+
+    let readers = vec![PcapReader::new(&eth0[..]).unwrap(), PcapReader::new(&eth1[..]).unwrap()];
+    let merged: Vec<_> = PcapMerger::new(readers).unwrap()
+        .map(|pcap| pcap.unwrap().header.ts_sec)
+        .collect();
+    assert_eq!(merged, vec![1, 2, 3, 4, 5, 6, 7]);
+
+    let readers = vec![PcapReader::new(&eth0[..]).unwrap(), PcapReader::new(&tun0[..]).unwrap()];
+    assert!(PcapMerger::new(readers).is_err());
+
+    let readers = vec![PcapReader::new(&eth0[..]).unwrap(), PcapReader::new(&tun0[..]).unwrap()];
+    assert_eq!(PcapMerger::new_permissive(readers).count(), 4);
+}
+
+#[test]
+fn multi_file_reader() {
+
+    let capture = |timestamps: &[u32], endianness, datalink| {
+
+        let mut pcap_writer = PcapWriterBuilder::new().endianness(endianness).datalink(datalink).build(Vec::new()).unwrap();
+        for &ts_sec in timestamps {
+            pcap_writer.write(ts_sec, 0, &[ts_sec as u8; 4]).unwrap();
+        }
+
+        pcap_writer.into_writer().unwrap()
+    };
+
+    let file1 = capture(&[1, 2], Endianness::Little, DataLink::ETHERNET);
+    let file2 = capture(&[], Endianness::Little, DataLink::ETHERNET);
+    let file3 = capture(&[3], Endianness::Little, DataLink::ETHERNET);
+    let big_endian = capture(&[4], Endianness::Big, DataLink::ETHERNET);
+    let raw = capture(&[4], Endianness::Little, DataLink::RAW);
+
+    let readers = vec![PcapReader::new(&file1[..]).unwrap(), PcapReader::new(&file2[..]).unwrap(), PcapReader::new(&file3[..]).unwrap()];
+    let packets: Vec<_> = MultiFileReader::new(readers).unwrap()
+        .map(|pcap| pcap.unwrap().header.ts_sec)
+        .collect();
+    assert_eq!(packets, vec![1, 2, 3]);
+
+    for other in &[big_endian, raw] {
+
+        let readers = vec![PcapReader::new(&file1[..]).unwrap(), PcapReader::new(&other[..]).unwrap()];
+        match *MultiFileReader::new(readers).unwrap_err().kind() {
+            ErrorKind::IncompatibleHeaders(_) => {},
+            ref kind => panic!("Unexpected error {:?}", kind)
+        }
+    }
+
+    assert!(MultiFileReader::<&[u8]>::new(Vec::new()).is_err());
+}
+
+#[test]
+fn split() {
+
+    let packets = |file: &Vec<u8>| PcapReader::new(&file[..]).unwrap().count();
+
+    let mut files = vec![Vec::new(); 10];
+    let count = {
+        let mut outputs = files.iter_mut();
+        split_by_count(PcapReader::new(&DATA[..]).unwrap(), 3, |_| outputs.next().unwrap()).unwrap()
+    };
+    assert_eq!(count, 4);
+    assert_eq!(files[..count].iter().map(packets).collect::<Vec<_>>(), vec![3, 3, 3, 1]);
+
+    // The outputs must stay under the limit, headers included
+    let size = 600;
+    let mut files = vec![Vec::new(); 10];
+    let count = {
+        let mut outputs = files.iter_mut();
+        split_by_size(PcapReader::new(&DATA[..]).unwrap(), size, |_| outputs.next().unwrap()).unwrap()
+    };
+    assert!(count > 1);
+    assert!(files[..count].iter().all(|file| file.len() as u64 <= size));
+    assert_eq!(files[..count].iter().map(packets).sum::<usize>(), 10);
+
+    assert!(split_by_count(PcapReader::new(&DATA[..]).unwrap(), 0, |_| Vec::new()).is_err());
+    assert!(split_by_size(PcapReader::new(&DATA[..]).unwrap(), 24, |_| Vec::new()).is_err());
+}
+
+#[test]
+fn record_len() {
+
+    // The records and the global header make up the whole file
+    let records: usize = PcapReader::new(&DATA[..]).unwrap().map(|packet| packet.unwrap().record_len()).sum();
+    assert_eq!(PcapHeader::SIZE + records, DATA.len());
+
+    // The packet headers of the modified format take 24 bytes
+    let mut pcap_writer = PcapWriterBuilder::new().modified(true).snaplen(8).build(Vec::new()).unwrap();
+    let packet = Packet::new(0, 0, 10, &[0u8; 10]);
+    assert_eq!(packet.record_len_in(&pcap_writer.header), 24 + 8);
+    pcap_writer.write_packet(&packet).unwrap();
+    let data = pcap_writer.into_writer().unwrap();
+
+    let records: usize = PcapReader::new(&data[..]).unwrap().map(|packet| packet.unwrap().record_len()).sum();
+    assert_eq!(records, 24 + 8);
+    assert_eq!(PcapHeader::SIZE + records, data.len());
+}
+
+#[test]
+fn rotating_writer() {
+
+    let dir = std::env::temp_dir().join(format!("pcap_file_rotating_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = |slot: usize| dir.join(format!("ring_{}.pcap", slot));
+
+    let src: Vec<_> = PcapReader::new(&DATA[..]).unwrap().collect::<Result<_, _>>().unwrap();
+    let header = PcapReader::new(&DATA[..]).unwrap().header;
+
+    // 10 packets in outputs of 3 packets, over 2 slots: the slots hold the last 2 outputs
+    let mut requested = Vec::new();
+    let outputs = {
+        let mut writer = RotatingWriter::by_count(header, 3, 2, |slot| {
+            requested.push(slot);
+            File::create(path(slot)).unwrap()
+        }).unwrap();
+
+        for packet in &src {
+            writer.write_packet(packet).unwrap();
+        }
+
+        let outputs = writer.outputs();
+        writer.into_writer().unwrap();
+        outputs
+    };
+    assert_eq!(outputs, 4);
+    assert_eq!(requested, vec![0, 1, 0, 1]);
+
+    let read = |slot: usize| {
+        let reader = PcapReader::new(File::open(path(slot)).unwrap()).unwrap();
+        assert_eq!(reader.header, header);
+        reader.collect::<Result<Vec<_>, _>>().unwrap()
+    };
+    assert_eq!(read(0), src[6..9].to_vec());
+    assert_eq!(read(1), src[9..].to_vec());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(RotatingWriter::by_count(header, 0, 2, |_| Vec::<u8>::new()).is_err());
+    assert!(RotatingWriter::by_count(header, 3, 0, |_| Vec::<u8>::new()).is_err());
+    assert!(RotatingWriter::by_size(header, 24, 2, |_| Vec::<u8>::new()).is_err());
+
+    // The records of the modified format take 24 + 10 bytes, 2 of them fit in 92 bytes
+    let header = PcapWriterBuilder::new().modified(true).build(Vec::new()).unwrap().header;
+    let mut files = Vec::new();
+    let outputs = {
+        let mut writer = RotatingWriter::by_size(header, 24 + 2 * 34, 10, |_| Vec::new()).unwrap();
+        for _ in 0..5 {
+            writer.write(0, 0, &[0u8; 10]).unwrap();
+        }
+        let outputs = writer.outputs();
+        files.extend(writer.into_writer().unwrap());
+        outputs
+    };
+    assert_eq!(outputs, 3);
+    assert_eq!(files[0].len(), 24 + 34);
+}
+
+/// Reader returning at most 1 byte per call, interrupted every other call
+struct Trickle<'a> {
+    data: &'a [u8],
+    interrupt: bool
+}
+
+impl<'a> Read for Trickle<'a> {
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+        }
+
+        let len = buf.len().min(self.data.len()).min(1);
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        Ok(len)
+    }
+}
+
+#[test]
+fn strip_fcs() {
+
+    let data: Vec<u8> = (0..64).collect();
+    let packet = Packet::new(0, 0, 64, &data);
+    assert_eq!(packet.strip_fcs(DataLink::ETHERNET), &data[..60]);
+    assert_eq!(packet.strip_fcs(DataLink::LINUX_SLL), &data[..]);
+
+    // Too short, or truncated before its FCS
+    assert_eq!(Packet::new(0, 0, 3, &data[..3]).strip_fcs(DataLink::ETHERNET), &data[..3]);
+    let truncated = Packet::from_data(0, 0, data[..32].into(), 64).unwrap();
+    assert_eq!(truncated.strip_fcs(DataLink::ETHERNET), &data[..32]);
+}
+
+#[test]
+fn copy_payload_into() {
+
+    // Keep the packets with an odd length in a single arena
+    let mut arena = Vec::new();
+    let mut kept = Vec::new();
+    for pcap in PcapReader::new(&DATA[..]).unwrap() {
+
+        let pcap = pcap.unwrap();
+        if pcap.data.len() % 2 == 1 {
+            let range = pcap.copy_payload_into(&mut arena);
+            kept.push((pcap.header, range, pcap.data.into_owned()));
+        }
+    }
+
+    assert!(!kept.is_empty());
+    assert_eq!(arena.len(), kept.iter().map(|(header, _, _)| header.incl_len as usize).sum::<usize>());
+    for (_, range, data) in &kept {
+        assert_eq!(&arena[range.clone()], &data[..]);
+    }
+
+    let empty = Packet::new(0, 0, 0, &[]).copy_payload_into(&mut arena);
+    assert_eq!(empty, arena.len()..arena.len());
+}
+
+#[test]
+fn parse_one() {
+
+    let header = PcapReader::new(&DATA[..]).unwrap().header;
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+
+    // The records arrive in chunks of 100 bytes
+    let mut buffer = Vec::new();
+    let mut packets = Vec::new();
+    for chunk in DATA[24..].chunks(100) {
+
+        buffer.extend_from_slice(chunk);
+
+        let mut consumed = 0;
+        while let Some((packet, len)) = Packet::parse_one(&header, &buffer[consumed..]).unwrap() {
+            assert_eq!(len, packet.record_len());
+            packets.push(packet.into_owned());
+            consumed += len;
+        }
+        buffer.drain(..consumed);
+    }
+
+    assert!(buffer.is_empty());
+    assert_eq!(packets, expected);
+
+    assert!(Packet::parse_one(&header, &DATA[24..30]).unwrap().is_none());
+    assert!(Packet::parse_one(&header, &DATA[24..50]).unwrap().is_none());
+    assert!(Packet::parse_one(&header, &[0xff; 16]).is_err());
+}
+
+#[test]
+fn read_trickle() {
+
+    let trickle = |data| Trickle { data, interrupt: false };
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+    let packets: Vec<_> = PcapReader::new(trickle(&DATA[..])).unwrap().map(|pcap| pcap.unwrap()).collect();
+    assert_eq!(packets, expected);
+
+    // A stream ending mid-record is reported, not returned as a shorter payload
+    let mut results: Vec<_> = PcapReader::new(trickle(&DATA[..DATA.len() - 3])).unwrap().collect();
+    match *results.pop().unwrap().unwrap_err().root_kind() {
+        ErrorKind::TruncatedPacket(expected, got) => assert_eq!(expected - got, 3),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+    assert!(results.into_iter().all(|pcap| pcap.is_ok()));
+
+    let record = &DATA[24..];
+    let packet = Packet::from_reader::<_, LittleEndian>(&mut trickle(record), TsResolution::MicroSecond).unwrap();
+    assert_eq!(packet, expected[0]);
+
+    match *Packet::from_reader::<_, LittleEndian>(&mut trickle(&record[..10]), TsResolution::MicroSecond).unwrap_err().kind() {
+        ErrorKind::TruncatedPacket(16, 10) => {},
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+
+    match *Packet::from_reader::<_, LittleEndian>(&mut trickle(&record[..20]), TsResolution::MicroSecond).unwrap_err().kind() {
+        ErrorKind::TruncatedPacket(_, 4) => {},
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+}
+
+#[test]
+fn read_truncated() {
+
+    // Truncated payload
+    let mut results: Vec<_> = PcapReader::new(&DATA[..DATA.len() - 3]).unwrap().collect();
+    assert_eq!(results.len(), 10);
+
+    match *results.pop().unwrap().unwrap_err().root_kind() {
+        ErrorKind::TruncatedPacket(expected, got) => assert_eq!(expected - got, 3),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+    assert!(results.into_iter().all(|pcap| pcap.is_ok()));
+
+    // Truncated header
+    let first_len = PcapReader::new(&DATA[..]).unwrap().next().unwrap().unwrap().data.len();
+    let mut pcap_reader = PcapReader::new(&DATA[..24 + 16 + first_len + 5]).unwrap();
+    assert!(pcap_reader.next().unwrap().is_ok());
+
+    let err = pcap_reader.next().unwrap().unwrap_err();
+    match *err.kind() {
+        ErrorKind::ReadPacket(index, offset) => assert_eq!((index, offset), (Some(1), 24 + 16 + first_len as u64)),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+    match *err.root_kind() {
+        ErrorKind::TruncatedPacket(16, 5) => {},
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+    assert!(pcap_reader.next().is_none());
+}
+
+/// Returns the index and the offset of a `ReadPacket` error
+fn location(err: &Error) -> (Option<u64>, u64) {
+
+    match *err.kind() {
+        ErrorKind::ReadPacket(index, offset) => (index, offset),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+}
+
+#[test]
+fn read_error_location() {
+
+    let index = PcapReader::new(Cursor::new(&DATA[..])).unwrap().index().unwrap();
+    let last = (Some(9), index.offset(9).unwrap());
+    let truncated = &DATA[..DATA.len() - 3];
+
+    // The peeked packets are only counted once
+    let mut pcap_reader = PcapReader::new(Cursor::new(truncated)).unwrap();
+    let mut buf = Vec::new();
+    for _ in 0..9 {
+        pcap_reader.peek_packet().unwrap().unwrap();
+        pcap_reader.next_into(&mut buf).unwrap().unwrap();
+    }
+    assert_eq!(location(&pcap_reader.peek_packet().unwrap().unwrap_err()), last);
+    assert_eq!(location(&pcap_reader.next_into(&mut buf).unwrap().unwrap_err()), last);
+
+    let mut pcap_reader = PcapReader::new(Cursor::new(truncated)).unwrap();
+    assert_eq!(pcap_reader.read_batch(9).unwrap().len(), 9);
+    assert_eq!(location(&pcap_reader.read_batch(9).unwrap_err()), last);
+
+    // The index follows the seeks to the indexed packets
+    let mut pcap_reader = PcapReader::new(Cursor::new(truncated)).unwrap();
+    pcap_reader.read_packet_at(&index, 8).unwrap();
+    assert_eq!(location(&pcap_reader.next().unwrap().unwrap_err()), last);
+    assert_eq!(location(&pcap_reader.read_packet_at(&index, 9).unwrap_err()), last);
+
+    // It is unknown after a seek to an arbitrary offset
+    let mut pcap_reader = PcapReader::new(Cursor::new(truncated)).unwrap();
+    pcap_reader.seek_to(last.1).unwrap();
+    assert_eq!(location(&pcap_reader.headers().last().unwrap().unwrap_err()), (None, last.1));
+
+    // And found again by index()
+    let mut pcap_reader = PcapReader::new(Cursor::new(truncated)).unwrap();
+    pcap_reader.seek_to(last.1).unwrap();
+    pcap_reader.set_lenient(true);
+    pcap_reader.index().unwrap();
+    pcap_reader.set_lenient(false);
+    assert_eq!(location(&pcap_reader.next().unwrap().unwrap_err()), last);
+}
+
+#[test]
+fn next_pooled() {
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+
+    let mut pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    let mut pool = PacketPool::new(2);
+
+    // Kept packets hold their buffers
+    let first = pcap_reader.next_pooled(&mut pool).unwrap().unwrap();
+    let second = pcap_reader.next_pooled(&mut pool).unwrap().unwrap();
+    let third = pcap_reader.next_pooled(&mut pool).unwrap().unwrap();
+    assert_eq!(first.as_packet(), expected[0]);
+    assert_eq!(second.data(), &expected[1].data[..]);
+    assert_eq!(pool.idle(), 0);
+
+    // At most 2 buffers are kept
+    drop((first, second, third));
+    assert_eq!(pool.idle(), 2);
+
+    // A dropped buffer is reused for the next packet
+    let ptr = {
+        let pcap = pcap_reader.next_pooled(&mut pool).unwrap().unwrap();
+        assert_eq!(pcap.header, expected[3].header);
+        pcap.data().as_ptr()
+    };
+    let pcap = pcap_reader.next_pooled(&mut pool).unwrap().unwrap();
+    assert_eq!(pcap.data().as_ptr(), ptr);
+    assert_eq!(pcap.into_packet(), expected[4]);
+    assert_eq!(pool.idle(), 1);
+
+    let mut count = 5;
+    while let Some(pcap) = pcap_reader.next_pooled(&mut pool) {
+        assert_eq!(pcap.unwrap().as_packet(), expected[count]);
+        count += 1;
+    }
+    assert_eq!(count, 10);
+
+    // A single buffer was needed for the packets dropped one after the other
+    assert_eq!(pool.idle(), 1);
+}
+
+#[test]
+fn lenient() {
+
+    // Truncated payload
+    let mut pcap_reader = PcapReader::new(&DATA[..DATA.len() - 3]).unwrap();
+    assert!(!pcap_reader.is_lenient());
+    pcap_reader.set_lenient(true);
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+    let packets: Vec<_> = pcap_reader.map(|pcap| pcap.unwrap()).collect();
+    assert_eq!(packets.len(), 10);
+    assert_eq!(&packets[..9], &expected[..9]);
+
+    let (last, expected_last) = (&packets[9], &expected[9]);
+    assert_eq!(last.header.incl_len, expected_last.header.incl_len - 3);
+    assert_eq!(last.header.orig_len, expected_last.header.orig_len);
+    assert_eq!(&last.data[..], &expected_last.data[..expected_last.data.len() - 3]);
+
+    // Truncated header
+    let first_len = expected[0].data.len();
+    let mut pcap_reader = PcapReader::new(&DATA[..24 + 16 + first_len + 5]).unwrap();
+    pcap_reader.set_lenient(true);
+    assert_eq!(pcap_reader.count(), 1);
+
+    let mut pcap_reader = PcapReader::new(&DATA[..24 + 16 + first_len + 5]).unwrap();
+    pcap_reader.set_lenient(true);
+    let mut buf = Vec::new();
+    assert!(pcap_reader.next_into(&mut buf).unwrap().is_ok());
+    assert!(pcap_reader.next_into(&mut buf).is_none());
+}
+
+#[test]
+fn is_eof() {
+
+    let mut pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+
+    // Nothing is consumed
+    assert!(!pcap_reader.is_eof().unwrap());
+    assert!(!pcap_reader.is_eof().unwrap());
+    assert_eq!(pcap_reader.bytes_read(), 24);
+
+    let mut count = 0;
+    while !pcap_reader.is_eof().unwrap() {
+        pcap_reader.next().unwrap().unwrap();
+        count += 1;
+    }
+    assert_eq!(count, 10);
+    assert!(pcap_reader.next().is_none());
+
+    // Only a global header
+    assert!(PcapReader::new(&DATA[..24]).unwrap().is_eof().unwrap());
+}
+
+#[test]
+fn trailing_bytes() {
+
+    let mut data = DATA.to_vec();
+    data.extend_from_slice(&[0xde, 0xad, 0xbe]);
+
+    // Strict mode
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    for _ in 0..10 {
+        pcap_reader.next().unwrap().unwrap();
+    }
+    assert!(matches!(*pcap_reader.next().unwrap().unwrap_err().root_kind(), ErrorKind::TruncatedPacket(16, 3)));
+
+    // Lenient mode
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    pcap_reader.set_lenient(true);
+    assert_eq!(pcap_reader.trailing_bytes(), 0);
+    for _ in 0..10 {
+        pcap_reader.next().unwrap().unwrap();
+    }
+    assert!(pcap_reader.next().is_none());
+    assert_eq!(pcap_reader.trailing_bytes(), 3);
+
+    // A clean end of stream has no trailing bytes
+    let mut pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    pcap_reader.set_lenient(true);
+    assert_eq!(pcap_reader.by_ref().count(), 10);
+    assert_eq!(pcap_reader.trailing_bytes(), 0);
+}
+
+#[test]
+fn fix_orig_len() {
+
+    // orig_len of the first packet set to 0
+    let mut data = DATA.to_vec();
+    data[24 + 12..24 + 16].copy_from_slice(&[0; 4]);
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    assert!(!pcap_reader.fixes_orig_len());
+    assert!(pcap_reader.next().unwrap().is_err());
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    pcap_reader.set_fix_orig_len(true);
+    let packets: Vec<_> = pcap_reader.map(|pcap| pcap.unwrap()).collect();
+    assert_eq!(packets[0].orig_len(), packets[0].incl_len());
+    assert_eq!(packets[0].data, expected[0].data);
+    assert_eq!(&packets[1..], &expected[1..]);
+
+    let mut header = PacketHeader::new(0, 0, 60);
+    assert!(!header.fix_orig_len());
+    header.orig_len = 10;
+    assert!(header.fix_orig_len());
+    assert_eq!(header.orig_len, 60);
+}
+
+#[test]
+fn max_packet_len_free_fns() {
+
+    // A jumbo packet over DEFAULT_MAX_PACKET_LEN
+    let len = DEFAULT_MAX_PACKET_LEN + 1;
+    let mut record = PacketHeader::new(0, 0, len).to_bytes(Endianness::Little).to_vec();
+    record.extend(vec![0u8; len as usize]);
+    let header = PcapHeader { magic_number: 0xd4c3b2a1, ..PcapHeader::default() };
+
+    match *Packet::from_reader::<_, LittleEndian>(&mut &record[..], TsResolution::MicroSecond).unwrap_err().kind() {
+        ErrorKind::PacketTooLarge(got, max) => assert_eq!((got, max), (len, DEFAULT_MAX_PACKET_LEN)),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+    assert!(Packet::parse_one(&header, &record).is_err());
+    assert!(read_packet::<_, LittleEndian>(&mut &record[..], TsResolution::MicroSecond).is_err());
+
+    // Unless the maximum is raised
+    let packet = Packet::from_reader_with_max::<_, LittleEndian>(&mut &record[..], TsResolution::MicroSecond, len).unwrap();
+    assert_eq!(packet.data.len(), len as usize);
+    assert_eq!(Packet::parse_one_with_max(&header, &record, len).unwrap().unwrap().1, record.len());
+    assert!(read_packet_with_max::<_, LittleEndian>(&mut &record[..], TsResolution::MicroSecond, len).unwrap().is_some());
+}
+
+#[test]
+fn read_packet_free_fn() {
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+
+    let mut reader = &DATA[24..];
+    let mut packets = Vec::new();
+    while let Some(packet) = read_packet::<_, LittleEndian>(&mut reader, TsResolution::MicroSecond).unwrap() {
+        packets.push(packet);
+    }
+    assert_eq!(packets, expected);
+    assert!(read_packet::<_, LittleEndian>(&mut reader, TsResolution::MicroSecond).unwrap().is_none());
+
+    // Nothing is read past the packet
+    let first_len = 16 + expected[0].data.len();
+    let mut data = DATA[24..24 + first_len].to_vec();
+    data.extend_from_slice(b"other");
+    let mut reader = &data[..];
+    assert_eq!(read_packet::<_, LittleEndian>(&mut reader, TsResolution::MicroSecond).unwrap().unwrap(), expected[0]);
+    assert_eq!(reader, b"other");
+
+    // Truncated packet header
+    let mut reader = &DATA[24..29];
+    let err = read_packet::<_, LittleEndian>(&mut reader, TsResolution::MicroSecond).unwrap_err();
+    assert!(matches!(*err.kind(), ErrorKind::TruncatedPacket(16, 5)));
+}
+
+#[test]
+fn swap_len_fields() {
+
+    // A packet of 100 bytes truncated to 10, its lengths written in the wrong order
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write_packet(&Packet::from_data(0, 0, Cow::Borrowed(&[7; 10][..]), 100).unwrap()).unwrap();
+    pcap_writer.write(0, 0, &[8; 20]).unwrap();
+    let good = pcap_writer.into_writer().unwrap();
+
+    let mut data = good.clone();
+    let (incl_len, orig_len) = data[24 + 8..24 + 16].split_at_mut(4);
+    incl_len.swap_with_slice(orig_len);
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    assert!(!pcap_reader.swaps_len_fields());
+    assert!(pcap_reader.next().unwrap().is_err());
+
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    pcap_reader.set_swap_len_fields(true);
+    let expected: Vec<_> = PcapReader::new(&good[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+    let packets: Vec<_> = pcap_reader.map(|pcap| pcap.unwrap()).collect();
+    assert_eq!(packets, expected);
+
+    // The raw records are repaired
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    pcap_reader.set_swap_len_fields(true);
+    let mut record = Vec::new();
+    pcap_reader.next_raw_into(&mut record).unwrap().unwrap();
+    assert_eq!(&record[..], &good[24..24 + 16 + 10]);
+
+    let mut header = PacketHeader::new(0, 0, 60);
+    assert!(!header.swap_len_fields());
+    header.orig_len = 10;
+    assert!(header.swap_len_fields());
+    assert_eq!((header.incl_len, header.orig_len), (10, 60));
+}
+
+#[test]
+fn headers() {
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap().header).collect();
+
+    let headers: Vec<_> = PcapReader::new(&DATA[..]).unwrap().headers().map(|header| header.unwrap()).collect();
+    assert_eq!(headers, expected);
+
+    let headers: Vec<_> = PcapReader::new(Cursor::new(&DATA[..])).unwrap().headers_seek().unwrap().map(|header| header.unwrap()).collect();
+    assert_eq!(headers, expected);
+
+    // A truncated last payload is an error, which ends the iteration
+    let truncated = &DATA[..DATA.len() - 3];
+    let results: Vec<_> = PcapReader::new(truncated).unwrap().headers().collect();
+    assert_eq!(results.len(), 10);
+    assert!(results[9].is_err());
+
+    let results: Vec<_> = PcapReader::new(Cursor::new(truncated)).unwrap().headers_seek().unwrap().collect();
+    assert_eq!(results.len(), 10);
+    match *results[9].as_ref().unwrap_err().root_kind() {
+        ErrorKind::TruncatedPacket(expected, got) => assert_eq!(expected - got, 3),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+
+    let mut pcap_reader = PcapReader::new(Cursor::new(truncated)).unwrap();
+    pcap_reader.set_lenient(true);
+    let last = pcap_reader.headers_seek().unwrap().last().unwrap().unwrap();
+    assert_eq!(last.incl_len, expected[9].incl_len - 3);
+}
+
+#[test]
+fn count_packets() {
+
+    assert_eq!(PcapReader::new(&DATA[..]).unwrap().count_packets().unwrap(), 10);
+    assert_eq!(PcapReader::new(Cursor::new(&DATA[..])).unwrap().count_packets_seek().unwrap(), 10);
+
+    let truncated = &DATA[..DATA.len() - 3];
+    assert!(PcapReader::new(truncated).unwrap().count_packets().is_err());
+
+    match *PcapReader::new(Cursor::new(truncated)).unwrap().count_packets_seek().unwrap_err().root_kind() {
+        ErrorKind::TruncatedPacket(expected, got) => assert_eq!(expected - got, 3),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+}
+
+#[test]
+fn summarize() {
+
+    let packets: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+    let lens: Vec<_> = packets.iter().map(|pcap| pcap.header.incl_len).collect();
+
+    let stats = PcapReader::new(&DATA[..]).unwrap().summarize().unwrap();
+    assert_eq!(stats, PcapReader::new(Cursor::new(&DATA[..])).unwrap().summarize_seek().unwrap());
+
+    assert_eq!(stats.packet_count, 10);
+    assert_eq!(stats.total_bytes, DATA.len() as u64 - 24 - 10 * 16);
+    assert_eq!(stats.min_len, *lens.iter().min().unwrap());
+    assert_eq!(stats.max_len, *lens.iter().max().unwrap());
+    assert_eq!(stats.first_ts, Some(packets[0].duration_since_epoch()));
+    assert_eq!(stats.last_ts, Some(packets[9].duration_since_epoch()));
+    assert_eq!(stats.duration(), packets[9].duration_since_epoch() - packets[0].duration_since_epoch());
+    assert_eq!(stats.avg_len(), stats.total_bytes as f64 / 10.0);
+
+    // Unsorted packets over 2 seconds
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write(12, 0, &[0u8; 60]).unwrap();
+    pcap_writer.write(10, 0, &[0u8; 100]).unwrap();
+    pcap_writer.write(11, 0, &[0u8; 20]).unwrap();
+    pcap_writer.write(11, 500_000, &[0u8; 60]).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let stats = PcapReader::new(&out[..]).unwrap().summarize().unwrap();
+    assert_eq!(stats.duration(), Duration::from_secs(2));
+    assert_eq!((stats.min_len, stats.max_len, stats.avg_len()), (20, 100, 60.0));
+    assert_eq!(stats.packets_per_second(), Some(2.0));
+
+    // No packet
+    let stats = PcapReader::new(&DATA[..24]).unwrap().summarize().unwrap();
+    assert_eq!(stats.packet_count, 0);
+    assert_eq!(stats.first_ts, None);
+    assert_eq!(stats.duration(), Duration::default());
+    assert_eq!(stats.packets_per_second(), None);
+
+    assert!(PcapReader::new(&DATA[..DATA.len() - 3]).unwrap().summarize().is_err());
+}
+
+#[test]
+fn arena_reader() {
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+    let payloads_len = DATA.len() - 24 - 10 * 16;
+
+    let arena = ArenaReader::new(PcapReader::new(&DATA[..]).unwrap(), payloads_len).unwrap();
+    assert_eq!(arena.len(), 10);
+    assert_eq!(arena.arena().len(), payloads_len);
+    assert_eq!(arena.iter().size_hint(), (10, Some(10)));
+    assert_eq!(arena.get(3).unwrap(), expected[3]);
+    assert!(arena.get(10).is_none());
+
+    for (pcap, expected) in arena.iter().zip(&expected) {
+        assert_eq!(&pcap, expected);
+        assert!(matches!(pcap.data, Cow::Borrowed(_)));
+    }
+
+    // The payloads don't fit
+    match *ArenaReader::new(PcapReader::new(&DATA[..]).unwrap(), payloads_len - 1).unwrap_err().root_kind() {
+        ErrorKind::ArenaFull(len, cap) => assert_eq!((len, cap), (payloads_len as u64, payloads_len - 1)),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+
+    // The truncated last packet is kept in lenient mode
+    let mut pcap_reader = PcapReader::new(&DATA[..DATA.len() - 3]).unwrap();
+    assert!(ArenaReader::new(PcapReader::new(&DATA[..DATA.len() - 3]).unwrap(), payloads_len).is_err());
+    pcap_reader.set_lenient(true);
+    let arena = ArenaReader::new(pcap_reader, payloads_len).unwrap();
+    assert_eq!(arena.arena().len(), payloads_len - 3);
+    assert_eq!((&arena).into_iter().last().unwrap().data.len(), expected[9].data.len() - 3);
+}
+
+#[test]
+fn concat() {
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+
+    let both = concat_bytes(&DATA[..], &DATA[..]).unwrap();
+    assert_eq!(both.len(), DATA.len() * 2 - 24);
+    assert_eq!(&both[..DATA.len()], &DATA[..]);
+
+    let packets: Vec<_> = PcapReader::new(&both[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+    assert_eq!(&packets[..10], &expected[..]);
+    assert_eq!(&packets[10..], &expected[..]);
+
+    // The snaplen is raised for the packets of b
+    let header = PcapReader::new(&DATA[..]).unwrap().header;
+    let mut pcap_writer = PcapWriter::with_header(PcapHeader { snaplen: 10, ..header }, Vec::new()).unwrap();
+    pcap_writer.write(0, 0, &[0; 10]).unwrap();
+    let short = pcap_writer.into_writer().unwrap();
+
+    let both = concat_bytes(&short, &DATA[..]).unwrap();
+    let pcap_reader = PcapReader::new(&both[..]).unwrap();
+    assert_eq!(pcap_reader.header.snaplen, header.snaplen);
+    let packets: Vec<_> = pcap_reader.map(|pcap| pcap.unwrap()).collect();
+    assert_eq!(&packets[1..], &expected[..]);
+
+    // Other datalink
+    let raw = PcapWriter::with_header(PcapHeader { datalink: DataLink::RAW, ..header }, Vec::new()).unwrap().into_writer().unwrap();
+    assert!(matches!(*concat_bytes(&DATA[..], &raw).unwrap_err().kind(), ErrorKind::IncompatibleHeaders(_)));
+
+    // Other endianness
+    let big = transcode(&DATA[..], Vec::new(), Endianness::Big).unwrap();
+    assert!(matches!(*concat_bytes(&DATA[..], &big).unwrap_err().kind(), ErrorKind::IncompatibleHeaders(_)));
+
+    assert!(concat_bytes(&DATA[..], &DATA[..10]).is_err());
+}
+
+#[test]
+fn transcode_endianness() {
+
+    let big = transcode(&DATA[..], Vec::new(), Endianness::Big).unwrap();
+    assert_eq!(big.len(), DATA.len());
+    assert_eq!(&big[..4], &[0xa1, 0xb2, 0xc3, 0xd4]);
+
+    let big_reader = PcapReader::new(&big[..]).unwrap();
+    let reader = PcapReader::new(&DATA[..]).unwrap();
+    assert_eq!(big_reader.endianness(), Endianness::Big);
+    assert_eq!((big_reader.header.snaplen, big_reader.header.datalink), (reader.header.snaplen, reader.header.datalink));
+    for (pcap, expected) in big_reader.zip(reader) {
+        assert_eq!(pcap.unwrap(), expected.unwrap());
+    }
+
+    // Back to little endian, and already in the target endianness
+    assert_eq!(transcode(&big[..], Vec::new(), Endianness::Little).unwrap(), &DATA[..]);
+    assert_eq!(transcode(&DATA[..], Vec::new(), Endianness::Little).unwrap(), &DATA[..]);
+
+    // The nanosecond resolution is kept
+    let mut pcap_writer = PcapWriter::new_nanos(Vec::new()).unwrap();
+    pcap_writer.write(1, 123_456_789, &[1, 2, 3]).unwrap();
+    let nanos = pcap_writer.into_writer().unwrap();
+    let little = transcode(&nanos[..], Vec::new(), Endianness::Little).unwrap();
+    assert_eq!(&little[..4], &[0x4d, 0x3c, 0xb2, 0xa1]);
+    assert_eq!(transcode(&little[..], Vec::new(), Endianness::Big).unwrap(), nanos);
+}
+
+#[test]
+fn unknown_magic() {
+
+    let err = PcapReader::new(&[0x0a, 0x0d, 0x0d, 0x0a, 0, 0, 0, 0][..]).unwrap_err();
+    assert!(matches!(*err.kind(), ErrorKind::UnknownMagic(0x0a0d0d0a)));
+    assert_eq!(err.to_string(), "Not a pcap file: got magic 0x0a0d0d0a, it looks like a pcapng file, read it with the PcapNgReader");
+
+    let err = PcapReader::new(&[0x1f, 0x8b, 8, 0, 0, 0, 0, 0][..]).unwrap_err();
+    assert!(err.to_string().contains("gzip"));
+
+    let err = PcapReader::new(&b"GMBU\x00\x02\x00\x00"[..]).unwrap_err();
+    assert_eq!(err.to_string(), "Not a pcap file: got magic 0x474d4255, it looks like a Microsoft Network Monitor capture, convert it to pcap first");
+
+    let err = PcapReader::new(&b"snoop\0\0\0\0\0\0\x02\0\0\0\x04"[..]).unwrap_err();
+    assert!(err.to_string().contains("snoop"));
+
+    let err = PcapReader::new(&[0x12, 0x34, 0x56, 0x78, 0, 0, 0, 0][..]).unwrap_err();
+    assert_eq!(err.to_string(), "Not a pcap file: got magic 0x12345678");
+}
+
+#[test]
+fn from_parts() {
+
+    let header = PcapReader::new(&DATA[..]).unwrap().header;
+
+    assert_eq!(PcapReader::from_parts(&DATA[24..], header).unwrap().count(), 10);
+
+    let expected = PcapReader::new(&DATA[..]).unwrap();
+    for (pcap, expected) in PcapReader::from_parts(&DATA[24..], header).unwrap().zip(expected) {
+        assert_eq!(pcap.unwrap().data, expected.unwrap().data);
+    }
+
+    let err = PcapReader::from_parts(&DATA[24..], PcapHeader { magic_number: 0x12345678, ..header }).unwrap_err();
+    assert!(matches!(*err.kind(), ErrorKind::UnknownMagic(0x12345678)));
+    assert_eq!(PcapReader::from_parts(&DATA[24..], PcapHeader { snaplen: 0, ..header }).unwrap().snaplen(), 65535);
+
+    let err = PcapReader::from_parts(&DATA[24..], PcapHeader { snaplen: DEFAULT_MAX_PACKET_LEN + 1, ..header }).unwrap_err();
+    assert!(matches!(*err.kind(), ErrorKind::InvalidSnaplen(..)));
+
+    // The header can be read from another stream than the packet records
+    let header = PcapHeader::from_reader(&mut &DATA[..24]).unwrap();
+    assert_eq!(PcapReader::with_external_header(&DATA[24..], header).unwrap().count(), 10);
+    assert!(PcapReader::with_external_header(&DATA[24..], PcapHeader { snaplen: u32::MAX, ..header }).is_err());
+}
+
+#[test]
+fn write_unknown_magic() {
+
+    let header = PcapHeader { magic_number: 0x12345678, ..PcapHeader::default() };
+
+    // Nothing is written
+    let mut out = Vec::new();
+    let err = PcapWriter::with_header(header, &mut out).unwrap_err();
+    assert!(matches!(*err.kind(), ErrorKind::UnknownMagic(0x12345678)));
+    assert!(out.is_empty());
+}
+
+#[test]
+fn headers_and_data() {
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+
+    let records: Vec<_> = PcapReader::new(&DATA[..]).unwrap().headers_and_data().map(|record| record.unwrap()).collect();
+    let slice_records: Vec<_> = PcapSliceReader::new(&DATA[..]).unwrap().headers_and_data().map(|record| record.unwrap()).collect();
+    assert_eq!(records.len(), 10);
+    assert_eq!(slice_records.len(), 10);
+
+    for ((expected, (header, data)), (slice_header, slice_data)) in expected.iter().zip(records).zip(slice_records) {
+
+        assert_eq!(header.ts_sec, expected.header.ts_sec);
+        assert_eq!(slice_header.incl_len, expected.header.incl_len);
+        assert_eq!(&data[..], &expected.data[..]);
+        assert_eq!(slice_data, &expected.data[..]);
+    }
+}
+
+#[test]
+fn read_batch() {
+
+    let mut pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+
+    let batches: Vec<_> = (0..4).map(|_| pcap_reader.read_batch(4).unwrap().len()).collect();
+    assert_eq!(batches, vec![4, 4, 2, 0]);
+
+    let mut pcap_reader = PcapReader::new(&DATA[..DATA.len() - 3]).unwrap();
+    assert_eq!(pcap_reader.read_batch(8).unwrap().len(), 8);
+
+    match *pcap_reader.read_batch(8).unwrap_err().root_kind() {
+        ErrorKind::TruncatedPacket(_, _) => {},
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+}
+
+#[test]
+fn size_hint() {
+
+    assert_eq!(PcapReader::new(&DATA[..]).unwrap().size_hint(), (0, None));
+
+    let mut pcap_reader = PcapReader::new_seek(Cursor::new(&DATA[..])).unwrap();
+    assert_eq!(pcap_reader.size_hint(), (1, Some((DATA.len() - 24).div_ceil(16))));
+
+    let packets = pcap_reader.by_ref().count();
+    assert!(packets <= (DATA.len() - 24).div_ceil(16));
+    assert_eq!(pcap_reader.size_hint(), (0, Some(0)));
+
+    // Stray bytes after the last packet, no more item in lenient mode
+    let stray = [DATA, &[0; 3][..]].concat();
+    let mut pcap_reader = PcapReader::new_seek(Cursor::new(&stray[..])).unwrap();
+    pcap_reader.set_lenient(true);
+    assert_eq!(pcap_reader.by_ref().take(10).count(), 10);
+    assert_eq!(pcap_reader.size_hint(), (0, Some(1)));
+    assert!(pcap_reader.next().is_none());
+
+    let mut limit = PcapReader::new_seek(Cursor::new(&stray[..])).unwrap().limit(20);
+    assert_eq!(limit.by_ref().take(10).count(), 10);
+    assert_eq!(limit.size_hint().0, 1);
+
+    let mut pcap_reader = limit.into_inner();
+    pcap_reader.set_lenient(true);
+    let limit = pcap_reader.limit(20);
+    assert_eq!(limit.size_hint().0, 0);
+    assert_eq!(limit.count(), 0);
+
+    // An error is still an item in strict mode
+    let mut pcap_reader = PcapReader::new_seek(Cursor::new(&stray[..])).unwrap();
+    assert_eq!(pcap_reader.by_ref().take(10).count(), 10);
+    assert_eq!(pcap_reader.size_hint(), (1, Some(1)));
+    assert!(pcap_reader.next().unwrap().is_err());
+}
+
+#[test]
+fn read_write_modified() {
+
+    let mut pcap_writer = PcapWriterBuilder::new().modified(true).endianness(Endianness::Little).build(Vec::new()).unwrap();
+
+    let data = [1u8, 2, 3, 4];
+    let mut packet = Packet::new(1, 2, 4, &data);
+    packet.header.ifindex = Some(3);
+    packet.header.protocol = Some(0x0800);
+    packet.header.pkt_type = Some(4);
+    pcap_writer.write_packet(&packet).unwrap();
+
+    let out = pcap_writer.into_writer().unwrap();
+    assert_eq!(out.len(), 24 + 24 + 4);
+
+    let mut pcap_reader = PcapReader::new(&out[..]).unwrap();
+    assert_eq!(pcap_reader.header.magic_number, 0x34cdb2a1);
+    assert!(pcap_reader.header.is_modified());
+
+    let pcap = pcap_reader.next().unwrap().unwrap();
+    assert_eq!(pcap.header.ifindex, Some(3));
+    assert_eq!(pcap.header.protocol, Some(0x0800));
+    assert_eq!(pcap.header.pkt_type, Some(4));
+    assert_eq!(&pcap.data[..], &data[..]);
+    assert!(pcap_reader.next().is_none());
+
+    let pcap = PcapSliceReader::new(&out).unwrap().next().unwrap().unwrap();
+    assert_eq!(pcap.header.ifindex, Some(3));
+    assert_eq!(&pcap.data[..], &data[..]);
+
+    // The standard format has none of these fields
+    let pcap = PcapReader::new(&DATA[..]).unwrap().next().unwrap().unwrap();
+    assert_eq!(pcap.header.ifindex, None);
+    assert_eq!(pcap.header.protocol, None);
+    assert_eq!(pcap.header.pkt_type, None);
+
+    assert!(PcapWriterBuilder::new().modified(true).ts_resolution(TsResolution::NanoSecond).build(Vec::new()).is_err());
+}
+
+#[test]
+fn with_capacity() {
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap().data).collect();
+
+    // The capacity doesn't change what is peeked and read
+    for &capacity in &[1, 100, 64 * 1024] {
+
+        let mut pcap_reader = PcapReader::with_capacity(&DATA[..], capacity).unwrap();
+        for expected in &expected {
+
+            assert_eq!(pcap_reader.peek_packet().unwrap().unwrap().incl_len as usize, expected.len());
+            assert_eq!(&pcap_reader.next().unwrap().unwrap().data, expected);
+        }
+
+        assert!(pcap_reader.peek_packet().is_none());
+        assert!(pcap_reader.next().is_none());
+    }
+}
+
+#[test]
+fn peek_packet() {
+
+    let mut pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+
+    for expected in expected {
+
+        // Peeking twice doesn't consume anything
+        assert_eq!(pcap_reader.peek_packet().unwrap().unwrap().incl_len, expected.header.incl_len);
+        let offset = pcap_reader.offset();
+        assert_eq!(pcap_reader.peek_packet().unwrap().unwrap().incl_len, expected.header.incl_len);
+        assert_eq!(pcap_reader.offset(), offset);
+
+        let pcap = pcap_reader.next().unwrap().unwrap();
+        assert_eq!(pcap.data, expected.data);
+    }
+
+    assert!(pcap_reader.peek_packet().is_none());
+    assert!(pcap_reader.next().is_none());
+
+    // A truncated packet can't be peeked
+    let mut pcap_reader = PcapReader::new(&DATA[..DATA.len() - 3]).unwrap();
+    for _ in 0..9 {
+        assert!(pcap_reader.next().unwrap().is_ok());
+    }
+    assert!(pcap_reader.peek_packet().unwrap().is_err());
+
+    // Unless the reader is lenient, it is peeked as read
+    for data in [&DATA[..DATA.len() - 3], &DATA[..24 + 16 + 3]] {
+
+        let mut pcap_reader = PcapReader::new(data).unwrap();
+        pcap_reader.set_lenient(true);
+        while let Some(header) = pcap_reader.peek_packet() {
+
+            let incl_len = header.unwrap().incl_len;
+            assert_eq!(pcap_reader.next().unwrap().unwrap().header.incl_len, incl_len);
+        }
+        assert!(pcap_reader.next().is_none());
+    }
+
+    // A truncated packet header at the end is ignored in lenient mode
+    let mut pcap_reader = PcapReader::new(&DATA[..24 + 5]).unwrap();
+    pcap_reader.set_lenient(true);
+    assert!(pcap_reader.peek_packet().is_none());
+    assert_eq!(pcap_reader.trailing_bytes(), 5);
+}
+
+#[test]
+fn clone_reader() {
+
+    let mut pcap_reader = PcapReader::new(Cursor::new(&DATA[..])).unwrap();
+    pcap_reader.next().unwrap().unwrap();
+    pcap_reader.peek_packet().unwrap().unwrap();
+
+    // The peeked packet is copied along with the position
+    let clone = pcap_reader.clone();
+    let packets: Vec<_> = pcap_reader.map(|pcap| pcap.unwrap().into_owned()).collect();
+    let cloned: Vec<_> = clone.map(|pcap| pcap.unwrap().into_owned()).collect();
+
+    assert_eq!(packets.len(), 9);
+    assert_eq!(packets.iter().map(|p| &p.data).collect::<Vec<_>>(), cloned.iter().map(|p| &p.data).collect::<Vec<_>>());
+}
+
+#[test]
+fn bytes_read() {
+
+    let mut pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    assert_eq!(pcap_reader.bytes_read(), 24);
+
+    let first = pcap_reader.peek_packet().unwrap().unwrap().incl_len as u64;
+    assert_eq!(pcap_reader.bytes_read(), 24);
+
+    pcap_reader.next().unwrap().unwrap();
+    assert_eq!(pcap_reader.bytes_read(), 24 + 16 + first);
+
+    for pcap in pcap_reader.by_ref() {
+        pcap.unwrap();
+    }
+    assert_eq!(pcap_reader.bytes_read(), DATA.len() as u64);
+}
+
+#[test]
+fn max_packet_len() {
+
+    let mut pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    let first_len = pcap_reader.peek_packet().unwrap().unwrap().incl_len;
+
+    pcap_reader.set_max_packet_len(first_len - 1);
+    match *pcap_reader.next().unwrap().unwrap_err().root_kind() {
+        ErrorKind::PacketTooLarge(len, max) => assert_eq!((len, max), (first_len, first_len - 1)),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+
+    // A huge length is refused before any allocation
+    let mut data = DATA[..24].to_vec();
+    data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xf0, 0xff, 0xff, 0xff, 0xf0]);
+    let mut pcap_reader = PcapReader::new(&data[..]).unwrap();
+    match *pcap_reader.next().unwrap().unwrap_err().root_kind() {
+        ErrorKind::PacketTooLarge(_, max) => assert_eq!(max, DEFAULT_MAX_PACKET_LEN),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+
+    // Packets longer than 0xFFFF are accepted
+    let data = vec![0u8; 100_000];
+    let mut pcap_writer = PcapWriterBuilder::new().snaplen(262_144).build(Vec::new()).unwrap();
+    pcap_writer.write(0, 0, &data).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+    assert_eq!(PcapReader::new(&out[..]).unwrap().next().unwrap().unwrap().data.len(), 100_000);
+}
+
+#[test]
+fn write_tuples() {
+
+    let packets = vec![
+        (Duration::new(1, 123_456_789), vec![1u8; 10]),
+        (Duration::new(2, 999_999_999), vec![2u8; 20])
+    ];
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write_tuples(packets.clone()).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    // Truncated to the microsecond
+    let read: Vec<_> = PcapReader::new(&out[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+    assert_eq!(read[0].header, PacketHeader::new(1, 123_456, 10));
+    assert_eq!(read[1].header, PacketHeader::new(2, 999_999, 20));
+    assert_eq!(read[1].data, packets[1].1);
+
+    let mut pcap_writer = PcapWriter::new_nanos(Vec::new()).unwrap();
+    pcap_writer.write_tuples(packets.clone()).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    let read: Vec<_> = PcapReader::new(&out[..]).unwrap().map(|pcap| pcap.unwrap().duration_since_epoch()).collect();
+    assert_eq!(read, vec![packets[0].0, packets[1].0]);
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    assert!(pcap_writer.write_tuples(vec![(Duration::from_secs(1 << 32), vec![])]).is_err());
+}
+
+#[test]
+fn copy_from() {
+
+    let pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    let mut pcap_writer = PcapWriter::with_header(pcap_reader.header, Vec::new()).unwrap();
+    pcap_writer.copy_from(pcap_reader).unwrap();
+    assert_eq!(&pcap_writer.into_writer().unwrap()[..], &DATA[..]);
+
+    let packets = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap());
+    let mut pcap_writer = PcapWriter::with_endianness(Endianness::Little, Vec::new()).unwrap();
+    pcap_writer.write_all(packets).unwrap();
+    assert_eq!(PcapReader::new(&pcap_writer.into_writer().unwrap()[..]).unwrap().count(), 10);
+
+    // The test file is in LittleEndian
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    assert!(pcap_writer.copy_from(PcapReader::new(&DATA[..]).unwrap()).is_err());
+    assert_eq!(pcap_writer.into_writer().unwrap().len(), 24);
+
+    let mut pcap_writer = PcapWriterBuilder::new().endianness(Endianness::Little).datalink(DataLink::RAW).build(Vec::new()).unwrap();
+    assert!(pcap_writer.copy_from(PcapReader::new(&DATA[..]).unwrap()).is_err());
+}
+
+#[test]
+fn raw_records() {
+
+    let mut pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    let mut pcap_writer = PcapWriter::with_header(pcap_reader.header, Vec::new()).unwrap();
+
+    let mut record = Vec::new();
+    let mut offset = 24;
+    while let Some(header) = pcap_reader.next_raw_into(&mut record) {
+
+        let header = header.unwrap();
+        assert_eq!(record.len(), 16 + header.incl_len as usize);
+        assert_eq!(&record[..], &DATA[offset..offset + record.len()]);
+        offset += record.len();
+
+        pcap_writer.write_raw_record(&record).unwrap();
+    }
+    assert_eq!(&pcap_writer.into_writer().unwrap()[..], &DATA[..]);
+
+    // The lengths fixed by the reader are written back into the record
+    let mut pcap_reader = PcapReader::new(&DATA[..DATA.len() - 3]).unwrap();
+    pcap_reader.set_lenient(true);
+    let mut pcap_writer = PcapWriter::with_header(pcap_reader.header, Vec::new()).unwrap();
+    while let Some(header) = pcap_reader.next_raw_into(&mut record) {
+        header.unwrap();
+        pcap_writer.write_raw_record(&record).unwrap();
+    }
+    let out = pcap_writer.into_writer().unwrap();
+    assert_eq!(out.len(), DATA.len() - 3);
+    let last = PcapReader::new(&out[..]).unwrap().last().unwrap().unwrap();
+    assert!(last.is_truncated());
+
+    // The length of the record must match its header
+    let mut pcap_writer = PcapWriter::with_header(PcapReader::new(&DATA[..]).unwrap().header, Vec::new()).unwrap();
+    assert!(pcap_writer.write_raw_record(&DATA[24..39]).is_err());
+    assert!(pcap_writer.write_raw_record(&record[..record.len() - 1]).is_err());
+    assert_eq!(pcap_writer.into_writer().unwrap().len(), 24);
+
+    // The records longer than the snaplen of the writer are truncated
+    let header = PcapReader::new(&DATA[..]).unwrap().header;
+    let mut pcap_writer = PcapWriter::with_header(PcapHeader { snaplen: 60, ..header }, Vec::new()).unwrap();
+    pcap_writer.copy_from(PcapReader::new(&DATA[..]).unwrap()).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+    for (pcap, expected) in PcapReader::new(&out[..]).unwrap().zip(PcapReader::new(&DATA[..]).unwrap()) {
+        let (pcap, expected) = (pcap.unwrap(), expected.unwrap());
+        assert_eq!(&pcap.data[..], &expected.data[..expected.data.len().min(60)]);
+    }
+}
+
+#[test]
+fn timestamp_utc() {
+
+    let packet = Packet::new(10_000, 500, 0, &[]);
+
+    let header = PcapHeader::default();
+    assert_eq!(packet.timestamp_utc(&header), packet.timestamp());
+
+    let header = PcapHeader { ts_correction: 3600, ..PcapHeader::default() };
+    assert_eq!(packet.timestamp_utc(&header), UNIX_EPOCH + Duration::new(6400, 500_000));
+
+    let header = PcapHeader { ts_correction: -3600, ..PcapHeader::default() };
+    assert_eq!(packet.timestamp_utc(&header), UNIX_EPOCH + Duration::new(13_600, 500_000));
+}
+
+#[test]
+fn packet_constructors() {
+
+    let packet = Packet::from_data(1, 2, Cow::Borrowed(&[0u8; 10][..]), 20).unwrap();
+    assert_eq!((packet.incl_len(), packet.orig_len()), (10, 20));
+    assert!(Packet::from_data(1, 2, Cow::Owned(vec![0u8; 10]), 5).is_err());
+
+    let packet = Packet::from_duration(Duration::new(3, 4_567_000), vec![0u8; 10]).unwrap();
+    assert_eq!((packet.header.ts_sec, packet.header.ts_usec), (3, 4_567));
+    assert_eq!((packet.incl_len(), packet.orig_len()), (10, 10));
+    assert_eq!(packet.duration_since_epoch(), Duration::new(3, 4_567_000));
+
+    assert!(Packet::from_duration(Duration::from_secs(1 << 40), Vec::new()).is_err());
+}
+
+#[test]
+fn payload_after_link() {
+
+    let data: Vec<u8> = (0..20).collect();
+    let packet = Packet::new(0, 0, 20, &data);
+
+    assert_eq!(packet.payload_after_link(DataLink::ETHERNET), Some(&data[14..]));
+    assert_eq!(packet.payload_after_link(DataLink::LINUX_SLL2), Some(&[][..]));
+    assert_eq!(packet.payload_after_link(DataLink::RAW), Some(&data[..]));
+
+    // Truncated link header or unknown link header length
+    let packet = Packet::new(0, 0, 20, &data[..10]);
+    assert_eq!(packet.payload_after_link(DataLink::ETHERNET), None);
+    assert_eq!(packet.payload_after_link(DataLink::IEEE802_11_RADIOTAP), None);
+}
+
+#[test]
+fn packet_eq() {
+
+    let pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    let header = pcap_reader.header;
+    let packets: Vec<_> = pcap_reader.map(|pcap| pcap.unwrap()).collect();
+
+    let mut pcap_writer = PcapWriter::with_header(header, Vec::new()).unwrap();
+    pcap_writer.write_all(packets.iter().cloned()).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    // Owned packets compared with borrowed ones
+    let pcap_reader = PcapSliceReader::new(&out).unwrap();
+    assert_eq!(pcap_reader.header, header);
+    assert_eq!(pcap_reader.map(|pcap| pcap.unwrap()).collect::<Vec<_>>(), packets);
+
+    let data = [1u8, 2, 3];
+    let packet = Packet::new(1, 2, 3, &data);
+    assert_eq!(packet, packet.to_owned());
+    assert_ne!(packet, Packet::new(1, 3, 3, &data));
+    assert_ne!(packet, Packet::new(1, 2, 3, &data[..2]));
+}
+
+#[test]
+fn packet_debug() {
+
+    let data: Vec<u8> = (0..100).collect();
+    let debug = format!("{:?}", Packet::new(1, 2, 100, &data));
+
+    assert!(debug.contains("incl_len: 100"));
+    assert!(debug.contains("[00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f ..] (100 bytes)"));
+
+    let debug = format!("{:?}", Packet::new(1, 2, 3, &data[..3]));
+    assert!(debug.contains("[00 01 02] (3 bytes)"));
+}
+
+#[test]
+fn variable_link_headers() {
+
+    let mut packet = vec![0, 0, 12, 0, 0x04, 0, 0, 0, 0x02, 0, 0, 0];
+    packet.extend_from_slice(&[0x80, 0, 0, 0]);
+
+    assert_eq!(radiotap_len(&packet), Some(12));
+    assert_eq!(ppi_len(&packet), Some(12));
+
+    // Truncated captures
+    assert_eq!(radiotap_len(&packet[..11]), None);
+    assert_eq!(ppi_len(&packet[..3]), None);
+
+    // Invalid version or length
+    packet[0] = 1;
+    assert_eq!(radiotap_len(&packet), None);
+    packet[0] = 0;
+    packet[2] = 4;
+    assert_eq!(ppi_len(&packet), None);
+}
+
+#[test]
+fn loopback_headers() {
+
+    // AF_INET6 as written by a little endian and a big endian host
+    let little = [30, 0, 0, 0, 0x60];
+    let big = [0, 0, 0, 30, 0x60];
+
+    assert_eq!(DataLink::NULL.header_len(), Some(4));
+    assert_eq!(DataLink::LOOP.header_len(), Some(4));
+
+    assert_eq!(loopback_af(&little, DataLink::NULL, Endianness::Little), Some(30));
+    assert_eq!(loopback_af(&big, DataLink::NULL, Endianness::Big), Some(30));
+
+    // LOOP is always in network byte order
+    assert_eq!(loopback_af(&big, DataLink::LOOP, Endianness::Little), Some(30));
+    assert_eq!(loopback_af(&big, DataLink::LOOP, Endianness::Big), Some(30));
+
+    assert_eq!(loopback_af(&little[..3], DataLink::NULL, Endianness::Little), None);
+    assert_eq!(loopback_af(&little, DataLink::ETHERNET, Endianness::Little), None);
+}
+
+#[test]
+fn owned_packets() {
+
+    let packets: Vec<Packet<'static>> = {
+
+        let data = DATA.to_vec();
+        PcapSliceReader::new(&data).unwrap()
+            .map(|pcap| {
+
+                let pcap = pcap.unwrap();
+                assert!(matches!(pcap.data, Cow::Borrowed(_)));
+
+                let owned = pcap.to_owned();
+                assert_eq!(owned.data, pcap.data);
+
+                pcap.into_owned()
+            })
+            .collect()
+    };
+
+    assert_eq!(packets.len(), 10);
+    assert!(packets.iter().all(|packet| matches!(packet.data, Cow::Owned(_))));
+}
+
+/// Writer counting its flushes
+struct Flushes<'a>(&'a std::cell::Cell<usize>);
+
+impl<'a> std::io::Write for Flushes<'a> {
+
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.set(self.0.get() + 1);
+        Ok(())
+    }
+}
+
+#[test]
+fn flush() {
+
+    let flushes = std::cell::Cell::new(0);
+
+    let mut pcap_writer = PcapWriter::new(Flushes(&flushes)).unwrap();
+    pcap_writer.flush().unwrap();
+    assert_eq!(flushes.get(), 1);
+
+    // into_writer flushes once, and the writer isn't flushed again when the PcapWriter is dropped
+    let _writer = pcap_writer.into_writer().unwrap();
+    assert_eq!(flushes.get(), 2);
+
+    let pcap_writer = PcapWriter::new(Flushes(&flushes)).unwrap();
+    drop(pcap_writer);
+    assert_eq!(flushes.get(), 3);
+}
+
+#[test]
+fn writer_accessors() {
+
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    assert!(pcap_writer.get_ref().is_empty());
+
+    pcap_writer.write(0, 0, &[0u8; 10]).unwrap();
+    pcap_writer.flush().unwrap();
+    assert_eq!(pcap_writer.get_ref().len(), 24 + 16 + 10);
+
+    pcap_writer.get_mut().truncate(24);
+    let out = pcap_writer.into_writer().unwrap();
+    assert_eq!(PcapReader::new(&out[..]).unwrap().count(), 0);
+}
+
+#[test]
+fn snaplen_check() {
+
+    let capture = |snaplen| {
+        let header = PcapHeader { snaplen, ..PcapHeader::default() };
+        PcapWriter::with_header(header, Vec::new()).unwrap().into_writer().unwrap()
+    };
+
+    // 0 is kept and means 65535
+    let unlimited = capture(0);
+    let pcap_reader = PcapReader::new(&unlimited[..]).unwrap();
+    assert_eq!(pcap_reader.header.raw_snaplen(), 0);
+    assert_eq!(pcap_reader.snaplen(), 65535);
+
+    let strict = SnaplenCheck { strict: true, ..SnaplenCheck::default() };
+    match *PcapReader::with_snaplen_check(&unlimited[..], strict).unwrap_err().kind() {
+        ErrorKind::InvalidSnaplen(0, max) => assert_eq!(max, DEFAULT_MAX_PACKET_LEN),
+        ref kind => panic!("Unexpected error {:?}", kind)
+    }
+
+    let huge = capture(DEFAULT_MAX_PACKET_LEN + 1);
+    assert!(PcapReader::new(&huge[..]).is_err());
+    assert!(PcapSliceReader::new(&huge[..]).is_err());
+
+    let lenient = SnaplenCheck { max: u32::MAX, ..SnaplenCheck::default() };
+    assert_eq!(PcapReader::with_snaplen_check(&huge[..], lenient).unwrap().header.snaplen, DEFAULT_MAX_PACKET_LEN + 1);
+}
+
+#[test]
+fn reader_header_accessors() {
+
+    let pcap_reader = PcapReader::new(&DATA[..]).unwrap();
+    assert_eq!(pcap_reader.snaplen(), pcap_reader.header.snaplen);
+    assert_eq!(pcap_reader.datalink(), DataLink::ETHERNET);
+    assert_eq!(pcap_reader.endianness(), Endianness::Little);
+    assert_eq!(pcap_reader.ts_resolution(), TsResolution::MicroSecond);
+}
+
+#[test]
+fn header_fields() {
+
+    let header = PcapReader::new(&DATA[..]).unwrap().header;
+    assert_eq!(header.magic(), 0xd4c3b2a1);
+    assert_eq!(header.version(), (2, 4));
+    assert_eq!(header.thiszone(), 0);
+    assert_eq!(header.sigfigs(), 0);
+
+    let pcap_writer = PcapWriterBuilder::new().thiszone(-3600).sigfigs(6).build(Vec::new()).unwrap();
+    let data = pcap_writer.into_writer().unwrap();
+
+    let header = PcapReader::new(&data[..]).unwrap().header;
+    assert_eq!(header.thiszone(), -3600);
+    assert_eq!(header.sigfigs(), 6);
+}
+
+#[test]
+fn append() {
+
+    let mut pcap_writer = PcapWriterBuilder::new().endianness(Endianness::Little).datalink(DataLink::RAW).build(Cursor::new(Vec::new())).unwrap();
+    pcap_writer.write(1, 0, &[1u8; 10]).unwrap();
+    let cursor = pcap_writer.into_writer().unwrap();
+
+    let mut pcap_writer = PcapWriter::append(cursor).unwrap();
+    assert_eq!(pcap_writer.header.endianness(), Endianness::Little);
+    assert_eq!(pcap_writer.header.datalink, DataLink::RAW);
+    pcap_writer.write(2, 0, &[2u8; 20]).unwrap();
+
+    // Packets of another datalink are refused
+    assert!(pcap_writer.copy_from(PcapReader::new(&DATA[..]).unwrap()).is_err());
+
+    let data = pcap_writer.into_writer().unwrap().into_inner();
+    let packets: Vec<_> = PcapReader::new(&data[..]).unwrap().map(|pcap| pcap.unwrap().data.into_owned()).collect();
+    assert_eq!(packets, vec![vec![1u8; 10], vec![2u8; 20]]);
+
+    assert!(PcapWriter::append(Cursor::new(vec![0u8; 24])).is_err());
+}
+
+#[test]
+fn append_checks_file() {
+
+    let mut pcap_writer = PcapWriterBuilder::new().datalink(DataLink::RAW).build(Cursor::new(Vec::new())).unwrap();
+    pcap_writer.write(1, 0, &[1u8; 10]).unwrap();
+    let data = pcap_writer.into_writer().unwrap().into_inner();
+
+    // The datalink is only checked by append_with_datalink
+    assert!(PcapWriter::append_with_datalink(Cursor::new(data.clone()), DataLink::RAW).is_ok());
+    assert!(PcapWriter::append(Cursor::new(data.clone())).is_ok());
+    let err = PcapWriter::append_with_datalink(Cursor::new(data.clone()), DataLink::ETHERNET).err().unwrap();
+    match *err.kind() {
+        ErrorKind::IncompatibleHeaders(_) => {},
+        ref kind => panic!("unexpected error {:?}", kind)
+    }
+
+    // A packet appended after stray bytes or a truncated record couldn't be read
+    let mut stray = data.clone();
+    stray.extend_from_slice(&[0u8; 5]);
+    assert!(PcapWriter::append(Cursor::new(stray)).is_err());
+    assert!(PcapWriter::append(Cursor::new(data[..data.len() - 1].to_vec())).is_err());
+}
+
+#[test]
+fn append_keeps_snaplen() {
+
+    let mut pcap_writer = PcapWriterBuilder::new().snaplen(0).build(Cursor::new(Vec::new())).unwrap();
+    pcap_writer.write(1, 0, &[1u8; 100]).unwrap();
+    let cursor = pcap_writer.into_writer().unwrap();
+
+    // Nothing appended, the stored snaplen of 0 is kept
+    let pcap_writer = PcapWriter::append(cursor).unwrap();
+    let cursor = pcap_writer.finalize_with_snaplen_fixup().unwrap();
+    assert_eq!(PcapReader::new(&cursor.get_ref()[..]).unwrap().header.snaplen, 0);
+
+    // The new snaplen still fits the packets already in the file
+    let mut pcap_writer = PcapWriter::append(cursor).unwrap();
+    pcap_writer.write(2, 0, &[2u8; 10]).unwrap();
+    let data = pcap_writer.finalize_with_snaplen_fixup().unwrap().into_inner();
+
+    let pcap_reader = PcapReader::new(&data[..]).unwrap();
+    assert_eq!(pcap_reader.header.snaplen, 100);
+    let packets: Vec<_> = pcap_reader.map(|pcap| pcap.unwrap().data.into_owned()).collect();
+    assert_eq!(packets, vec![vec![1u8; 100], vec![2u8; 10]]);
+}
+
+#[test]
+fn snaplen_fixup() {
+
+    for &endianness in &[Endianness::Big, Endianness::Little] {
+
+        let mut pcap_writer = PcapWriterBuilder::new().endianness(endianness).build(Cursor::new(Vec::new())).unwrap();
+        pcap_writer.write(1, 0, &[0u8; 10]).unwrap();
+        pcap_writer.write(2, 0, &[0u8; 42]).unwrap();
+        pcap_writer.write(3, 0, &[0u8; 20]).unwrap();
+
+        // The writer is moved back to the end of the data
+        let cursor = pcap_writer.finalize_with_snaplen_fixup().unwrap();
+        assert_eq!(cursor.position(), cursor.get_ref().len() as u64);
+
+        let data = cursor.into_inner();
+        let pcap_reader = PcapReader::new(&data[..]).unwrap();
+        assert_eq!(pcap_reader.header.snaplen, 42);
+        assert_eq!(pcap_reader.map(|pcap| pcap.unwrap().header.incl_len).collect::<Vec<_>>(), vec![10, 42, 20]);
+    }
+
+    // Without packets the snaplen is kept
+    let pcap_writer = PcapWriter::new(Cursor::new(Vec::new())).unwrap();
+    let data = pcap_writer.finalize_with_snaplen_fixup().unwrap().into_inner();
+    assert_eq!(PcapReader::new(&data[..]).unwrap().header.snaplen, 65535);
+}
+
+/// Writer counting the calls to write
+struct CountingWriter {
+    data: Vec<u8>,
+    writes: usize
+}
+
+impl Write for CountingWriter {
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writes += 1;
+        self.data.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn buffered_writer() {
+
+    let write = |mut pcap_writer: PcapWriter<CountingWriter>| {
+        for pcap in PcapReader::new(&DATA[..]).unwrap() {
+            pcap_writer.write_packet(&pcap.unwrap()).unwrap();
+        }
+        pcap_writer.into_writer().unwrap()
+    };
+
+    let header = PcapReader::new(&DATA[..]).unwrap().header;
+    let unbuffered = write(PcapWriterBuilder::new().endianness(Endianness::Little).snaplen(header.snaplen).buffer_capacity(0).build(CountingWriter { data: Vec::new(), writes: 0 }).unwrap());
+    let buffered = write(PcapWriter::with_header(header, CountingWriter { data: Vec::new(), writes: 0 }).unwrap());
+
+    assert_eq!(unbuffered.data, &DATA[..]);
+    assert_eq!(buffered.data, &DATA[..]);
+    assert_eq!(unbuffered.writes, 1 + 2 * 10);
+    assert_eq!(buffered.writes, 1);
+
+    // Nothing reaches the underlying writer before a flush
+    let mut pcap_writer = PcapWriter::with_buffer_capacity(1024, Vec::new()).unwrap();
+    pcap_writer.write(0, 0, &[0u8; 10]).unwrap();
+    assert!(pcap_writer.get_ref().is_empty());
+    pcap_writer.flush().unwrap();
+    assert_eq!(pcap_writer.get_ref().len(), 24 + 16 + 10);
+
+    // The snaplen fixup writes the buffered bytes before seeking
+    let mut pcap_writer = PcapWriter::with_buffer_capacity(1024, Cursor::new(Vec::new())).unwrap();
+    pcap_writer.write(0, 0, &[0u8; 10]).unwrap();
+    let out = pcap_writer.finalize_with_snaplen_fixup().unwrap().into_inner();
+    assert_eq!(PcapReader::new(&out[..]).unwrap().header.snaplen, 10);
+    assert_eq!(out.len(), 24 + 16 + 10);
+}
+
+#[test]
+fn write_ts_resolution() {
+
+    let data = [0u8; 10];
+    let mut nano_writer = PcapWriterBuilder::new().ts_resolution(TsResolution::NanoSecond).build(Vec::new()).unwrap();
+    let mut micro_writer = PcapWriter::new(Vec::new()).unwrap();
+
+    // Packet::new uses microseconds, it is converted to the resolution of the file
+    let packet = Packet::new(1, 123_456, 10, &data);
+    nano_writer.write_packet(&packet).unwrap();
+    micro_writer.write_packet(&packet).unwrap();
+
+    let mut nano_packet = packet.clone();
+    nano_packet.header.ts_resolution = TsResolution::NanoSecond;
+    nano_packet.header.ts_usec = 123_456_789;
+    nano_writer.write_packet(&nano_packet).unwrap();
+    micro_writer.write_packet(&nano_packet).unwrap();
+
+    let nanos: Vec<_> = PcapReader::new(&nano_writer.into_writer().unwrap()[..]).unwrap()
+        .map(|pcap| pcap.unwrap().header.ts_nsec())
+        .collect();
+    assert_eq!(nanos, vec![123_456_000, 123_456_789]);
+
+    let nanos: Vec<_> = PcapReader::new(&micro_writer.into_writer().unwrap()[..]).unwrap()
+        .map(|pcap| pcap.unwrap().header.ts_nsec())
+        .collect();
+    assert_eq!(nanos, vec![123_456_000, 123_456_000]);
+
+    // Out of range fractions
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    assert!(pcap_writer.write(0, 1_000_000, &data).is_err());
+    nano_packet.header.ts_usec = 1_000_000_000;
+    assert!(pcap_writer.write_packet(&nano_packet).is_err());
+}
+
+#[test]
+fn write_nanos() {
+
+    let mut pcap_writer = PcapWriter::new_nanos(Vec::new()).unwrap();
+    pcap_writer.write(1, 999_999_999, &[0u8; 4]).unwrap();
+    pcap_writer.write(2, 1, &[0u8; 4]).unwrap();
+    let out = pcap_writer.into_writer().unwrap();
+
+    assert_eq!(&out[..4], &[0xa1, 0xb2, 0x3c, 0x4d]);
+
+    let pcap_reader = PcapReader::new(&out[..]).unwrap();
+    assert_eq!(pcap_reader.ts_resolution(), TsResolution::NanoSecond);
+
+    let ts: Vec<_> = pcap_reader.map(|pcap| pcap.unwrap().duration_since_epoch()).collect();
+    assert_eq!(ts, vec![Duration::new(1, 999_999_999), Duration::new(2, 1)]);
+}
+
+#[test]
+fn magic_matrix() {
+
+    let expected: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+
+    let formats = [
+        (Endianness::Big, TsResolution::MicroSecond, [0xa1, 0xb2, 0xc3, 0xd4]),
+        (Endianness::Big, TsResolution::NanoSecond, [0xa1, 0xb2, 0x3c, 0x4d]),
+        (Endianness::Little, TsResolution::MicroSecond, [0xd4, 0xc3, 0xb2, 0xa1]),
+        (Endianness::Little, TsResolution::NanoSecond, [0x4d, 0x3c, 0xb2, 0xa1])
+    ];
+
+    // The same capture written with each magic number is read back identically
+    for &(endianness, ts_resolution, magic) in &formats {
+
+        let mut pcap_writer = PcapWriterBuilder::new().endianness(endianness).ts_resolution(ts_resolution).build(Vec::new()).unwrap();
+        pcap_writer.write_all(expected.iter().cloned()).unwrap();
+        let out = pcap_writer.into_writer().unwrap();
+        assert_eq!(&out[..4], &magic);
+
+        let pcap_reader = PcapReader::new(&out[..]).unwrap();
+        assert_eq!(pcap_reader.header.endianness(), endianness);
+        assert_eq!(pcap_reader.header.ts_resolution(), ts_resolution);
+
+        let packets: Vec<_> = pcap_reader.map(|pcap| pcap.unwrap()).collect();
+        assert_eq!(packets.iter().map(|packet| packet.timestamp()).collect::<Vec<_>>(), expected.iter().map(|packet| packet.timestamp()).collect::<Vec<_>>());
+        assert!(packets.iter().zip(&expected).all(|(packet, expected)| packet.data == expected.data));
+    }
+}
+
+#[test]
+fn diff_files() {
+
+    let packets: Vec<_> = PcapReader::new(&DATA[..]).unwrap().map(|pcap| pcap.unwrap()).collect();
+    assert_eq!(diff(PcapReader::new(&DATA[..]).unwrap(), PcapReader::new(&DATA[..]).unwrap()).count(), 0);
+
+    // Packet 1 resized, packet 3 modified, last packet dropped
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    for (i, packet) in packets[..9].iter().enumerate() {
+
+        let mut packet = packet.clone();
+        match i {
+            1 => {
+                packet.data.to_mut().push(0);
+                packet.header.orig_len += 1;
+            },
+            3 => packet.data.to_mut()[0] ^= 1,
+            _ => {}
+        }
+        pcap_writer.write_packet(&packet).unwrap();
+    }
+    let out = pcap_writer.into_writer().unwrap();
+
+    let entries: Vec<_> = diff(PcapReader::new(&DATA[..]).unwrap(), PcapReader::new(&out[..]).unwrap()).map(|entry| entry.unwrap()).collect();
+    let len = packets[1].data.len();
+    assert_eq!(entries, vec![
+        DiffEntry { index: 1, reason: DiffReason::LengthMismatch(len, len + 1) },
+        DiffEntry { index: 3, reason: DiffReason::PayloadMismatch },
+        DiffEntry { index: 9, reason: DiffReason::OnlyInA }
+    ]);
+
+    let entries: Vec<_> = diff(PcapReader::new(&out[..]).unwrap(), PcapReader::new(&DATA[..]).unwrap()).map(|entry| entry.unwrap()).collect();
+    assert_eq!(entries.last().unwrap().reason, DiffReason::OnlyInB);
+
+    // A read error ends the comparison
+    let mut entries = diff(PcapReader::new(&DATA[..]).unwrap(), PcapReader::new(&DATA[..DATA.len() - 3]).unwrap());
+    assert!(entries.next().unwrap().is_err());
+    assert!(entries.next().is_none());
+}
+
+#[test]
+fn validate_file() {
+
+    let report = validate(&DATA[..]).unwrap();
+    assert!(report.is_valid());
+    assert_eq!(report.packet_count, 10);
+    assert_eq!(report.total_bytes, DATA.len() as u64);
+
+    let report = validate(&DATA[..DATA.len() - 3]).unwrap();
+    assert!(!report.clean_end);
+    assert_eq!(report.packet_count, 9);
+    assert_eq!(report.total_bytes, DATA.len() as u64 - 3);
+    assert!(report.first_anomaly.is_some());
+
+    // A packet longer than the snaplen is reported, the validation goes on
+    let mut pcap_writer = PcapWriter::new(Vec::new()).unwrap();
+    pcap_writer.write(0, 0, &[0u8; 10]).unwrap();
+    pcap_writer.write(0, 0, &[0u8; 30]).unwrap();
+    pcap_writer.write(0, 0, &[0u8; 10]).unwrap();
+    let mut out = pcap_writer.into_writer().unwrap();
+
+    // BigEndian snaplen of the global header
+    out[16..20].copy_from_slice(&[0, 0, 0, 20]);
+
+    let report = validate(&out[..]).unwrap();
+    assert!(report.clean_end);
+    assert!(!report.is_valid());
+    assert_eq!(report.packet_count, 3);
+    assert_eq!(report.first_anomaly.unwrap().offset, 24 + 16 + 10);
+
+    assert!(validate(&DATA[..10]).is_err());
+}